@@ -1,6 +1,8 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use serde::{Deserialize, Serialize};
 
+use crate::api::{Column, RowKey, Timestamp};
+
 /// Represents the type of aggregation to perform on a column
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum AggregationType {
@@ -9,8 +11,88 @@ pub enum AggregationType {
     Average,
     Min,
     Max,
+    /// Numeric maximum minus numeric minimum
+    Range,
+    /// Smallest value compared as a number, e.g. b"9" is less than b"10"
+    MinNumeric,
+    /// Largest value compared as a number
+    MaxNumeric,
+    /// Middle value of the numbers, or the mean of the two middle ones
+    Median,
+    /// Population standard deviation of the numbers
+    StdDev,
+    /// Value with the earliest timestamp
+    First,
+    /// Value with the latest timestamp
+    Last,
+    /// The p-th quantile of the numbers, p between 0.0 and 1.0, interpolating linearly
+    /// between the two nearest values. Percentile(0.5) is the median.
+    Percentile(f64),
+}
+
+/// How the bytes of a column's values encode a number.
+/// Used by every aggregation except Count, Min, Max, First and Last to decode values before combining them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ValueEncoding {
+    /// A decimal number as UTF-8 text, e.g. b"42" or b"3.5".
+    #[default]
+    Utf8,
+    /// An 8-byte big-endian signed integer.
+    BeI64,
+    /// An 8-byte little-endian IEEE 754 double.
+    LeF64,
+}
+
+/// A numeric value decoded from a cell.
+enum Number {
+    Int(i64),
+    Float(f64),
+}
+
+impl Number {
+    fn as_f64(&self) -> f64 {
+        match self {
+            Number::Int(n) => *n as f64,
+            Number::Float(n) => *n,
+        }
+    }
+}
+
+impl ValueEncoding {
+    /// Decode a cell value into a number according to this encoding.
+    fn decode(&self, value: &[u8]) -> Result<Number, &'static str> {
+        match self {
+            ValueEncoding::Utf8 => {
+                let value_str = std::str::from_utf8(value)
+                    .map_err(|_| "Invalid UTF-8 in value")?;
+
+                if let Ok(num) = value_str.parse::<i64>() {
+                    Ok(Number::Int(num))
+                } else if let Ok(num) = value_str.parse::<f64>() {
+                    Ok(Number::Float(num))
+                } else {
+                    Err("Non-numeric value found")
+                }
+            },
+            ValueEncoding::BeI64 => {
+                let bytes: [u8; 8] = value.try_into()
+                    .map_err(|_| "Expected an 8-byte big-endian i64 value")?;
+                Ok(Number::Int(i64::from_be_bytes(bytes)))
+            },
+            ValueEncoding::LeF64 => {
+                let bytes: [u8; 8] = value.try_into()
+                    .map_err(|_| "Expected an 8-byte little-endian f64 value")?;
+                Ok(Number::Float(f64::from_le_bytes(bytes)))
+            },
+        }
+    }
 }
 
+/// The timestamps, per column, of the versions that are native counters
+/// (CellValue::Counter). Their values are 8-byte big-endian integers whatever the
+/// column's encoding says.
+pub(crate) type CounterVersions = BTreeMap<Column, BTreeSet<Timestamp>>;
+
 /// Represents an aggregation to be performed on a specific column
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Aggregation {
@@ -18,6 +100,9 @@ pub struct Aggregation {
     pub column: Vec<u8>,
     /// The type of aggregation to perform
     pub aggregation_type: AggregationType,
+    /// How the column's values encode numbers (not used by Count, Min, Max, First and Last)
+    #[serde(default)]
+    pub encoding: ValueEncoding,
 }
 
 /// Result of an aggregation operation
@@ -29,6 +114,16 @@ pub enum AggregationResult {
     Average(f64),
     Min(Vec<u8>),
     Max(Vec<u8>),
+    Range(f64),
+    /// The numeric minimum, together with the cell value it was decoded from
+    MinNumeric { value: f64, raw: Vec<u8> },
+    /// The numeric maximum, together with the cell value it was decoded from
+    MaxNumeric { value: f64, raw: Vec<u8> },
+    Median(f64),
+    StdDev(f64),
+    First(Vec<u8>),
+    Last(Vec<u8>),
+    Percentile { p: f64, value: f64 },
     Error(String),
 }
 
@@ -42,36 +137,131 @@ impl AggregationResult {
             AggregationResult::Average(avg) => format!("{}", avg),
             AggregationResult::Min(min) => format!("{:?}", min),
             AggregationResult::Max(max) => format!("{:?}", max),
+            AggregationResult::Range(range) => format!("{}", range),
+            AggregationResult::MinNumeric { value, .. } => format!("{}", value),
+            AggregationResult::MaxNumeric { value, .. } => format!("{}", value),
+            AggregationResult::Median(median) => format!("{}", median),
+            AggregationResult::StdDev(std_dev) => format!("{}", std_dev),
+            AggregationResult::First(first) => format!("{:?}", first),
+            AggregationResult::Last(last) => format!("{:?}", last),
+            AggregationResult::Percentile { value, .. } => format!("{}", value),
             AggregationResult::Error(err) => format!("Error: {}", err),
         }
     }
+
+    /// The result as a single f64, whatever numeric variant it came in: Sum and SumFloat
+    /// read the same way, so callers don't have to care whether a column held any decimals.
+    /// None for Min, Max, First, Last and Error, which are not numbers.
+    pub fn to_f64(&self) -> Option<f64> {
+        match self {
+            AggregationResult::Count(count) => Some(*count as f64),
+            AggregationResult::Sum(sum) => Some(*sum as f64),
+            AggregationResult::SumFloat(sum) => Some(*sum),
+            AggregationResult::Average(avg) => Some(*avg),
+            AggregationResult::Range(range) => Some(*range),
+            AggregationResult::MinNumeric { value, .. } => Some(*value),
+            AggregationResult::MaxNumeric { value, .. } => Some(*value),
+            AggregationResult::Median(median) => Some(*median),
+            AggregationResult::StdDev(std_dev) => Some(*std_dev),
+            AggregationResult::Percentile { value, .. } => Some(*value),
+            AggregationResult::Min(_)
+            | AggregationResult::Max(_)
+            | AggregationResult::First(_)
+            | AggregationResult::Last(_)
+            | AggregationResult::Error(_) => None,
+        }
+    }
+}
+
+/// Per-row aggregation results over a row range, as returned by
+/// `ColumnFamily::aggregate_range_with_limit`.
+#[derive(Debug, Clone, Default)]
+pub struct RangeAggregation {
+    /// Results for each row with at least one aggregated column, in row order
+    pub groups: BTreeMap<RowKey, BTreeMap<Column, AggregationResult>>,
+    /// True if the range held more rows with results than the limit allowed
+    pub truncated: bool,
 }
 
 /// Represents a set of aggregations to be performed on query results
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AggregationSet {
     pub aggregations: Vec<Aggregation>,
+    /// Aggregation applied to every column of the data that has no aggregation of its own
+    #[serde(default)]
+    pub all_columns: Option<AggregationType>,
 }
 
 impl AggregationSet {
     pub fn new() -> Self {
         AggregationSet {
             aggregations: Vec::new(),
+            all_columns: None,
         }
     }
 
     pub fn add_aggregation(&mut self, column: Vec<u8>, aggregation_type: AggregationType) -> &mut Self {
+        self.add_encoded_aggregation(column, aggregation_type, ValueEncoding::Utf8)
+    }
+
+    /// Add an aggregation over a column whose values are stored with the given encoding,
+    /// e.g. binary counters written as big-endian i64.
+    pub fn add_encoded_aggregation(
+        &mut self,
+        column: Vec<u8>,
+        aggregation_type: AggregationType,
+        encoding: ValueEncoding,
+    ) -> &mut Self {
         self.aggregations.push(Aggregation {
             column,
             aggregation_type,
+            encoding,
         });
         self
     }
 
+    /// Also apply aggregation_type to every column present in the data that has no
+    /// aggregation of its own, giving a result per column without naming them up front.
+    /// Values are decoded as UTF-8 text.
+    pub fn apply_all(&mut self, aggregation_type: AggregationType) -> &mut Self {
+        self.all_columns = Some(aggregation_type);
+        self
+    }
+
     pub fn apply(&self, values: &BTreeMap<Vec<u8>, Vec<(u64, Vec<u8>)>>) -> BTreeMap<Vec<u8>, AggregationResult> {
+        self.apply_with_counters(values, &CounterVersions::new())
+    }
+
+    /// Like apply, but the versions listed in counters are read as native counters rather
+    /// than with the aggregation's encoding, so counters sum as integers without parsing.
+    pub(crate) fn apply_with_counters(
+        &self,
+        values: &BTreeMap<Vec<u8>, Vec<(u64, Vec<u8>)>>,
+        counters: &CounterVersions,
+    ) -> BTreeMap<Vec<u8>, AggregationResult> {
         let mut results = BTreeMap::new();
 
-        for aggregation in &self.aggregations {
+        let discovered: Vec<Aggregation> = match &self.all_columns {
+            Some(aggregation_type) => values.keys()
+                .filter(|column| !self.aggregations.iter().any(|a| &a.column == *column))
+                .map(|column| Aggregation {
+                    column: column.clone(),
+                    aggregation_type: aggregation_type.clone(),
+                    encoding: ValueEncoding::Utf8,
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+
+        for aggregation in self.aggregations.iter().chain(&discovered) {
+            let column_counters = counters.get(&aggregation.column);
+            let decode = |ts: &u64, value: &[u8]| {
+                if column_counters.is_some_and(|timestamps| timestamps.contains(ts)) {
+                    ValueEncoding::BeI64.decode(value)
+                } else {
+                    aggregation.encoding.decode(value)
+                }
+            };
             let result = match values.get(&aggregation.column) {
                 Some(column_values) => {
                     match aggregation.aggregation_type {
@@ -79,28 +269,21 @@ impl AggregationSet {
                             AggregationResult::Count(column_values.len() as u64)
                         },
                         AggregationType::Sum => {
-                            let result = column_values.iter()
-                                .try_fold((0i64, 0.0f64, false), |(sum_i64, sum_f64, is_float), (_, value)| {
-                                    let value_str = std::str::from_utf8(value)
-                                        .map_err(|_| "Invalid UTF-8 in value")?;
-
-                                    if let Ok(num) = value_str.parse::<i64>() {
-                                        Ok((sum_i64 + num, sum_f64, is_float))
-                                    } else if let Ok(num) = value_str.parse::<f64>() {
-                                        Ok((sum_i64, sum_f64 + num, true))
-                                    } else {
-                                        Err("Non-numeric value found")
-                                    }
+                            // The sum stays an integer until the first float, which promotes the
+                            // running total so far into a float total that every later value joins.
+                            let result: Result<(i64, Option<f64>), &'static str> = column_values.iter()
+                                .try_fold((0i64, None), |(sum_i64, sum_f64), (ts, value)| {
+                                    let num = decode(ts, value)?;
+                                    Ok(match (sum_f64, num) {
+                                        (None, Number::Int(num)) => (sum_i64 + num, None),
+                                        (None, Number::Float(num)) => (0, Some(sum_i64 as f64 + num)),
+                                        (Some(sum), num) => (0, Some(sum + num.as_f64())),
+                                    })
                                 });
 
                             match result {
-                                Ok((sum_i64, sum_f64, is_float)) => {
-                                    if is_float {
-                                        AggregationResult::SumFloat(sum_f64)
-                                    } else {
-                                        AggregationResult::Sum(sum_i64)
-                                    }
-                                },
+                                Ok((sum_i64, None)) => AggregationResult::Sum(sum_i64),
+                                Ok((_, Some(sum_f64))) => AggregationResult::SumFloat(sum_f64),
                                 Err(err) => {
                                     return BTreeMap::from([(
                                         aggregation.column.clone(),
@@ -115,11 +298,7 @@ impl AggregationSet {
                             } else {
                                 let result: Result<(f64, f64, Vec<(&u64, f64)>), &'static str> = column_values.iter()
                                     .try_fold((0.0, 0.0, Vec::new()), |(sum, count, mut debug_values), (ts, value)| {
-                                        let value_str = std::str::from_utf8(value)
-                                            .map_err(|_| "Invalid UTF-8 in value")?;
-
-                                        let num = value_str.parse::<f64>()
-                                            .map_err(|_| "Non-numeric value found")?;
+                                        let num = decode(ts, value)?.as_f64();
 
                                         debug_values.push((ts, num));
 
@@ -163,6 +342,103 @@ impl AggregationSet {
                                 AggregationResult::Max(max_value)
                             }
                         },
+                        AggregationType::Range => {
+                            if column_values.is_empty() {
+                                AggregationResult::Error("No values to find range".to_string())
+                            } else {
+                                let result: Result<(f64, f64), &'static str> = column_values.iter()
+                                    .try_fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), (ts, value)| {
+                                        let num = decode(ts, value)?.as_f64();
+                                        Ok((min.min(num), max.max(num)))
+                                    });
+
+                                match result {
+                                    Ok((min, max)) => AggregationResult::Range(max - min),
+                                    Err(err) => {
+                                        return BTreeMap::from([(
+                                            aggregation.column.clone(),
+                                            AggregationResult::Error(err.to_string())
+                                        )]);
+                                    }
+                                }
+                            }
+                        },
+                        AggregationType::MinNumeric | AggregationType::MaxNumeric => {
+                            let want_max = matches!(aggregation.aggregation_type, AggregationType::MaxNumeric);
+                            let result: Result<Option<(f64, &Vec<u8>)>, &'static str> = column_values.iter()
+                                .try_fold(None, |best: Option<(f64, &Vec<u8>)>, (ts, value)| {
+                                    let num = decode(ts, value)?.as_f64();
+                                    let better = best.is_none_or(|(best_num, _)| {
+                                        if want_max { num > best_num } else { num < best_num }
+                                    });
+                                    Ok(if better { Some((num, value)) } else { best })
+                                });
+
+                            match result {
+                                Ok(Some((value, raw))) if want_max => {
+                                    AggregationResult::MaxNumeric { value, raw: raw.clone() }
+                                },
+                                Ok(Some((value, raw))) => {
+                                    AggregationResult::MinNumeric { value, raw: raw.clone() }
+                                },
+                                Ok(None) => AggregationResult::Error("No values to compare".to_string()),
+                                Err(err) => {
+                                    return BTreeMap::from([(
+                                        aggregation.column.clone(),
+                                        AggregationResult::Error(err.to_string())
+                                    )]);
+                                }
+                            }
+                        },
+                        AggregationType::First => {
+                            match column_values.iter().min_by_key(|(ts, _)| *ts) {
+                                Some((_, value)) => AggregationResult::First(value.clone()),
+                                None => AggregationResult::Error("No values to find first".to_string()),
+                            }
+                        },
+                        AggregationType::Last => {
+                            match column_values.iter().max_by_key(|(ts, _)| *ts) {
+                                Some((_, value)) => AggregationResult::Last(value.clone()),
+                                None => AggregationResult::Error("No values to find last".to_string()),
+                            }
+                        },
+                        AggregationType::Percentile(p) if !(0.0..=1.0).contains(&p) => {
+                            AggregationResult::Error(format!("Percentile must be between 0 and 1, got {}", p))
+                        },
+                        AggregationType::Median | AggregationType::StdDev | AggregationType::Percentile(_) => {
+                            if column_values.is_empty() {
+                                AggregationResult::Error("No values to aggregate".to_string())
+                            } else {
+                                let result: Result<Vec<f64>, &'static str> = column_values.iter()
+                                    .map(|(ts, value)| Ok(decode(ts, value)?.as_f64()))
+                                    .collect();
+
+                                match result {
+                                    Ok(mut nums) => match aggregation.aggregation_type {
+                                        AggregationType::Percentile(p) => {
+                                            nums.sort_by(f64::total_cmp);
+                                            AggregationResult::Percentile { p, value: percentile(&nums, p) }
+                                        },
+                                        AggregationType::Median => {
+                                            nums.sort_by(f64::total_cmp);
+                                            AggregationResult::Median(percentile(&nums, 0.5))
+                                        },
+                                        _ => {
+                                            let count = nums.len() as f64;
+                                            let mean = nums.iter().sum::<f64>() / count;
+                                            let variance = nums.iter().map(|n| (n - mean).powi(2)).sum::<f64>() / count;
+                                            AggregationResult::StdDev(variance.sqrt())
+                                        },
+                                    },
+                                    Err(err) => {
+                                        return BTreeMap::from([(
+                                            aggregation.column.clone(),
+                                            AggregationResult::Error(err.to_string())
+                                        )]);
+                                    }
+                                }
+                            }
+                        },
                     }
                 },
                 None => AggregationResult::Error(format!("Column not found: {:?}", aggregation.column)),
@@ -175,6 +451,15 @@ impl AggregationSet {
     }
 }
 
+/// The p-th quantile of sorted, which must not be empty: the value at rank p * (len - 1),
+/// interpolated linearly when that rank falls between two values.
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let rank = p * (sorted.len() - 1) as f64;
+    let below = rank.floor() as usize;
+    let above = rank.ceil() as usize;
+    sorted[below] + (sorted[above] - sorted[below]) * (rank - below as f64)
+}
+
 impl Default for AggregationSet {
     fn default() -> Self {
         Self::new()
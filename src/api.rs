@@ -1,23 +1,54 @@
 use std::{
-    collections::{BTreeMap, HashMap},
-    fs,
-    io::Result as IoResult,
+    collections::{BTreeMap, BTreeSet, HashMap},
+    io::{Read, Result as IoResult, Write},
     path::{Path, PathBuf},
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex, MutexGuard, Weak,
+    },
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
+
+/// Default for ColumnFamilyOptions::compaction_interval.
+const BACKGROUND_COMPACTION_INTERVAL: Duration = Duration::from_secs(60);
+/// Name of the file holding a column family's saved ColumnFamilyOptions.
+const OPTIONS_FILE: &str = "options.json";
+/// How often the background compaction thread checks whether it should exit.
+const BACKGROUND_POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// Most column families Table::compact_all compacts at the same time.
+const MAX_PARALLEL_COMPACTIONS: usize = 4;
+/// Most cells ColumnFamily::get_cached keeps at once; an arbitrary one is evicted past that.
+const MAX_CACHED_READS: usize = 10_000;
 use serde::{Deserialize, Serialize};
 
 use crate::memstore::{MemStore, WalEntry};
-use crate::storage::{SSTable, SSTableReader};
+use crate::storage::{Dump, SSTable, SSTableReader, DEFAULT_WRITE_BUFFER_BYTES};
 use crate::filter::{Filter, FilterSet};
-use crate::aggregation::{AggregationSet, AggregationResult};
+use crate::aggregation::{AggregationSet, AggregationResult, CounterVersions, RangeAggregation};
+use crate::events::{CompactionInfo, EventListener, FlushInfo};
+use crate::filesystem::{FileSystem, MemFileSystem, OsFileSystem};
 
 pub type RowKey = Vec<u8>;
 pub type Column = Vec<u8>;
 pub type Timestamp = u64;
 
+/// Cells read by ColumnFamily::get_cached: when each was read and what was found.
+type ReadCache = HashMap<(RowKey, Column), (Instant, Option<Vec<u8>>)>;
+
+/// (reads, writes) counted by ColumnFamily::record_access, per row-key prefix.
+type AccessStats = HashMap<Vec<u8>, (u64, u64)>;
+
+/// The live versions of each column of one row, newest first.
+type RowVersions = BTreeMap<Column, Vec<(Timestamp, Vec<u8>)>>;
+
+/// Every stored version of each column of one row, tombstones included.
+type RowCells = BTreeMap<Column, Vec<(Timestamp, CellValue)>>;
+
+/// One page of ColumnFamily::scan_latest_after: the (row, column, timestamp, value) of each
+/// cell, and the last row examined.
+type LatestPage = (Vec<(RowKey, Column, Timestamp, Vec<u8>)>, Option<RowKey>);
+
 /// A Get operation that can be used to retrieve data for a specific row.
 /// Similar to the HBase/Java Get API.
 pub struct Get {
@@ -25,6 +56,8 @@ pub struct Get {
     row: RowKey,
     /// Maximum number of versions to retrieve per column
     max_versions: Option<usize>,
+    /// Retrieve every version, ignoring max_versions
+    all_versions: bool,
     /// Optional time range for filtering versions (start_time, end_time)
     time_range: Option<(Timestamp, Timestamp)>,
 }
@@ -35,6 +68,7 @@ impl Get {
         Get {
             row,
             max_versions: None,
+            all_versions: false,
             time_range: None,
         }
     }
@@ -42,6 +76,14 @@ impl Get {
     /// Set the maximum number of versions to retrieve.
     pub fn set_max_versions(&mut self, max_versions: usize) -> &mut Self {
         self.max_versions = Some(max_versions);
+        self.all_versions = false;
+        self
+    }
+
+    /// Retrieve every live version of each column instead of a bounded number.
+    pub fn set_all_versions(&mut self) -> &mut Self {
+        self.max_versions = None;
+        self.all_versions = true;
         self
     }
 
@@ -61,6 +103,21 @@ impl Get {
         self.max_versions
     }
 
+    /// Whether every version was requested with set_all_versions.
+    pub fn all_versions(&self) -> bool {
+        self.all_versions
+    }
+
+    /// Number of versions to return per column: unbounded for set_all_versions, and
+    /// default (the column family's default_get_versions) when none was set.
+    fn version_limit(&self, default: usize) -> usize {
+        if self.all_versions {
+            usize::MAX
+        } else {
+            self.max_versions.unwrap_or(default)
+        }
+    }
+
     /// Get the time range for filtering versions.
     pub fn time_range(&self) -> Option<(Timestamp, Timestamp)> {
         self.time_range
@@ -102,18 +159,73 @@ impl Put {
     }
 }
 
-/// A cell can either be a Put (with actual bytes) or a Delete marker with optional TTL.
+/// A cell can either be a Put (with actual bytes), a Delete marker with optional TTL,
+/// or a Counter maintained natively by ColumnFamily::increment.
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub enum CellValue {
     Put(Vec<u8>),
     Delete(Option<u64>),
+    /// Reads that return bytes see it as 8 big-endian bytes (ValueEncoding::BeI64).
+    Counter(i64),
+}
+
+/// Where ColumnFamily::get_with_source found a value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReadSource {
+    /// The in-memory MemStore (not yet flushed)
+    Memstore,
+    /// The SSTable file at this path
+    SSTable(PathBuf),
+}
+
+/// Which data a read consults, for ColumnFamily::get_with_consistency and
+/// get_versions_with_consistency.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReadConsistency {
+    /// The MemStore and every SSTable, exactly like get and get_versions
+    #[default]
+    Default,
+    /// Only the MemStore: fast, but blind to anything already flushed
+    MemstoreOnly,
+    /// Only the SSTables, ignoring writes that have not been flushed yet
+    SkipMemstore,
+    /// The data as it was at this timestamp: versions written later are ignored
+    SnapshotAt(Timestamp),
+}
+
+/// One SSTable of a column family, as listed by ColumnFamily::sstables.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SSTableMeta {
+    pub path: PathBuf,
+    /// Sequence number from the file name; higher numbers hold newer data
+    pub seq: u64,
+    pub entry_count: u32,
+    /// First and last row in the file, or None if it is empty or predates footers
+    pub key_range: Option<(RowKey, RowKey)>,
+    pub size_bytes: u64,
+}
+
+/// Row order of the results of ColumnFamily::scan_ordered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ScanOrder {
+    /// Lowest row key first
+    #[default]
+    Ascending,
+    /// Highest row key first
+    Descending,
 }
 
-/// Compaction type: minor (merge some SSTables) or major (merge all SSTables)
+/// Compaction type: minor (merge some SSTables), major (merge all SSTables) or
+/// cleanup (rewrite each SSTable on its own without its expired entries)
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CompactionType {
     Minor,
     Major,
+    /// Sweep every SSTable in place, dropping versions past the column family's ttl_ms or
+    /// options.max_age_ms and, with options.cleanup_tombstones, TTL tombstones that have
+    /// expired. Nothing is merged, so version limits and tombstones without a TTL, which
+    /// may hide versions in other files, are left alone. Files left empty are removed.
+    Cleanup,
 }
 
 /// Compaction options for controlling the compaction process
@@ -123,6 +235,20 @@ pub struct CompactionOptions {
     pub max_versions: Option<usize>,
     pub max_age_ms: Option<u64>,
     pub cleanup_tombstones: bool,
+    /// Split the output into several SSTables of roughly this many bytes each, covering
+    /// disjoint row ranges. A row is never split across files. None writes a single SSTable.
+    pub target_output_bytes: Option<u64>,
+    /// Version limits for individual columns. Columns not listed use max_versions.
+    pub max_versions_per_column: Option<BTreeMap<Column, usize>>,
+}
+
+impl CompactionOptions {
+    /// The number of versions to keep for column, if limited.
+    fn max_versions_for(&self, column: &[u8]) -> Option<usize> {
+        self.max_versions_per_column.as_ref()
+            .and_then(|limits| limits.get(column).copied())
+            .or(self.max_versions)
+    }
 }
 
 impl Default for CompactionOptions {
@@ -132,7 +258,235 @@ impl Default for CompactionOptions {
             max_versions: None,
             max_age_ms: None,
             cleanup_tombstones: true,
+            target_output_bytes: None,
+            max_versions_per_column: None,
+        }
+    }
+}
+
+/// What a compaction would do, as reported by ColumnFamily::compaction_plan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompactionPlan {
+    /// The SSTables that would be merged; empty if the compaction would do nothing
+    pub inputs: Vec<PathBuf>,
+    /// Combined size of the inputs in bytes
+    pub input_bytes: u64,
+    /// Number of entries (versions and tombstones) in the inputs
+    pub input_entries: usize,
+    /// Number of entries that would be written
+    pub output_entries: usize,
+    /// Combined size of the SSTables that would be written, in bytes
+    pub estimated_output_bytes: u64,
+    /// Versions that would be dropped by max_versions, max_versions_per_column or max_age_ms
+    pub dropped_versions: usize,
+    /// Tombstones that would be cleaned up
+    pub dropped_tombstones: usize,
+}
+
+/// Per-ColumnFamily configuration, supplied when the column family is created.
+/// Build one with ColumnFamilyOptions::builder() or start from Default.
+///
+/// Options are saved to options.json in the column family directory, so a column family
+/// reopened without explicit options keeps its configured behaviour. Fields missing from
+/// an older options.json take their defaults.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ColumnFamilyOptions {
+    /// Flush the MemStore once it holds more than this many entries.
+    pub flush_threshold: usize,
+    /// How often the background thread runs a minor compaction.
+    pub compaction_interval: Duration,
+    /// Merge SSTables in the background when a single read has to consult too many of them.
+    pub read_triggered_compaction: bool,
+    /// Number of SSTables a read may consult for one key before a read-triggered
+    /// compaction is scheduled.
+    pub read_compaction_threshold: usize,
+    /// Flush the MemStore as soon as any single cell holds more than this many versions
+    /// in memory, so one hot cell can't fill the MemStore before the entry-count trigger.
+    /// Versions are flushed to an SSTable, never dropped.
+    pub max_versions_in_memstore_per_cell: Option<usize>,
+    /// Versions older than this many milliseconds are expired: reads no longer return them.
+    pub ttl_ms: Option<u64>,
+    /// Size in bytes of the buffer flushes and compactions write SSTables through.
+    /// Bigger buffers make fewer write calls on large flushes; smaller ones save memory.
+    pub sstable_write_buffer_bytes: usize,
+    /// Flush automatically when flush_threshold or max_versions_in_memstore_per_cell is
+    /// exceeded. When false the MemStore only shrinks on an explicit flush, however large
+    /// it gets, so callers must flush often enough to bound its memory (and the WAL replayed
+    /// on reopen).
+    pub auto_flush: bool,
+    /// fsync every SSTable written by a flush or compaction, and the column family directory
+    /// after it, before the WAL is reset or the compaction's inputs are removed, so a crash
+    /// can't lose data the file holds. Turning this off makes flushes and compactions cheaper,
+    /// at the risk of losing a just-written SSTable (and, once the WAL has been reset, its
+    /// data) on a power failure or OS crash.
+    pub sync_sstables: bool,
+    /// Count reads and writes per row-key prefix of this many bytes, for
+    /// ColumnFamily::access_heatmap. None, the default, turns the accounting off.
+    pub access_stats_prefix_len: Option<usize>,
+    /// Versions per column returned by execute_get and execute_get_column when the Get
+    /// sets no max_versions of its own.
+    pub default_get_versions: usize,
+}
+
+impl Default for ColumnFamilyOptions {
+    fn default() -> Self {
+        ColumnFamilyOptions {
+            flush_threshold: 10_000,
+            compaction_interval: BACKGROUND_COMPACTION_INTERVAL,
+            read_triggered_compaction: false,
+            read_compaction_threshold: 8,
+            max_versions_in_memstore_per_cell: None,
+            ttl_ms: None,
+            sstable_write_buffer_bytes: DEFAULT_WRITE_BUFFER_BYTES,
+            auto_flush: true,
+            sync_sstables: true,
+            access_stats_prefix_len: None,
+            default_get_versions: 1,
+        }
+    }
+}
+
+impl ColumnFamilyOptions {
+    /// Start building options from the defaults.
+    pub fn builder() -> ColumnFamilyOptionsBuilder {
+        ColumnFamilyOptionsBuilder::default()
+    }
+
+    /// Reject settings that can't work, such as a zero flush threshold.
+    pub fn validate(&self) -> IoResult<()> {
+        let invalid = |msg: &str| Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, msg.to_string()));
+        if self.flush_threshold == 0 {
+            return invalid("flush_threshold must be greater than 0");
+        }
+        if self.compaction_interval.is_zero() {
+            return invalid("compaction_interval must be greater than 0");
+        }
+        if self.read_compaction_threshold == 0 {
+            return invalid("read_compaction_threshold must be greater than 0");
+        }
+        if self.max_versions_in_memstore_per_cell == Some(0) {
+            return invalid("max_versions_in_memstore_per_cell must be greater than 0");
+        }
+        if self.sstable_write_buffer_bytes == 0 {
+            return invalid("sstable_write_buffer_bytes must be greater than 0");
+        }
+        if self.default_get_versions == 0 {
+            return invalid("default_get_versions must be greater than 0");
+        }
+        if self.access_stats_prefix_len == Some(0) {
+            return invalid("access_stats_prefix_len must be greater than 0");
         }
+        Ok(())
+    }
+
+    /// Load the options saved in a column family directory, if any.
+    fn load(fs: &dyn FileSystem, cf_path: &Path) -> IoResult<Option<Self>> {
+        let path = cf_path.join(OPTIONS_FILE);
+        if !fs.exists(&path) {
+            return Ok(None);
+        }
+        let mut data = Vec::new();
+        fs.open(&path)?.read_to_end(&mut data)?;
+        let options: Self = serde_json::from_slice(&data).map_err(|e| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Invalid {}: {}", path.display(), e),
+            )
+        })?;
+        options.validate()?;
+        Ok(Some(options))
+    }
+
+    /// Save the options into a column family directory, replacing any previous file atomically.
+    fn save(&self, fs: &dyn FileSystem, cf_path: &Path) -> IoResult<()> {
+        let data = serde_json::to_vec_pretty(self)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        let tmp_path = cf_path.join(format!("{}.tmp", OPTIONS_FILE));
+        let mut tmp = fs.create(&tmp_path)?;
+        tmp.write_all(&data)?;
+        tmp.flush()?;
+        drop(tmp);
+        fs.rename(&tmp_path, &cf_path.join(OPTIONS_FILE))
+    }
+}
+
+/// Fluent builder for ColumnFamilyOptions. Unset options keep their defaults.
+#[derive(Debug, Clone, Default)]
+pub struct ColumnFamilyOptionsBuilder {
+    options: ColumnFamilyOptions,
+}
+
+impl ColumnFamilyOptionsBuilder {
+    /// Flush the MemStore once it holds more than this many entries.
+    pub fn flush_threshold(&mut self, flush_threshold: usize) -> &mut Self {
+        self.options.flush_threshold = flush_threshold;
+        self
+    }
+
+    /// Run the background minor compaction at this interval.
+    pub fn compaction_interval(&mut self, interval: Duration) -> &mut Self {
+        self.options.compaction_interval = interval;
+        self
+    }
+
+    /// Enable or disable read-triggered compaction.
+    pub fn read_triggered_compaction(&mut self, enabled: bool) -> &mut Self {
+        self.options.read_triggered_compaction = enabled;
+        self
+    }
+
+    /// Number of SSTables a read may consult before a read-triggered compaction is scheduled.
+    pub fn read_compaction_threshold(&mut self, threshold: usize) -> &mut Self {
+        self.options.read_compaction_threshold = threshold;
+        self
+    }
+
+    /// Flush as soon as a single cell holds more than this many versions in memory.
+    pub fn max_versions_in_memstore_per_cell(&mut self, max_versions: usize) -> &mut Self {
+        self.options.max_versions_in_memstore_per_cell = Some(max_versions);
+        self
+    }
+
+    /// Expire versions older than ttl_ms milliseconds.
+    pub fn ttl_ms(&mut self, ttl_ms: u64) -> &mut Self {
+        self.options.ttl_ms = Some(ttl_ms);
+        self
+    }
+
+    /// Write SSTables through a buffer of this many bytes.
+    pub fn sstable_write_buffer_bytes(&mut self, buffer_bytes: usize) -> &mut Self {
+        self.options.sstable_write_buffer_bytes = buffer_bytes;
+        self
+    }
+
+    /// Enable or disable the automatic size-triggered flush.
+    pub fn auto_flush(&mut self, enabled: bool) -> &mut Self {
+        self.options.auto_flush = enabled;
+        self
+    }
+
+    /// Enable or disable fsyncing SSTables after they are written.
+    pub fn sync_sstables(&mut self, enabled: bool) -> &mut Self {
+        self.options.sync_sstables = enabled;
+        self
+    }
+
+    /// Count reads and writes per row-key prefix of prefix_len bytes.
+    pub fn access_stats_prefix_len(&mut self, prefix_len: usize) -> &mut Self {
+        self.options.access_stats_prefix_len = Some(prefix_len);
+        self
+    }
+
+    /// Versions per column a Get returns when it sets no max_versions.
+    pub fn default_get_versions(&mut self, versions: usize) -> &mut Self {
+        self.options.default_get_versions = versions;
+        self
+    }
+
+    /// Finish building the options.
+    pub fn build(&self) -> ColumnFamilyOptions {
+        self.options.clone()
     }
 }
 
@@ -151,6 +505,33 @@ pub struct Entry {
     pub value: CellValue,
 }
 
+/// One cell version written by ColumnFamily::scan_to_writer.
+/// On the wire each record is [u32 big-endian: length][bincode(ScanRecord)].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ScanRecord {
+    pub row: RowKey,
+    pub column: Column,
+    pub timestamp: Timestamp,
+    pub value: Vec<u8>,
+}
+
+impl ScanRecord {
+    /// Read the next record written by scan_to_writer, or Ok(None) at the end of the stream.
+    pub fn read_from(reader: &mut impl Read) -> IoResult<Option<Self>> {
+        let mut len_buf = [0u8; 4];
+        match reader.read_exact(&mut len_buf) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        }
+        let mut buf = vec![0u8; u32::from_be_bytes(len_buf) as usize];
+        reader.read_exact(&mut buf)?;
+        bincode::deserialize(&buf)
+            .map(Some)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+}
+
 /// A single ColumnFamily inside a Table, with MVCC support and version filtering.
 ///
 /// - *MemStore*: in‐memory BTreeMap + WAL (append‐only).
@@ -163,44 +544,92 @@ pub struct ColumnFamily {
     path: PathBuf,
     memstore: Arc<Mutex<MemStore>>,
     sst_files: Arc<Mutex<Vec<PathBuf>>>,
+    options: ColumnFamilyOptions,
+    /// Sequence number for the next SSTable written by a flush or compaction.
+    next_sst_seq: Arc<AtomicU64>,
+    /// Held for the duration of a compaction so that two compactions never merge the same files.
+    compaction_lock: Arc<Mutex<()>>,
+    read_compaction_pending: Arc<AtomicBool>,
+    /// Set by close(); tells the background compaction thread to exit.
+    closed: Arc<AtomicBool>,
+    /// Shared by every user-held handle. The background thread only keeps a Weak to it,
+    /// so it can exit once the last handle is dropped.
+    handle: Arc<()>,
+    listeners: Arc<Mutex<Vec<Arc<dyn EventListener>>>>,
+    /// Set by open_read_only(); nothing in the column family directory may be created or changed.
+    read_only: bool,
+    /// Set by seal(); new writes are refused but flushes and compactions still run.
+    sealed: Arc<AtomicBool>,
+    /// Where the WAL, options and SSTables live.
+    fs: Arc<dyn FileSystem>,
+    /// Results of get_cached, with the time each was read from the store.
+    read_cache: Arc<Mutex<ReadCache>>,
+    /// Reads and writes per row-key prefix, kept when options.access_stats_prefix_len is set.
+    access_stats: Arc<Mutex<AccessStats>>,
+    /// The newest timestamp written through this column family. While the clock hasn't
+    /// passed it, a new write may need a timestamp past the cell's newest version.
+    newest_ts: Arc<AtomicU64>,
 }
 
 impl ColumnFamily {
+    /// Open (or create) a column family, using the options saved in its directory.
+    /// A new column family gets the default options, which are saved for next time.
     pub fn open(table_path: &Path, colfam_name: &str) -> IoResult<Self> {
+        Self::open_with_fs(table_path, colfam_name, Arc::new(OsFileSystem))
+    }
+
+    /// Like open, but keeps every file of the column family on fs instead of the local disk.
+    pub fn open_with_fs(table_path: &Path, colfam_name: &str, fs: Arc<dyn FileSystem>) -> IoResult<Self> {
         let cf_path = table_path.join(colfam_name);
-        fs::create_dir_all(&cf_path)?;
+        let options = ColumnFamilyOptions::load(&*fs, &cf_path)?.unwrap_or_default();
+        Self::open_in(fs, table_path, colfam_name, options)
+    }
 
-        let mem = MemStore::open(&cf_path.join("wal.log"))?;
+    /// Open (or create) a column family with the given options.
+    /// They replace any options saved in the column family directory and are saved in their place.
+    pub fn open_with_options(
+        table_path: &Path,
+        colfam_name: &str,
+        options: ColumnFamilyOptions,
+    ) -> IoResult<Self> {
+        Self::open_in(Arc::new(OsFileSystem), table_path, colfam_name, options)
+    }
 
-        let mut sst_files = fs::read_dir(&cf_path)?
-            .filter_map(|entry| {
-                entry.ok().and_then(|e| {
-                    e.path().extension()
-                        .and_then(|ext| ext.to_str())
-                        .filter(|ext| *ext == "sst")
-                        .map(|_| e.path())
-                })
-            })
-            .collect::<Vec<_>>();
-        sst_files.sort();
+    /// Open (or create) a column family on fs with the given options, saving them if they changed.
+    fn open_in(
+        fs: Arc<dyn FileSystem>,
+        table_path: &Path,
+        colfam_name: &str,
+        options: ColumnFamilyOptions,
+    ) -> IoResult<Self> {
+        options.validate()?;
+        let cf_path = table_path.join(colfam_name);
+        fs.create_dir_all(&cf_path)?;
+        if ColumnFamilyOptions::load(&*fs, &cf_path).ok().flatten().as_ref() != Some(&options) {
+            options.save(&*fs, &cf_path)?;
+        }
 
-        let cf = ColumnFamily {
-            name: colfam_name.to_string(),
-            path: cf_path.clone(),
-            memstore: Arc::new(Mutex::new(mem)),
-            sst_files: Arc::new(Mutex::new(sst_files)),
-        };
+        let mem = MemStore::open_with_fs(fs.clone(), cf_path.join("wal.log"))?;
+        let cf = Self::from_parts(fs, colfam_name, cf_path, mem, options, false)?;
 
         {
-            let cf_clone = cf.clone();
+            let user_handle: Weak<()> = Arc::downgrade(&cf.handle);
+            let mut cf_clone = cf.clone();
+            cf_clone.handle = Arc::new(());
             thread::spawn(move || {
+                let mut since_last_run = Duration::ZERO;
                 loop {
-                    thread::sleep(Duration::from_secs(60));
-                    if let Err(err) = cf_clone.compact() {
-                        eprintln!(
-                            "[ColumnFamily::compact] error in CF '{}': {:?}",
-                            cf_clone.name, err
-                        );
+                    thread::sleep(BACKGROUND_POLL_INTERVAL);
+                    if user_handle.strong_count() == 0 || !cf_clone.is_live() {
+                        return;
+                    }
+                    since_last_run += BACKGROUND_POLL_INTERVAL;
+                    if since_last_run < cf_clone.options.compaction_interval {
+                        continue;
+                    }
+                    since_last_run = Duration::ZERO;
+                    if !cf_clone.background_compaction() {
+                        return;
                     }
                 }
             });
@@ -209,29 +638,272 @@ impl ColumnFamily {
         Ok(cf)
     }
 
+    /// Open a column family without creating or writing anything on disk, e.g. from a
+    /// read-only filesystem. The directory must already exist. Reads are served from the
+    /// existing SSTables (and the WAL, if there is one); writes, flushes and compactions
+    /// fail with PermissionDenied, and no background compaction is started.
+    pub fn open_read_only(table_path: &Path, colfam_name: &str) -> IoResult<Self> {
+        Self::open_read_only_with_fs(table_path, colfam_name, Arc::new(OsFileSystem))
+    }
+
+    /// Like open_read_only, but reads every file of the column family from fs.
+    pub fn open_read_only_with_fs(table_path: &Path, colfam_name: &str, fs: Arc<dyn FileSystem>) -> IoResult<Self> {
+        let cf_path = table_path.join(colfam_name);
+        if !fs.is_dir(&cf_path) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("ColumnFamily directory {} does not exist", cf_path.display()),
+            ));
+        }
+        let options = ColumnFamilyOptions::load(&*fs, &cf_path)?.unwrap_or_default();
+        let mem = MemStore::open_read_only_with_fs(fs.clone(), cf_path.join("wal.log"))?;
+        Self::from_parts(fs, colfam_name, cf_path, mem, options, true)
+    }
+
+    /// Build a handle around an opened MemStore and the SSTables found in cf_path.
+    fn from_parts(
+        fs: Arc<dyn FileSystem>,
+        colfam_name: &str,
+        cf_path: PathBuf,
+        mem: MemStore,
+        options: ColumnFamilyOptions,
+        read_only: bool,
+    ) -> IoResult<Self> {
+        let mut sst_files = fs.read_dir(&cf_path)?
+            .into_iter()
+            .filter(|path| path.extension().and_then(|ext| ext.to_str()) == Some("sst"))
+            .collect::<Vec<_>>();
+        sst_files.sort();
+        let max_seq = sst_files.iter().filter_map(|path| sst_seq(path)).max().unwrap_or(0);
+
+        Ok(ColumnFamily {
+            name: colfam_name.to_string(),
+            path: cf_path,
+            memstore: Arc::new(Mutex::new(mem)),
+            sst_files: Arc::new(Mutex::new(sst_files)),
+            options,
+            next_sst_seq: Arc::new(AtomicU64::new(max_seq + 1)),
+            compaction_lock: Arc::new(Mutex::new(())),
+            read_compaction_pending: Arc::new(AtomicBool::new(false)),
+            closed: Arc::new(AtomicBool::new(false)),
+            handle: Arc::new(()),
+            listeners: Arc::new(Mutex::new(Vec::new())),
+            read_only,
+            sealed: Arc::new(AtomicBool::new(false)),
+            fs,
+            read_cache: Arc::new(Mutex::new(HashMap::new())),
+            access_stats: Arc::new(Mutex::new(HashMap::new())),
+            newest_ts: Arc::new(AtomicU64::new(0)),
+        })
+    }
+
+    /// Write entries to a new SSTable at path, using the configured write buffer.
+    /// With sync_sstables the file and its directory are synced before this returns.
+    fn write_sstable(&self, path: &Path, entries: &[Entry]) -> IoResult<()> {
+        SSTable::create_with_buffer(&*self.fs, path, entries, self.options.sstable_write_buffer_bytes)?;
+        if self.options.sync_sstables {
+            self.fs.sync_file(path)?;
+            self.sync_sstable_dir()?;
+        }
+        Ok(())
+    }
+
+    /// With sync_sstables, sync the column family directory so SSTables just created or
+    /// renamed in it are durable.
+    fn sync_sstable_dir(&self) -> IoResult<()> {
+        if self.options.sync_sstables {
+            self.fs.sync_dir(&self.path)?;
+        }
+        Ok(())
+    }
+
+    /// Open one of this column family's SSTables.
+    /// Errors keep their kind but name the file, so a single bad SSTable is easy to find.
+    fn open_sstable(&self, path: impl AsRef<Path>) -> IoResult<SSTableReader> {
+        let path = path.as_ref();
+        SSTableReader::open_with_fs(&*self.fs, path).map_err(|e| {
+            std::io::Error::new(e.kind(), format!("Failed to read SSTable {}: {}", path.display(), e))
+        })
+    }
+
+    /// Whether this column family was opened with open_read_only().
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Fail with PermissionDenied if this column family was opened read-only.
+    fn ensure_writable(&self) -> IoResult<()> {
+        if self.read_only {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                format!("ColumnFamily {} is read-only", self.name),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Register a listener for flush and compaction events on this column family.
+    /// It is shared by all clones of this handle.
+    pub fn add_listener(&self, listener: Arc<dyn EventListener>) {
+        self.listeners.lock().unwrap().push(listener);
+    }
+
+    /// Deliver a flush event to the registered listeners on a separate thread.
+    /// info is only built when there is someone to receive it.
+    fn notify_flush(&self, info: impl FnOnce() -> FlushInfo) {
+        let listeners = self.listeners.lock().unwrap().clone();
+        if listeners.is_empty() {
+            return;
+        }
+        let info = info();
+        thread::spawn(move || {
+            listeners.iter().for_each(|listener| listener.on_flush(info.clone()));
+        });
+    }
+
+    /// Deliver a compaction event to the registered listeners on a separate thread.
+    /// info is only built when there is someone to receive it.
+    fn notify_compaction(&self, info: impl FnOnce() -> CompactionInfo) {
+        let listeners = self.listeners.lock().unwrap().clone();
+        if listeners.is_empty() {
+            return;
+        }
+        let info = info();
+        thread::spawn(move || {
+            listeners.iter().for_each(|listener| listener.on_compaction(info.clone()));
+        });
+    }
+
+    /// The options this column family was opened with.
+    pub fn options(&self) -> &ColumnFamilyOptions {
+        &self.options
+    }
+
+    /// Stop background maintenance for this column family.
+    /// Reads and writes through existing handles keep working; only the periodic
+    /// compaction thread exits (within BACKGROUND_POLL_INTERVAL).
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+    }
+
+    /// Refuse all further writes to this column family: put, delete and the other write
+    /// operations fail with PermissionDenied. Reads, flushes of data already written and
+    /// compactions keep working. Applies to every clone of this handle; the seal is not
+    /// persisted, so reopening the column family makes it writable again.
+    pub fn seal(&self) {
+        self.sealed.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether seal() has been called on this column family.
+    pub fn is_sealed(&self) -> bool {
+        self.sealed.load(Ordering::SeqCst)
+    }
+
+    /// False once the column family has been closed or its directory has been removed.
+    fn is_live(&self) -> bool {
+        !self.closed.load(Ordering::SeqCst) && self.fs.exists(&self.path)
+    }
+
+    /// Run one periodic compaction. Returns false when the background thread should exit.
+    /// Failures caused by the column family being closed or its directory disappearing
+    /// (e.g. a dropped tempdir) end the thread quietly; any other error is logged.
+    fn background_compaction(&self) -> bool {
+        match self.compact() {
+            Ok(()) => true,
+            Err(_) if !self.is_live() => false,
+            Err(err) => {
+                eprintln!(
+                    "[ColumnFamily::compact] error in CF '{}': {:?}",
+                    self.name, err
+                );
+                true
+            }
+        }
+    }
+
     /// Write a new versioned cell (row, column) = value with a fresh timestamp.
     pub fn put(&self, row: RowKey, column: Column, value: Vec<u8>) -> IoResult<()> {
-        let ts = chrono::Utc::now().timestamp_millis() as u64;
+        let mut ms = self.memstore.lock().unwrap();
+        let ts = self.write_timestamp(&ms, [(&row, &column)])?;
         let entry = Entry {
             key: EntryKey { row, column, timestamp: ts },
             value: CellValue::Put(value),
         };
-        let mut ms = self.memstore.lock().unwrap();
-        ms.append(entry)?;
-        if ms.len() > 10_000 {
+        if self.append_checked(&mut ms, entry)? {
             drop(ms);
             self.flush()?;
         }
         Ok(())
     }
 
+    /// Write an i64 as 8 big-endian bytes, the layout of ValueEncoding::BeI64 and of the
+    /// value get returns for a counter, so typed values, counters and aggregations agree.
+    pub fn put_i64(&self, row: RowKey, column: Column, value: i64) -> IoResult<()> {
+        self.put(row, column, value.to_be_bytes().to_vec())
+    }
+
+    /// Read a value written with put_i64 (or a counter). Fails with InvalidData if the
+    /// cell does not hold exactly 8 bytes.
+    pub fn get_i64(&self, row: &[u8], column: &[u8]) -> IoResult<Option<i64>> {
+        self.get(row, column)?.map(|v| fixed_bytes(v, "i64").map(i64::from_be_bytes)).transpose()
+    }
+
+    /// Write an f64 as 8 little-endian IEEE 754 bytes, the layout of ValueEncoding::LeF64.
+    pub fn put_f64(&self, row: RowKey, column: Column, value: f64) -> IoResult<()> {
+        self.put(row, column, value.to_le_bytes().to_vec())
+    }
+
+    /// Read a value written with put_f64. Fails with InvalidData if the cell does not hold
+    /// exactly 8 bytes.
+    pub fn get_f64(&self, row: &[u8], column: &[u8]) -> IoResult<Option<f64>> {
+        self.get(row, column)?.map(|v| fixed_bytes(v, "f64").map(f64::from_le_bytes)).transpose()
+    }
+
+    /// Write a bool as a single byte, 1 for true and 0 for false.
+    pub fn put_bool(&self, row: RowKey, column: Column, value: bool) -> IoResult<()> {
+        self.put(row, column, vec![value as u8])
+    }
+
+    /// Read a value written with put_bool. Fails with InvalidData if the cell holds anything
+    /// but a single 0 or 1 byte.
+    pub fn get_bool(&self, row: &[u8], column: &[u8]) -> IoResult<Option<bool>> {
+        self.get(row, column)?.map(|v| match fixed_bytes(v, "bool")? {
+            [0] => Ok(false),
+            [1] => Ok(true),
+            _ => Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "cell does not hold a bool")),
+        }).transpose()
+    }
+
+    /// Append entry to the MemStore and report whether a flush is now due: either the
+    /// MemStore holds more than flush_threshold entries, or the entry's cell has more in-memory versions
+    /// than max_versions_in_memstore_per_cell allows. Never due when auto_flush is off.
+    fn append_checked(&self, ms: &mut MemStore, entry: Entry) -> IoResult<bool> {
+        if self.is_sealed() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                format!("ColumnFamily {} is sealed", self.name),
+            ));
+        }
+        self.record_access(&entry.key.row, true);
+        self.newest_ts.fetch_max(entry.key.timestamp, Ordering::SeqCst);
+        let cell_limit = self.options.max_versions_in_memstore_per_cell
+            .map(|max| (max, entry.key.row.clone(), entry.key.column.clone()));
+        ms.append(entry)?;
+        if !self.options.auto_flush {
+            return Ok(false);
+        }
+        let over_cell_limit = cell_limit
+            .is_some_and(|(max, row, column)| ms.version_count(&row, &column) > max);
+        Ok(over_cell_limit || ms.len() > self.options.flush_threshold)
+    }
+
     /// Execute a Put operation with multiple columns.
     /// This is similar to the HBase/Java Put API.
     pub fn execute_put(&self, put: Put) -> IoResult<()> {
-        let ts = chrono::Utc::now().timestamp_millis() as u64;
         let mut ms = self.memstore.lock().unwrap();
+        let ts = self.write_timestamp(&ms, put.columns().keys().map(|column| (put.row(), column)))?;
 
-        put.columns().iter().try_for_each(|(column, value)| {
+        let needs_flush = put.columns().iter().try_fold(false, |needs_flush, (column, value)| {
             let entry = Entry {
                 key: EntryKey { 
                     row: put.row().clone(), 
@@ -240,10 +912,69 @@ impl ColumnFamily {
                 },
                 value: CellValue::Put(value.clone()),
             };
-            ms.append(entry)
+            Ok::<_, std::io::Error>(self.append_checked(&mut ms, entry)? || needs_flush)
+        })?;
+
+        if needs_flush {
+            drop(ms);
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// The timestamp for a write to cells: next_timestamp of the newest stored version of
+    /// any of them, so the write always becomes the newest version. Versions can be ahead of the clock when a cell is written
+    /// more than once within a millisecond. The MemStore is checked whenever the clock hasn't
+    /// passed the newest timestamp written, the SSTables only while that timestamp is still
+    /// ahead of the clock. Callers pass in the locked MemStore.
+    fn write_timestamp<'a>(
+        &self,
+        ms: &MemStore,
+        cells: impl IntoIterator<Item = (&'a RowKey, &'a Column)>,
+    ) -> IoResult<Timestamp> {
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        let newest = self.newest_ts.load(Ordering::SeqCst);
+        if now > newest {
+            return Ok(now);
+        }
+        let latest = cells.into_iter().try_fold(None, |latest, (row, column)| {
+            let latest_ts = if newest > now {
+                self.latest_cell(ms, row, column)?.map(|(latest_ts, _)| latest_ts)
+            } else {
+                ms.get_latest(row, column).map(|(latest_ts, _)| latest_ts)
+            };
+            Ok::<_, std::io::Error>(latest.max(latest_ts))
+        })?;
+        Ok(next_timestamp(latest))
+    }
+
+    /// Write cells in order under a single MemStore lock, each timestamped by write_timestamp.
+    /// A cell written more than once gets increasing timestamps, so the last write wins.
+    /// Used by batches.
+    pub(crate) fn write_cells(&self, cells: Vec<(RowKey, Column, CellValue)>) -> IoResult<()> {
+        let mut ms = self.memstore.lock().unwrap();
+        let needs_flush = cells.into_iter().try_fold(false, |needs_flush, (row, column, value)| {
+            let ts = self.write_timestamp(&ms, [(&row, &column)])?;
+            let entry = Entry { key: EntryKey { row, column, timestamp: ts }, value };
+            Ok::<_, std::io::Error>(self.append_checked(&mut ms, entry)? || needs_flush)
+        })?;
+
+        if needs_flush {
+            drop(ms);
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Append entries with the timestamps they already carry, under a single MemStore lock.
+    /// Used by imports, which keep the timestamps of the data they copy.
+    pub(crate) fn write_entries(&self, entries: Vec<Entry>) -> IoResult<()> {
+        let mut ms = self.memstore.lock().unwrap();
+        let needs_flush = entries.into_iter().try_fold(false, |needs_flush, entry| {
+            Ok::<_, std::io::Error>(self.append_checked(&mut ms, entry)? || needs_flush)
         })?;
 
-        if ms.len() > 10_000 {
+        if needs_flush {
             drop(ms);
             self.flush()?;
         }
@@ -264,85 +995,759 @@ impl ColumnFamily {
     /// * `column` - The column name
     /// * `ttl_ms` - Optional TTL in milliseconds. If None, the tombstone never expires.
     pub fn delete_with_ttl(&self, row: RowKey, column: Column, ttl_ms: Option<u64>) -> IoResult<()> {
-        let ts = chrono::Utc::now().timestamp_millis() as u64;
+        let mut ms = self.memstore.lock().unwrap();
+        let ts = self.write_timestamp(&ms, [(&row, &column)])?;
         let entry = Entry {
             key: EntryKey { row, column, timestamp: ts },
             value: CellValue::Delete(ttl_ms),
         };
-        let mut ms = self.memstore.lock().unwrap();
-        ms.append(entry)?;
-        if ms.len() > 10_000 {
+        if self.append_checked(&mut ms, entry)? {
             drop(ms);
             self.flush()?;
         }
         Ok(())
     }
 
-    /// *Get* the single latest value for (row, column).
-    /// If the latest version is a tombstone, returns Ok(None).
-    /// Otherwise returns Ok(Some(value_bytes)).
-    pub fn get(&self, row: &[u8], column: &[u8]) -> IoResult<Option<Vec<u8>>> {
+    /// Delete column from every row that currently has a live value for it, returning the
+    /// number of rows affected. Each row gets an ordinary tombstone, so the dropped values are
+    /// hidden immediately and discarded by compaction like any other deleted cell.
+    pub fn drop_column(&self, column: Column) -> IoResult<u64> {
         let ms = self.memstore.lock().unwrap();
-        if let Some(cell) = ms.get_full(row, column) {
-            return match cell {
-                CellValue::Put(data) => Ok(Some(data.clone())),
-                CellValue::Delete(_) => Ok(None),
-            };
-        }
-        drop(ms);
 
-        let sst_list = self.sst_files.lock().unwrap();
-        for sst_path in sst_list.iter().rev() {
-            let mut reader = SSTableReader::open(sst_path)?;
-            if let Some(cell) = reader.get_full(row, column)? {
-                return match cell {
-                    CellValue::Put(data) => Ok(Some(data)),
-                    CellValue::Delete(_) => Ok(None),
-                };
-            }
+        let mut cells = Vec::new();
+        for sst_path in self.sst_files.lock().unwrap().iter() {
+            let reader = self.open_sstable(sst_path)?;
+            cells.extend(reader.scan_all()?.into_iter().filter(|(key, _)| key.column == column));
         }
-        Ok(None)
+        cells.extend(ms.scan_all().into_iter().filter(|(key, _)| key.column == column));
+
+        self.tombstone_live_cells(ms, cells)
     }
 
-    /// *MVCC read*: return up to max_versions recent (timestamp, value) for (row, column).
-    /// - Versions are sorted descending by timestamp.
-    /// - Tombstone versions (CellValue::Delete) are skipped entirely.
-    pub fn get_versions(
-        &self,
-        row: &[u8],
-        column: &[u8],
-        max_versions: usize,
-    ) -> IoResult<Vec<(Timestamp, Vec<u8>)>> {
-        let mut all_versions: Vec<(Timestamp, CellValue)> = Vec::new();
+    /// Delete every cell of every row whose key starts with prefix, returning the number of
+    /// cells deleted. Like drop_column, each live cell gets an ordinary tombstone; the rows
+    /// read are limited to those between prefix and the first key past it.
+    pub fn delete_prefix(&self, prefix: &[u8]) -> IoResult<u64> {
+        let ms = self.memstore.lock().unwrap();
 
-        {
+        // With no upper bound (an empty or all-0xFF prefix) every row from prefix on is read.
+        let upper = prefix_upper_bound(prefix);
+        let in_prefix = |(key, _): &(EntryKey, CellValue)| key.row.starts_with(prefix);
+
+        let mut cells = Vec::new();
+        for sst_path in self.sst_files.lock().unwrap().iter() {
+            let mut reader = self.open_sstable(sst_path)?;
+            let entries = match &upper {
+                Some(upper) => reader.scan_range(prefix, upper)?,
+                None => reader.scan_all()?,
+            };
+            cells.extend(entries.into_iter().filter(in_prefix));
+        }
+        let entries = match &upper {
+            Some(upper) => ms.scan_range(prefix, upper),
+            None => ms.scan_all(),
+        };
+        cells.extend(entries.into_iter().filter(in_prefix));
+
+        self.tombstone_live_cells(ms, cells)
+    }
+
+    /// Atomically overwrite a whole row, so that it ends up holding exactly the given
+    /// columns: every other column with a live value gets a tombstone, and every given
+    /// column is written. All of it happens under one lock with a single timestamp, newer
+    /// than any version already in the row, so readers never see a mix of old and new.
+    pub fn replace_row(&self, row: RowKey, columns: HashMap<Column, Vec<u8>>) -> IoResult<()> {
+        let mut ms = self.memstore.lock().unwrap();
+
+        let mut cells: Vec<(EntryKey, CellValue)> = Vec::new();
+        for sst_path in self.sst_files.lock().unwrap().iter() {
+            let mut reader = self.open_sstable(sst_path)?;
+            cells.extend(reader.scan_row_full(&row)?.map(|(column, timestamp, cell)| {
+                (EntryKey { row: row.clone(), column, timestamp }, cell)
+            }));
+        }
+        cells.extend(ms.scan_row_full(&row));
+
+        let ts = next_timestamp(cells.iter().map(|(key, _)| key.timestamp).max());
+
+        let mut entries = Vec::new();
+        for ((_, column), (cell_ts, cell)) in newest_per_cell(cells) {
+            if !columns.contains_key(&column) && self.live_value(cell_ts, cell).is_some() {
+                entries.push(Entry {
+                    key: EntryKey { row: row.clone(), column, timestamp: ts },
+                    value: CellValue::Delete(None),
+                });
+            }
+        }
+        for (column, value) in columns {
+            entries.push(Entry {
+                key: EntryKey { row: row.clone(), column, timestamp: ts },
+                value: CellValue::Put(value),
+            });
+        }
+
+        let mut needs_flush = false;
+        for entry in entries {
+            needs_flush |= self.append_checked(&mut ms, entry)?;
+        }
+        if needs_flush {
+            drop(ms);
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Write a tombstone over every (row, column) among cells whose newest version is live,
+    /// returning how many were written. cells may hold any number of versions per cell,
+    /// gathered under the MemStore lock ms, which is held until the tombstones are in.
+    fn tombstone_live_cells(
+        &self,
+        mut ms: MutexGuard<'_, MemStore>,
+        cells: Vec<(EntryKey, CellValue)>,
+    ) -> IoResult<u64> {
+        let latest = newest_per_cell(cells);
+
+        let mut deleted = 0;
+        let mut flush_due = false;
+        for ((row, column), (ts, cell)) in latest {
+            if self.live_value(ts, cell).is_none() {
+                continue;
+            }
+            let entry = Entry {
+                key: EntryKey { row, column, timestamp: next_timestamp(Some(ts)) },
+                value: CellValue::Delete(None),
+            };
+            flush_due |= self.append_checked(&mut ms, entry)?;
+            deleted += 1;
+        }
+        drop(ms);
+
+        if flush_due {
+            self.flush()?;
+        }
+        Ok(deleted)
+    }
+
+    /// Every column qualifier with a live value in at least one row of [start_row, end_row],
+    /// e.g. to discover the schema of a sparse, wide table. A column whose newest version in
+    /// a row is a delete or has expired doesn't count for that row. Only the names are
+    /// collected; no values are returned.
+    pub fn distinct_columns(&self, start_row: &[u8], end_row: &[u8]) -> IoResult<BTreeSet<Column>> {
+        let mut cells = Vec::new();
+        {
+            let sst_list = self.sst_files.lock().unwrap();
+            for sst_path in sst_list.iter() {
+                let mut reader = self.open_sstable(sst_path)?;
+                cells.extend(reader.scan_range(start_row, end_row)?);
+            }
+        }
+        cells.extend(self.memstore.lock().unwrap().scan_range(start_row, end_row));
+
+        Ok(newest_per_cell(cells)
+            .into_iter()
+            .filter(|(_, (ts, cell))| self.is_live_version(*ts, cell))
+            .map(|((_, column), _)| column)
+            .collect())
+    }
+
+    /// *Get* the single latest value for (row, column).
+    /// If the latest version is a tombstone or has expired (see ttl_ms), returns Ok(None).
+    /// Otherwise returns Ok(Some(value_bytes)).
+    pub fn get(&self, row: &[u8], column: &[u8]) -> IoResult<Option<Vec<u8>>> {
+        Ok(self.get_with_source(row, column)?.map(|(value, _source)| value))
+    }
+
+    /// Like get, but may answer from a cache: a result read from the store less than
+    /// max_staleness ago is returned as is, even if the cell has been written since.
+    /// Otherwise the cell is read with get and the cached result refreshed.
+    /// Missing cells are cached too. Only get_cached reads and fills the cache.
+    pub fn get_cached(&self, row: &[u8], column: &[u8], max_staleness: Duration) -> IoResult<Option<Vec<u8>>> {
+        let key = (row.to_vec(), column.to_vec());
+        if let Some((read_at, value)) = self.read_cache.lock().unwrap().get(&key) {
+            if read_at.elapsed() < max_staleness {
+                return Ok(value.clone());
+            }
+        }
+
+        let read_at = Instant::now();
+        let value = self.get(row, column)?;
+
+        let mut cache = self.read_cache.lock().unwrap();
+        if cache.len() >= MAX_CACHED_READS && !cache.contains_key(&key) {
+            if let Some(evicted) = cache.keys().next().cloned() {
+                cache.remove(&evicted);
+            }
+        }
+        cache.insert(key, (read_at, value.clone()));
+        Ok(value)
+    }
+
+    /// *Diagnostic*: like get, but also reports where the value was found, to help debug
+    /// the read path and check flush behaviour. The lookup is exactly the one get performs.
+    /// Not meant for application logic: which source holds a value changes with every flush
+    /// and compaction.
+    pub fn get_with_source(&self, row: &[u8], column: &[u8]) -> IoResult<Option<(Vec<u8>, ReadSource)>> {
+        self.record_access(row, false);
+        let ms = self.memstore.lock().unwrap();
+        if let Some((ts, cell)) = ms.get_latest(row, column) {
+            return Ok(self.live_value(ts, cell.clone()).map(|v| (v, ReadSource::Memstore)));
+        }
+        drop(ms);
+
+        let sst_list = self.sst_files.lock().unwrap();
+        for (consulted, sst_path) in sst_list.iter().rev().enumerate() {
+            let reader = self.open_sstable(sst_path)?;
+            if let Some((ts, cell)) = reader.get_latest(row, column) {
+                self.maybe_schedule_read_compaction(consulted + 1, sst_path);
+                return Ok(self.live_value(ts, cell).map(|v| (v, ReadSource::SSTable(sst_path.clone()))));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Like get, but consults the MemStore and at most max_sstables of the newest SSTables,
+    /// for latency-critical reads that would rather miss an old value than open every file.
+    /// Returns the value found together with whether the search was truncated: true when
+    /// the cell was not found and older SSTables were left unread, in which case a None
+    /// (or nothing at all) may hide an older value.
+    pub fn get_bounded(&self, row: &[u8], column: &[u8], max_sstables: usize) -> IoResult<(Option<Vec<u8>>, bool)> {
+        self.record_access(row, false);
+        let ms = self.memstore.lock().unwrap();
+        if let Some((ts, cell)) = ms.get_latest(row, column) {
+            return Ok((self.live_value(ts, cell.clone()), false));
+        }
+        drop(ms);
+
+        let sst_list = self.sst_files.lock().unwrap();
+        for (consulted, sst_path) in sst_list.iter().rev().take(max_sstables).enumerate() {
+            let reader = self.open_sstable(sst_path)?;
+            if let Some((ts, cell)) = reader.get_latest(row, column) {
+                self.maybe_schedule_read_compaction(consulted + 1, sst_path);
+                return Ok((self.live_value(ts, cell), false));
+            }
+        }
+        Ok((None, sst_list.len() > max_sstables))
+    }
+
+    /// Reads and writes per row-key bucket, as (reads, writes), to find hot partitions.
+    /// Rows are bucketed by their first prefix_len bytes, but never more finely than
+    /// options.access_stats_prefix_len, the prefix the counts were kept for. Point reads
+    /// (get, exists, get_versions and the like) and row reads (scan_row_versions and the
+    /// row-oriented scans built on it) count as reads; every cell written counts as a
+    /// write. Empty when access_stats_prefix_len is not set.
+    pub fn access_heatmap(&self, prefix_len: usize) -> BTreeMap<Vec<u8>, (u64, u64)> {
+        let mut heatmap: BTreeMap<Vec<u8>, (u64, u64)> = BTreeMap::new();
+        for (prefix, (reads, writes)) in self.access_stats.lock().unwrap().iter() {
+            let bucket = heatmap.entry(prefix[..prefix_len.min(prefix.len())].to_vec()).or_default();
+            bucket.0 += reads;
+            bucket.1 += writes;
+        }
+        heatmap
+    }
+
+    /// Count one read or write of row for access_heatmap, if access stats are on.
+    fn record_access(&self, row: &[u8], write: bool) {
+        let Some(prefix_len) = self.options.access_stats_prefix_len else {
+            return;
+        };
+        let mut stats = self.access_stats.lock().unwrap();
+        let counts = stats.entry(row[..prefix_len.min(row.len())].to_vec()).or_default();
+        if write {
+            counts.1 += 1;
+        } else {
+            counts.0 += 1;
+        }
+    }
+
+    /// Whether get would return a value for (row, column), without copying the value.
+    /// Sources are consulted in the same order as get, stopping at the first that holds
+    /// the cell: true for a live put or counter, false for a tombstone, an expired version
+    /// or a missing cell.
+    pub fn exists(&self, row: &[u8], column: &[u8]) -> IoResult<bool> {
+        self.record_access(row, false);
+        let ms = self.memstore.lock().unwrap();
+        if let Some((ts, cell)) = ms.get_latest(row, column) {
+            return Ok(self.is_live_version(ts, cell));
+        }
+        drop(ms);
+
+        let sst_list = self.sst_files.lock().unwrap();
+        for (consulted, sst_path) in sst_list.iter().rev().enumerate() {
+            let reader = self.open_sstable(sst_path)?;
+            if let Some((ts, cell)) = reader.get_latest_ref(row, column) {
+                self.maybe_schedule_read_compaction(consulted + 1, sst_path);
+                return Ok(self.is_live_version(ts, cell));
+            }
+        }
+        Ok(false)
+    }
+
+    /// The value stored in a version, or None if it is a tombstone or has outlived ttl_ms.
+    fn live_value(&self, ts: Timestamp, cell: CellValue) -> Option<Vec<u8>> {
+        match cell {
+            CellValue::Put(v) if !self.is_expired(ts) => Some(v),
+            CellValue::Counter(n) if !self.is_expired(ts) => Some(n.to_be_bytes().to_vec()),
+            _ => None,
+        }
+    }
+
+    /// True if a version holds a value reads can return: not a tombstone, and not expired.
+    fn is_live_version(&self, ts: Timestamp, cell: &CellValue) -> bool {
+        !matches!(cell, CellValue::Delete(_)) && !self.is_expired(ts)
+    }
+
+    /// True if a version written at ts is older than the column family's ttl_ms.
+    fn is_expired(&self, ts: Timestamp) -> bool {
+        self.options.ttl_ms.is_some_and(|ttl| {
+            let now = chrono::Utc::now().timestamp_millis() as u64;
+            now.saturating_sub(ts) > ttl
+        })
+    }
+
+    /// Atomically write value to (row, column) if its current value equals expected.
+    /// An expected value of None means the cell must be absent or deleted.
+    /// Returns Ok(true) if the put was applied, Ok(false) if the check failed.
+    ///
+    /// The current value is the newest version across the MemStore and every SSTable,
+    /// read while holding the MemStore lock so that no write or flush can slip in between
+    /// the check and the put.
+    pub fn check_and_put(
+        &self,
+        row: RowKey,
+        column: Column,
+        expected: Option<&[u8]>,
+        value: Vec<u8>,
+    ) -> IoResult<bool> {
+        let mut ms = self.memstore.lock().unwrap();
+        let latest = self.latest_cell(&ms, &row, &column)?;
+
+        let current = latest.clone().and_then(|(ts, cell)| self.live_value(ts, cell));
+        if current.as_deref() != expected {
+            return Ok(false);
+        }
+
+        let ts = next_timestamp(latest.map(|(latest_ts, _)| latest_ts));
+        let entry = Entry {
+            key: EntryKey { row, column, timestamp: ts },
+            value: CellValue::Put(value),
+        };
+        if self.append_checked(&mut ms, entry)? {
+            drop(ms);
+            self.flush()?;
+        }
+        Ok(true)
+    }
+
+    /// Atomically apply put if the current value of (row, column) equals expected, like
+    /// HBase's checkAndPut. An expected value of None means the cell must be absent or deleted.
+    /// Returns Ok(true) if the put was applied, Ok(false) if the check failed, and fails
+    /// with InvalidInput if put is for a different row.
+    ///
+    /// The check and every column of the put happen under one MemStore lock, as in
+    /// check_and_put, and the put's columns share one timestamp, past the newest version
+    /// of the checked cell and of every column written.
+    ///
+    /// This is HBase's checkAndPut taking a whole Put; it has its own name because
+    /// check_and_put already writes a single value. expected is borrowed, as there.
+    pub fn check_and_execute_put(
+        &self,
+        row: RowKey,
+        column: Column,
+        expected: Option<&[u8]>,
+        put: Put,
+    ) -> IoResult<bool> {
+        if put.row() != &row {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "put is for a different row than the check",
+            ));
+        }
+
+        let mut ms = self.memstore.lock().unwrap();
+        let latest = self.latest_cell(&ms, &row, &column)?;
+
+        let current = latest.clone().and_then(|(ts, cell)| self.live_value(ts, cell));
+        if current.as_deref() != expected {
+            return Ok(false);
+        }
+
+        let cells = std::iter::once(&column).chain(put.columns().keys()).map(|col| (&row, col));
+        let ts = self.write_timestamp(&ms, cells)?;
+        let needs_flush = put.columns().iter().try_fold(false, |needs_flush, (column, value)| {
+            let entry = Entry {
+                key: EntryKey { row: row.clone(), column: column.clone(), timestamp: ts },
+                value: CellValue::Put(value.clone()),
+            };
+            Ok::<_, std::io::Error>(self.append_checked(&mut ms, entry)? || needs_flush)
+        })?;
+
+        if needs_flush {
+            drop(ms);
+            self.flush()?;
+        }
+        Ok(true)
+    }
+
+    /// Write value to (row, column) and return the value that was live before it, like a
+    /// map's insert. The read and the write happen under one MemStore lock, so no other
+    /// write to the cell can land in between.
+    pub fn put_and_get_previous(&self, row: RowKey, column: Column, value: Vec<u8>) -> IoResult<Option<Vec<u8>>> {
+        self.replace_cell(row, column, CellValue::Put(value))
+    }
+
+    /// Delete (row, column) and return the value that was live before the delete.
+    /// None means the cell was already absent, deleted or expired.
+    pub fn delete_and_get_previous(&self, row: RowKey, column: Column) -> IoResult<Option<Vec<u8>>> {
+        self.replace_cell(row, column, CellValue::Delete(None))
+    }
+
+    /// Write cell as the newest version of (row, column) and return the previous live value.
+    fn replace_cell(&self, row: RowKey, column: Column, cell: CellValue) -> IoResult<Option<Vec<u8>>> {
+        let mut ms = self.memstore.lock().unwrap();
+        let latest = self.latest_cell(&ms, &row, &column)?;
+        let previous = latest.clone().and_then(|(ts, cell)| self.live_value(ts, cell));
+
+        let ts = next_timestamp(latest.map(|(latest_ts, _)| latest_ts));
+        let entry = Entry {
+            key: EntryKey { row, column, timestamp: ts },
+            value: cell,
+        };
+        if self.append_checked(&mut ms, entry)? {
+            drop(ms);
+            self.flush()?;
+        }
+        Ok(previous)
+    }
+
+    /// Atomically add delta to the counter at (row, column) and return the new total.
+    /// A missing, deleted or expired cell counts as 0. The new total is stored as a
+    /// CellValue::Counter; a cell still holding a decimal number written with put
+    /// (e.g. b"41") is read as that number, so existing counters carry on from where they were.
+    /// After that, get returns the counter as 8 big-endian bytes rather than text; aggregations
+    /// read counter versions as integers whatever encoding their column is given.
+    /// Fails with InvalidData if the cell holds anything else, and with InvalidInput on overflow.
+    pub fn increment(&self, row: RowKey, column: Column, delta: i64) -> IoResult<i64> {
+        let mut ms = self.memstore.lock().unwrap();
+        let latest = self.latest_cell(&ms, &row, &column)?;
+
+        let current = match latest.clone() {
+            Some((ts, cell)) => self.counter_value(ts, cell)?.unwrap_or(0),
+            None => 0,
+        };
+        let total = current.checked_add(delta).ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::InvalidInput, "counter overflow")
+        })?;
+
+        let ts = next_timestamp(latest.map(|(latest_ts, _)| latest_ts));
+        let entry = Entry {
+            key: EntryKey { row, column, timestamp: ts },
+            value: CellValue::Counter(total),
+        };
+        if self.append_checked(&mut ms, entry)? {
+            drop(ms);
+            self.flush()?;
+        }
+        Ok(total)
+    }
+
+    /// Atomically add each delta to its counter in row and return the new totals.
+    /// All counters are read and written under one lock, with a single timestamp, so no
+    /// other write to them can land in between. Counters are read as by increment. If any
+    /// cell holds a non-number or any total would overflow, nothing is written.
+    pub fn increment_columns(&self, row: RowKey, deltas: HashMap<Column, i64>) -> IoResult<HashMap<Column, i64>> {
+        let mut ms = self.memstore.lock().unwrap();
+
+        let mut totals = HashMap::with_capacity(deltas.len());
+        let mut latest_ts = None;
+        for (column, delta) in deltas {
+            let latest = self.latest_cell(&ms, &row, &column)?;
+            let current = match latest {
+                Some((ts, cell)) => {
+                    latest_ts = latest_ts.max(Some(ts));
+                    self.counter_value(ts, cell)?.unwrap_or(0)
+                }
+                None => 0,
+            };
+            let total = current.checked_add(delta).ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::InvalidInput, "counter overflow")
+            })?;
+            totals.insert(column, total);
+        }
+
+        // Every new version must sort after the one read for its cell.
+        let ts = next_timestamp(latest_ts);
+        let mut needs_flush = false;
+        for (column, total) in &totals {
+            let entry = Entry {
+                key: EntryKey { row: row.clone(), column: column.clone(), timestamp: ts },
+                value: CellValue::Counter(*total),
+            };
+            needs_flush |= self.append_checked(&mut ms, entry)?;
+        }
+
+        if needs_flush {
+            drop(ms);
+            self.flush()?;
+        }
+        Ok(totals)
+    }
+
+    /// Read the counter at (row, column), or Ok(None) if the cell is missing, deleted or expired.
+    /// Decimal numbers written with put are accepted too; any other value fails with InvalidData.
+    pub fn get_counter(&self, row: &[u8], column: &[u8]) -> IoResult<Option<i64>> {
+        let ms = self.memstore.lock().unwrap();
+        match self.latest_cell(&ms, row, column)? {
+            Some((ts, cell)) => self.counter_value(ts, cell),
+            None => Ok(None),
+        }
+    }
+
+    /// The number held by one version of a counter cell; None for tombstones and expired versions.
+    fn counter_value(&self, ts: Timestamp, cell: CellValue) -> IoResult<Option<i64>> {
+        if self.is_expired(ts) {
+            return Ok(None);
+        }
+        match cell {
+            CellValue::Counter(n) => Ok(Some(n)),
+            CellValue::Put(bytes) => std::str::from_utf8(&bytes).ok()
+                .and_then(|text| text.trim().parse::<i64>().ok())
+                .map(Some)
+                .ok_or_else(|| std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "cell does not hold a counter",
+                )),
+            CellValue::Delete(_) => Ok(None),
+        }
+    }
+
+    /// The newest version of (row, column) by timestamp, looking at the MemStore and every
+    /// SSTable rather than stopping at the first source that has the cell.
+    /// Callers pass in the locked MemStore so the result can't go stale under a flush.
+    fn latest_cell(
+        &self,
+        ms: &MemStore,
+        row: &[u8],
+        column: &[u8],
+    ) -> IoResult<Option<(Timestamp, CellValue)>> {
+        let mut latest = ms.get_versions_full(row, column).into_iter().next();
+
+        let sst_list = self.sst_files.lock().unwrap();
+        for sst_path in sst_list.iter() {
+            let mut reader = self.open_sstable(sst_path)?;
+            if let Some(candidate) = reader.get_versions_full(row, column)?.into_iter().next() {
+                if latest.as_ref().is_none_or(|(ts, _)| candidate.0 > *ts) {
+                    latest = Some(candidate);
+                }
+            }
+        }
+        Ok(latest)
+    }
+
+    /// *MVCC read*: return up to max_versions recent (timestamp, value) for (row, column).
+    /// - Versions are sorted descending by timestamp.
+    /// - Tombstone versions (CellValue::Delete) are skipped entirely.
+    pub fn get_versions(
+        &self,
+        row: &[u8],
+        column: &[u8],
+        max_versions: usize,
+    ) -> IoResult<Vec<(Timestamp, Vec<u8>)>> {
+        let result = self.versions_full(row, column)?
+            .into_iter()
+            .filter_map(|(ts, cell)| self.live_value(ts, cell).map(|v| (ts, v)))
+            .take(max_versions)
+            .collect();
+
+        Ok(result)
+    }
+
+    /// The latest value of (row, column), as get returns it, together with the timestamps of
+    /// every live version of the cell, newest first, i.e. those get_versions would return
+    /// with no limit. Only the latest value's bytes are returned. Ok(None) when get would
+    /// return None.
+    pub fn get_with_versions_meta(&self, row: &[u8], column: &[u8]) -> IoResult<Option<(Vec<u8>, Vec<Timestamp>)>> {
+        let mut versions = self.versions_full(row, column)?.into_iter();
+        let latest = match versions.next() {
+            Some((ts, cell)) => match self.live_value(ts, cell) {
+                Some(value) => (ts, value),
+                None => return Ok(None),
+            },
+            None => return Ok(None),
+        };
+
+        let mut timestamps = vec![latest.0];
+        timestamps.extend(versions
+            .filter(|(ts, cell)| self.is_live_version(*ts, cell))
+            .map(|(ts, _)| ts));
+        Ok(Some((latest.1, timestamps)))
+    }
+
+    /// The oldest live version of (row, column), i.e. the last one get_versions would return
+    /// with no limit. Each source is only read up to its first live version of the cell,
+    /// so this does not gather every version.
+    pub fn get_oldest(&self, row: &[u8], column: &[u8]) -> IoResult<Option<(Timestamp, Vec<u8>)>> {
+        let mut oldest = {
             let ms = self.memstore.lock().unwrap();
-            all_versions.extend(ms.get_versions_full(row, column));
+            self.first_live(ms.versions_oldest_first(row, column))
+        };
+
+        let sst_list = self.sst_files.lock().unwrap();
+        for sst_path in sst_list.iter() {
+            let reader = self.open_sstable(sst_path)?;
+            if let Some(found) = self.first_live(reader.versions_oldest_first(row, column)) {
+                if oldest.as_ref().is_none_or(|(ts, _)| found.0 < *ts) {
+                    oldest = Some(found);
+                }
+            }
+        }
+        Ok(oldest)
+    }
+
+    /// The first of versions that is live, with its value.
+    fn first_live<'a>(
+        &self,
+        mut versions: impl Iterator<Item = (Timestamp, &'a CellValue)>,
+    ) -> Option<(Timestamp, Vec<u8>)> {
+        versions.find_map(|(ts, cell)| self.live_value(ts, cell.clone()).map(|v| (ts, v)))
+    }
+
+    /// *Low-level MVCC read* for auditing: return up to max_versions recent versions of
+    /// (row, column) exactly as stored, tombstones included.
+    /// - Versions are sorted descending by timestamp.
+    /// - A CellValue::Delete entry shows when the cell was deleted and with which TTL.
+    ///
+    /// Application reads should use get_versions, which hides tombstones.
+    pub fn get_versions_raw(
+        &self,
+        row: &[u8],
+        column: &[u8],
+        max_versions: usize,
+    ) -> IoResult<Vec<(Timestamp, CellValue)>> {
+        let mut versions = self.versions_full(row, column)?;
+        versions.truncate(max_versions);
+        Ok(versions)
+    }
+
+    /// *MVCC read* at the given consistency level: like get, but only consulting the
+    /// sources consistency allows. ReadConsistency::Default is the same as get.
+    pub fn get_with_consistency(
+        &self,
+        row: &[u8],
+        column: &[u8],
+        consistency: ReadConsistency,
+    ) -> IoResult<Option<Vec<u8>>> {
+        if consistency == ReadConsistency::Default {
+            return self.get(row, column);
+        }
+        let newest = self.versions_with_consistency(row, column, consistency)?.into_iter().next();
+        Ok(newest.and_then(|(ts, cell)| self.live_value(ts, cell)))
+    }
+
+    /// *MVCC read* at the given consistency level: like get_versions, but only consulting
+    /// the sources consistency allows.
+    pub fn get_versions_with_consistency(
+        &self,
+        row: &[u8],
+        column: &[u8],
+        max_versions: usize,
+        consistency: ReadConsistency,
+    ) -> IoResult<Vec<(Timestamp, Vec<u8>)>> {
+        let result = self.versions_with_consistency(row, column, consistency)?
+            .into_iter()
+            .filter_map(|(ts, cell)| self.live_value(ts, cell).map(|v| (ts, v)))
+            .take(max_versions)
+            .collect();
+
+        Ok(result)
+    }
+
+    /// Every stored version of (row, column) from the MemStore and all SSTables,
+    /// tombstones included, sorted descending by timestamp.
+    fn versions_full(&self, row: &[u8], column: &[u8]) -> IoResult<Vec<(Timestamp, CellValue)>> {
+        self.versions_with_consistency(row, column, ReadConsistency::Default)
+    }
+
+    /// Like versions_full, but only from the sources consistency allows.
+    fn versions_with_consistency(
+        &self,
+        row: &[u8],
+        column: &[u8],
+        consistency: ReadConsistency,
+    ) -> IoResult<Vec<(Timestamp, CellValue)>> {
+        self.record_access(row, false);
+        let mut all_versions: Vec<(Timestamp, CellValue)> = Vec::new();
+
+        let memstore_versions = match consistency {
+            ReadConsistency::SkipMemstore => Vec::new(),
+            _ => self.memstore.lock().unwrap().get_versions_full(row, column),
+        };
+        if consistency == ReadConsistency::MemstoreOnly {
+            return Ok(memstore_versions);
         }
 
         let sst_list = self.sst_files.lock().unwrap();
         let readers: IoResult<Vec<_>> = sst_list.iter()
-            .map(|sst_path| SSTableReader::open(sst_path))
+            .map(|sst_path| self.open_sstable(sst_path))
             .collect();
 
-        for mut reader in readers? {
-            all_versions.extend(reader.get_versions_full(row, column)?);
+        let mut containing: Vec<&PathBuf> = Vec::new();
+        for (mut reader, sst_path) in readers?.into_iter().zip(sst_list.iter()) {
+            let versions = reader.get_versions_full(row, column)?;
+            if !versions.is_empty() {
+                containing.push(sst_path);
+            }
+            all_versions.extend(versions);
         }
+        if let Some(oldest) = containing.first() {
+            self.maybe_schedule_read_compaction(containing.len(), oldest);
+        }
+        all_versions.extend(memstore_versions);
 
-        all_versions.sort_by(|a, b| b.0.cmp(&a.0));
+        sort_newest_first(&mut all_versions);
+        if let ReadConsistency::SnapshotAt(snapshot_ts) = consistency {
+            all_versions.retain(|(ts, _)| *ts <= snapshot_ts);
+        }
+        Ok(all_versions)
+    }
 
-        let result = all_versions.into_iter()
-            .filter_map(|(ts, cell)| {
-                if let CellValue::Put(v) = cell {
-                    Some((ts, v))
+    /// Sort each column's versions newest first, drop tombstones and expired versions,
+    /// and keep at most max_versions_per_column. Columns left with no versions are omitted.
+    fn live_versions_by_column(
+        &self,
+        per_column: BTreeMap<Column, Vec<(Timestamp, CellValue)>>,
+        max_versions_per_column: usize,
+    ) -> BTreeMap<Column, Vec<(Timestamp, Vec<u8>)>> {
+        per_column
+            .into_iter()
+            .filter_map(|(col, mut versions)| {
+                sort_newest_first(&mut versions);
+
+                let kept: Vec<(Timestamp, Vec<u8>)> = versions.into_iter()
+                    .filter_map(|(ts, cell)| self.live_value(ts, cell).map(|v| (ts, v)))
+                    .take(max_versions_per_column)
+                    .collect();
+
+                if !kept.is_empty() {
+                    Some((col, kept))
                 } else {
                     None
                 }
             })
-            .take(max_versions)
-            .collect();
+            .collect()
+    }
 
-        Ok(result)
+    /// *MVCC read*: return the index-th newest live version of (row, column), 0 being the newest.
+    /// Tombstone versions are skipped and do not count towards the index.
+    /// Returns Ok(None) when the cell has no more than index live versions.
+    pub fn get_version_at(
+        &self,
+        row: &[u8],
+        column: &[u8],
+        index: usize,
+    ) -> IoResult<Option<(Timestamp, Vec<u8>)>> {
+        Ok(self.get_versions(row, column, index.saturating_add(1))?
+            .into_iter()
+            .nth(index))
     }
 
     /// *MVCC read with time range*: return versions within a specific time range.
@@ -359,31 +1764,26 @@ impl ColumnFamily {
     ) -> IoResult<Vec<(Timestamp, Vec<u8>)>> {
         let mut all_versions: Vec<(Timestamp, CellValue)> = Vec::new();
 
-        {
+        let memstore_versions = {
             let ms = self.memstore.lock().unwrap();
-            all_versions.extend(ms.get_versions_full(row, column));
-        }
+            ms.get_versions_full(row, column)
+        };
 
         let sst_list = self.sst_files.lock().unwrap();
         let readers: IoResult<Vec<_>> = sst_list.iter()
-            .map(|sst_path| SSTableReader::open(sst_path))
+            .map(|sst_path| self.open_sstable(sst_path))
             .collect();
 
         for mut reader in readers? {
             all_versions.extend(reader.get_versions_full(row, column)?);
         }
+        all_versions.extend(memstore_versions);
 
-        all_versions.sort_by(|a, b| b.0.cmp(&a.0));
+        sort_newest_first(&mut all_versions);
 
         let result = all_versions.into_iter()
             .filter(|(ts, _)| *ts >= start_time && *ts <= end_time)
-            .filter_map(|(ts, cell)| {
-                if let CellValue::Put(v) = cell {
-                    Some((ts, v))
-                } else {
-                    None
-                }
-            })
+            .filter_map(|(ts, cell)| self.live_value(ts, cell).map(|v| (ts, v)))
             .take(max_versions)
             .collect();
 
@@ -394,10 +1794,10 @@ impl ColumnFamily {
     /// This is similar to the HBase/Java Get API.
     pub fn execute_get(&self, get: &Get) -> IoResult<BTreeMap<Column, Vec<(Timestamp, Vec<u8>)>>> {
         let row = get.row();
-        let max_versions = get.max_versions().unwrap_or(1);
+        let max_versions = get.version_limit(self.options.default_get_versions);
 
         if let Some((start_time, end_time)) = get.time_range() {
-            let row_data = self.scan_row_versions(row, max_versions * 10)?;
+            let row_data = self.scan_row_versions(row, max_versions.saturating_mul(10))?;
             let result = row_data.into_iter()
                 .filter_map(|(column, versions)| {
                     let filtered_versions: Vec<(Timestamp, Vec<u8>)> = versions
@@ -420,99 +1820,400 @@ impl ColumnFamily {
         }
     }
 
-    /// Execute a Get operation for a specific column.
-    /// This is a convenience method that returns only the versions for a single column.
-    pub fn execute_get_column(&self, get: &Get, column: &[u8]) -> IoResult<Vec<(Timestamp, Vec<u8>)>> {
-        let row = get.row();
-        let max_versions = get.max_versions().unwrap_or(1);
+    /// Execute several Gets at once, returning their results in the order of gets.
+    /// Each SSTable is opened once and read for every row before moving on to the next,
+    /// rather than once per Get. Each result is what execute_get would return for that Get.
+    pub fn multi_get(&self, gets: &[Get]) -> IoResult<Vec<RowVersions>> {
+        let mut cells: Vec<RowCells> = vec![RowCells::new(); gets.len()];
+        for get in gets {
+            self.record_access(get.row(), false);
+        }
+        {
+            let sst_list = self.sst_files.lock().unwrap();
+            for sst_path in sst_list.iter() {
+                let mut reader = self.open_sstable(sst_path)?;
+                for (get, per_column) in gets.iter().zip(cells.iter_mut()) {
+                    for (col, ts, cell) in reader.scan_row_full(get.row())? {
+                        per_column.entry(col).or_default().push((ts, cell));
+                    }
+                }
+            }
+        }
+
+        let ms = self.memstore.lock().unwrap();
+        for (get, per_column) in gets.iter().zip(cells.iter_mut()) {
+            for (entry_key, cell) in ms.scan_row_full(get.row()) {
+                per_column.entry(entry_key.column).or_default().push((entry_key.timestamp, cell));
+            }
+        }
+        drop(ms);
+
+        Ok(gets.iter().zip(cells).map(|(get, per_column)| {
+            let max_versions = get.version_limit(self.options.default_get_versions);
+            match get.time_range() {
+                // As in execute_get, look at ten times the limit before filtering by time.
+                Some((start_time, end_time)) => self
+                    .live_versions_by_column(per_column, max_versions.saturating_mul(10))
+                    .into_iter()
+                    .filter_map(|(column, versions)| {
+                        let versions: Vec<(Timestamp, Vec<u8>)> = versions
+                            .into_iter()
+                            .filter(|(ts, _)| *ts >= start_time && *ts <= end_time)
+                            .take(max_versions)
+                            .collect();
+                        (!versions.is_empty()).then_some((column, versions))
+                    })
+                    .collect(),
+                None => self.live_versions_by_column(per_column, max_versions),
+            }
+        }).collect())
+    }
+
+    /// Execute a Get operation for a specific column.
+    /// This is a convenience method that returns only the versions for a single column.
+    pub fn execute_get_column(&self, get: &Get, column: &[u8]) -> IoResult<Vec<(Timestamp, Vec<u8>)>> {
+        let row = get.row();
+        let max_versions = get.version_limit(self.options.default_get_versions);
+
+        if let Some((start_time, end_time)) = get.time_range() {
+            self.get_versions_with_time_range(row, column, max_versions, start_time, end_time)
+        } else {
+            self.get_versions(row, column, max_versions)
+        }
+    }
+
+    /// *MVCC scan*: for each column under row, return up to max_versions_per_column recent (timestamp, value).
+    /// - Tombstone versions are skipped.
+    /// - If a column has fewer than max_versions_per_column puts, you get as many as exist.
+    pub fn scan_row_versions(
+        &self,
+        row: &[u8],
+        max_versions_per_column: usize,
+    ) -> IoResult<BTreeMap<Column, Vec<(Timestamp, Vec<u8>)>>> {
+        if max_versions_per_column == 1 {
+            // Only the newest live version is wanted, so don't gather whole histories.
+            return self.newest_by_column(row, true);
+        }
+        let per_column = self.row_cells_by_column(row)?;
+        Ok(self.live_versions_by_column(per_column, max_versions_per_column))
+    }
+
+    /// The number of columns in row that get would return a value for: those whose newest
+    /// version is neither a tombstone nor expired. Only a timestamp and a flag are kept per
+    /// column, not the values. This can be less than scan_row_versions(row, 1).len(), which
+    /// passes over a newest tombstone to the put beneath it where cell_count drops the column.
+    pub fn cell_count(&self, row: &[u8]) -> IoResult<usize> {
+        let mut newest: BTreeMap<Column, (Timestamp, bool)> = BTreeMap::new();
+        self.for_each_row_cell(row, |col, ts, cell| {
+            // Sources come oldest first, so on a timestamp tie the later one wins, as in get.
+            match newest.get(&col) {
+                Some((newest_ts, _)) if *newest_ts > ts => {}
+                _ => {
+                    newest.insert(col, (ts, self.is_live_version(ts, &cell)));
+                }
+            }
+        })?;
+        Ok(newest.values().filter(|(_, live)| *live).count())
+    }
+
+    /// The newest version of every column in row, or nothing for a column whose newest
+    /// version is a tombstone or has expired.
+    fn scan_row_newest(&self, row: &[u8]) -> IoResult<RowVersions> {
+        self.newest_by_column(row, false)
+    }
+
+    /// The newest version of every column in row, keeping one candidate per column as the
+    /// sources are read. With skip_dead, tombstones and expired versions are passed over, so
+    /// the result is the newest live version (what scan_row_versions returns for a single
+    /// version); otherwise a column whose newest version is dead is left out.
+    fn newest_by_column(&self, row: &[u8], skip_dead: bool) -> IoResult<RowVersions> {
+        let mut newest: BTreeMap<Column, (Timestamp, CellValue)> = BTreeMap::new();
+        self.for_each_row_cell(row, |col, ts, cell| {
+            if skip_dead && !self.is_live_version(ts, &cell) {
+                return;
+            }
+            // Sources come oldest first, so on a timestamp tie the later one wins, as in get.
+            match newest.get(&col) {
+                Some((newest_ts, _)) if *newest_ts > ts => {}
+                _ => {
+                    newest.insert(col, (ts, cell));
+                }
+            }
+        })?;
+
+        Ok(newest
+            .into_iter()
+            .filter_map(|(col, (ts, cell))| self.live_value(ts, cell).map(|v| (col, vec![(ts, v)])))
+            .collect())
+    }
+
+    /// The newest version of each column in per_column, leaving out columns whose newest
+    /// version is a tombstone or has expired: scan_row_newest over cells already read.
+    fn newest_live_by_column(&self, per_column: RowCells) -> RowVersions {
+        per_column
+            .into_iter()
+            .filter_map(|(col, mut versions)| {
+                sort_newest_first(&mut versions);
+                let (ts, cell) = versions.into_iter().next()?;
+                self.live_value(ts, cell).map(|v| (col, vec![(ts, v)]))
+            })
+            .collect()
+    }
 
-        if let Some((start_time, end_time)) = get.time_range() {
-            self.get_versions_with_time_range(row, column, max_versions, start_time, end_time)
-        } else {
-            self.get_versions(row, column, max_versions)
-        }
+    /// Every version of every column in row, tombstones included, SSTables first and
+    /// MemStore last so that sort_newest_first breaks timestamp ties correctly.
+    fn row_cells_by_column(&self, row: &[u8]) -> IoResult<RowCells> {
+        let mut per_column = RowCells::new();
+        self.for_each_row_cell(row, |col, ts, cell| {
+            per_column.entry(col).or_default().push((ts, cell));
+        })?;
+        Ok(per_column)
     }
 
-    /// *MVCC scan*: for each column under row, return up to max_versions_per_column recent (timestamp, value).
-    /// - Tombstone versions are skipped.
-    /// - If a column has fewer than max_versions_per_column puts, you get as many as exist.
-    pub fn scan_row_versions(
-        &self,
-        row: &[u8],
-        max_versions_per_column: usize,
-    ) -> IoResult<BTreeMap<Column, Vec<(Timestamp, Vec<u8>)>>> {
-        let mut per_column: BTreeMap<Column, Vec<(Timestamp, CellValue)>> = BTreeMap::new();
+    /// Call visit with every stored version in row, SSTables oldest first, then the MemStore.
+    /// Within a source, the versions of a column come oldest first.
+    fn for_each_row_cell(&self, row: &[u8], mut visit: impl FnMut(Column, Timestamp, CellValue)) -> IoResult<()> {
+        self.record_access(row, false);
         {
             let sst_list = self.sst_files.lock().unwrap();
             let readers: IoResult<Vec<_>> = sst_list.iter()
-                .map(|sst_path| SSTableReader::open(sst_path))
+                .map(|sst_path| self.open_sstable(sst_path))
                 .collect();
 
             for mut reader in readers? {
-                reader.scan_row_full(row)?.into_iter().for_each(|(col, ts, cell)| {
-                    per_column.entry(col.clone()).or_default().push((ts, cell.clone()));
-                });
+                for (col, ts, cell) in reader.scan_row_full(row)? {
+                    visit(col, ts, cell);
+                }
             }
         }
 
-        {
-            let ms = self.memstore.lock().unwrap();
-            ms.scan_row_full(row).into_iter().for_each(|(entry_key, cell)| {
-                per_column
-                    .entry(entry_key.column.clone())
-                    .or_default()
-                    .push((entry_key.timestamp, cell.clone()));
-            });
+        let ms = self.memstore.lock().unwrap();
+        for (entry_key, cell) in ms.scan_row_full(row) {
+            visit(entry_key.column, entry_key.timestamp, cell);
         }
+        Ok(())
+    }
 
-        let result: BTreeMap<Column, Vec<(Timestamp, Vec<u8>)>> = per_column
-            .into_iter()
-            .filter_map(|(col, mut versions)| {
-                versions.sort_by(|a, b| b.0.cmp(&a.0));
-
-                let kept: Vec<(Timestamp, Vec<u8>)> = versions.into_iter()
-                    .filter_map(|(ts, cell)| {
-                        if let CellValue::Put(v) = cell {
-                            Some((ts, v))
-                        } else {
-                            None
-                        }
-                    })
-                    .take(max_versions_per_column)
-                    .collect();
+    /// Page through the whole column family in row order: return the newest live value of
+    /// every cell in the first max_rows rows after `after` (or from the first row when None),
+    /// sorted by (row, column), together with the last row examined. Pass that row as `after`
+    /// to fetch the next page; it is None once there are no rows left. A page may hold no
+    /// cells when all of its rows are deleted or expired.
+    pub fn scan_latest_after(
+        &self,
+        after: Option<&[u8]>,
+        max_rows: usize,
+    ) -> IoResult<LatestPage> {
+        let mut sources = Vec::new();
+        sources.push(self.memstore.lock().unwrap().scan_rows_after(after, max_rows));
+        for sst_path in self.sst_files.lock().unwrap().iter() {
+            let reader = self.open_sstable(sst_path)?;
+            sources.push(reader.scan_rows_after(after, max_rows));
+        }
 
-                if !kept.is_empty() {
-                    Some((col.clone(), kept))
-                } else {
-                    None
+        // Every row among the first max_rows overall is also among the first max_rows of
+        // each source holding it, so keeping the first max_rows rows of the union is exact.
+        let mut latest: BTreeMap<RowKey, BTreeMap<Column, (Timestamp, CellValue)>> = BTreeMap::new();
+        for (key, cell) in sources.into_iter().flatten() {
+            let columns = latest.entry(key.row).or_default();
+            match columns.get(&key.column) {
+                Some((ts, _)) if *ts >= key.timestamp => {}
+                _ => {
+                    columns.insert(key.column, (key.timestamp, cell));
                 }
+            }
+        }
+        let last_row = latest.keys().take(max_rows).next_back().cloned();
+
+        let cells = latest.into_iter()
+            .take(max_rows)
+            .flat_map(|(row, columns)| {
+                columns.into_iter().filter_map(move |(column, (ts, cell))| {
+                    self.live_value(ts, cell).map(|value| (row.clone(), column, ts, value))
+                })
             })
             .collect();
-
-        Ok(result)
+        Ok((cells, last_row))
     }
 
     /// Flush the MemStore into a new SSTable file, then clear the MemStore + WAL.
     pub fn flush(&self) -> IoResult<()> {
+        self.ensure_writable()?;
         let mut ms = self.memstore.lock().unwrap();
         if ms.is_empty() {
             return Ok(());
         }
 
-        let sst_seq = {
-            let existing = self.sst_files.lock().unwrap();
-            existing.len() + 1
-        };
-        let sst_name = format!("{:010}.sst", sst_seq as u64);
-        let sst_path = self.path.join(&sst_name);
+        let sst_path = self.next_sst_path();
 
-        let entries = ms.drain_all()?;
-        SSTable::create(&sst_path, &entries)?;
+        let started = Instant::now();
+        let entries = ms.entries();
+        if let Err(e) = self.write_sstable(&sst_path, &entries) {
+            // Nothing is lost: the entries are still in the MemStore and its WAL.
+            let _ = self.fs.remove_file(&sst_path);
+            return Err(e);
+        }
+        self.sst_files.lock().unwrap().push(sst_path.clone());
+        // Only now that the SSTable is written (and synced, with sync_sstables) can the WAL go.
+        ms.clear()?;
+        let duration = started.elapsed();
+        drop(ms);
 
-        self.sst_files.lock().unwrap().push(sst_path);
+        self.notify_flush(|| FlushInfo {
+            cf_name: self.name.clone(),
+            bytes: file_size(&*self.fs, &sst_path),
+            sstable: sst_path,
+            entries: entries.len(),
+            duration,
+        });
         Ok(())
     }
 
+    /// Establish a clean recovery point: flush the MemStore and leave the WAL empty, so
+    /// reopening the column family replays nothing. Writes that arrive while the
+    /// checkpoint runs may end up in the new WAL.
+    pub fn checkpoint(&self) -> IoResult<()> {
+        self.ensure_writable()?;
+        let mut ms = self.memstore.lock().unwrap();
+        if ms.is_empty() {
+            // Nothing to flush, but the WAL may still hold records if an earlier reset failed.
+            return ms.reset_wal();
+        }
+        drop(ms);
+        // flush resets the WAL itself, once the SSTable is written.
+        self.flush()
+    }
+
+    /// A snapshot of the current SSTables, oldest first, with the metadata held in their
+    /// headers and footers. Entries themselves are not read.
+    pub fn sstables(&self) -> IoResult<Vec<SSTableMeta>> {
+        // Keep compaction from removing files while we look at them.
+        let _compaction = self.compaction_lock.lock().unwrap();
+        let sst_list = self.sst_files.lock().unwrap().clone();
+
+        sst_list.into_iter()
+            .map(|path| {
+                let footer = SSTableReader::read_footer_with_fs(&*self.fs, &path)?;
+                Ok(SSTableMeta {
+                    seq: sst_seq(&path).unwrap_or(0),
+                    entry_count: SSTableReader::entry_count_with_fs(&*self.fs, &path)?,
+                    key_range: footer.map(|footer| (footer.min_row, footer.max_row)),
+                    size_bytes: self.fs.file_size(&path)?,
+                    path,
+                })
+            })
+            .collect()
+    }
+
+    /// Number of on-disk SSTables and their combined size in bytes.
+    pub fn sstable_usage(&self) -> IoResult<(usize, u64)> {
+        let sst_list = self.sst_files.lock().unwrap().clone();
+        let mut total_bytes = 0;
+        for path in &sst_list {
+            total_bytes += self.fs.file_size(path)?;
+        }
+        Ok((sst_list.len(), total_bytes))
+    }
+
+    /// Write every stored entry, including all versions and tombstones, to writer in the
+    /// checksummed dump format. Returns the number of records written.
+    pub fn export_dump(&self, writer: impl Write) -> IoResult<u64> {
+        let entries: Vec<Entry> = self.all_entries()?
+            .into_iter()
+            .map(|(key, value)| Entry { key, value })
+            .collect();
+        Dump::write(writer, &entries)
+    }
+
+    /// Every stored entry, including all versions and tombstones, in key order.
+    fn all_entries(&self) -> IoResult<BTreeMap<EntryKey, CellValue>> {
+        // Keep compaction from swapping SSTables out from under us while we read them.
+        let _compaction = self.compaction_lock.lock().unwrap();
+
+        let mut merged = BTreeMap::new();
+        let sst_list = self.sst_files.lock().unwrap().clone();
+        for path in &sst_list {
+            merged.extend(self.open_sstable(path)?.scan_all()?);
+        }
+        merged.extend(self.memstore.lock().unwrap().scan_all());
+        Ok(merged)
+    }
+
+    /// Iterate over every stored entry with a key in [start, end], tombstones and all
+    /// versions included, in key order: by row, then column, then timestamp oldest first.
+    /// This is a lower-level primitive than the row-oriented scans, for tools that need
+    /// control below the row level. The range is read when this is called; a read error is
+    /// yielded as the last item.
+    pub fn iter_entries_range(
+        &self,
+        start: EntryKey,
+        end: EntryKey,
+    ) -> impl Iterator<Item = IoResult<(EntryKey, CellValue)>> {
+        let (entries, error) = match self.entries_in_range(&start, &end) {
+            Ok(entries) => (entries, None),
+            Err(e) => (BTreeMap::new(), Some(e)),
+        };
+        entries.into_iter().map(Ok).chain(error.map(Err))
+    }
+
+    /// The entries of iter_entries_range. SSTables are read oldest first and the MemStore
+    /// last, so where two sources hold the same key the newest one wins.
+    fn entries_in_range(&self, start: &EntryKey, end: &EntryKey) -> IoResult<BTreeMap<EntryKey, CellValue>> {
+        let mut merged = BTreeMap::new();
+        if start > end {
+            return Ok(merged);
+        }
+        let in_range = |(key, _): &(EntryKey, CellValue)| start <= key && key <= end;
+        {
+            let sst_list = self.sst_files.lock().unwrap();
+            for path in sst_list.iter() {
+                let mut reader = self.open_sstable(path)?;
+                merged.extend(reader.scan_range(&start.row, &end.row)?.into_iter().filter(in_range));
+            }
+        }
+        let ms = self.memstore.lock().unwrap();
+        merged.extend(ms.scan_range(&start.row, &end.row).into_iter().filter(in_range));
+        Ok(merged)
+    }
+
+    /// Copy every live version held by other into this column family, keeping its
+    /// timestamp, e.g. to consolidate two column families. Versions a tombstone hides in
+    /// other, tombstones themselves and versions expired under other's TTL are not copied.
+    /// Where both column families hold a cell, the newest version wins on reads as usual;
+    /// a version of other with exactly the same timestamp replaces the one already here.
+    pub fn ingest_from(&self, other: &ColumnFamily) -> IoResult<()> {
+        self.ensure_writable()?;
+
+        // Walk each cell newest first, so a tombstone is seen before the versions it hides.
+        let mut live = Vec::new();
+        let mut current_cell: Option<(RowKey, Column)> = None;
+        let mut deleted = false;
+        for (key, value) in other.all_entries()?.into_iter().rev() {
+            if current_cell.as_ref().is_none_or(|(row, column)| *row != key.row || *column != key.column) {
+                current_cell = Some((key.row.clone(), key.column.clone()));
+                deleted = false;
+            }
+            match value {
+                CellValue::Delete(_) => deleted = true,
+                _ if deleted || other.is_expired(key.timestamp) => {}
+                value => live.push(Entry { key, value }),
+            }
+        }
+
+        live.reverse();
+        self.write_entries(live)
+    }
+
+    /// Load a dump written by export_dump, keeping the original timestamps.
+    /// The whole dump is validated before anything is written, so a truncated or
+    /// corrupt dump is rejected and leaves the column family unchanged.
+    /// Returns the number of records imported.
+    pub fn import_dump(&self, reader: impl Read) -> IoResult<u64> {
+        let entries = Dump::read(reader)?;
+        let count = entries.len() as u64;
+        self.write_entries(entries)?;
+        Ok(count)
+    }
 
     /// *Compact* all on-disk SSTables into one, preserving all versions (no dropping).
     /// After merging, the old SSTables are deleted, and replaced by a single new .sst.
@@ -571,6 +2272,17 @@ impl ColumnFamily {
     }
 
     /// Scan a row with a filter set applied
+    ///
+    /// The parts of the filter set are applied independently:
+    /// - `max_versions` limits the newest versions considered per column (all if unset).
+    /// - `timestamp_range` drops versions outside the range, for every column.
+    /// - `column_filters`, when present, restrict the result to the filtered columns and
+    ///   keep only the versions whose value matches. When there are none, every column of
+    ///   the row is returned (see FilterSet::match_all).
+    /// - `max_columns` then keeps only the first columns of what is left, in column order.
+    /// - `keys_only` finally replaces every value with an empty one.
+    ///
+    /// Columns left without any version are omitted from the result.
     /// 
     /// # Arguments
     /// * `row` - The row key
@@ -580,37 +2292,24 @@ impl ColumnFamily {
         row: &[u8],
         filter_set: &FilterSet,
     ) -> IoResult<BTreeMap<Column, Vec<(Timestamp, Vec<u8>)>>> {
-        let max_versions = filter_set.max_versions.unwrap_or(usize::MAX);
-        let mut result = self.scan_row_versions(row, max_versions)?;
-
-        if !filter_set.column_filters.is_empty() {
-            let filter_columns: Vec<Vec<u8>> = filter_set.column_filters
-                .iter()
-                .map(|cf| cf.column.clone())
-                .collect();
-
-            result.retain(|column, _| filter_columns.contains(column));
-        }
-
-        for column_filter in &filter_set.column_filters {
-            if let Some(versions) = result.get_mut(&column_filter.column) {
-                let filtered_versions: Vec<(Timestamp, Vec<u8>)> = versions
-                    .iter()
-                    .filter(|(ts, value)| {
-                        filter_set.timestamp_matches(*ts) && column_filter.filter.matches(value)
-                    })
-                    .cloned()
-                    .collect();
+        Ok(self.scan_row_with_filter_limited(row, filter_set)?.0)
+    }
 
-                if filtered_versions.is_empty() {
-                    result.remove(&column_filter.column);
-                } else {
-                    *versions = filtered_versions;
-                }
-            }
-        }
+    /// Like scan_row_with_filter, but also report whether the row had more matching columns
+    /// than filter_set.max_columns allowed, i.e. whether the result was cut short.
+    pub fn scan_row_with_filter_limited(
+        &self,
+        row: &[u8],
+        filter_set: &FilterSet,
+    ) -> IoResult<(RowVersions, bool)> {
+        let result = if filter_set.first_version_only {
+            self.scan_row_newest(row)?
+        } else {
+            let max_versions = filter_set.max_versions.unwrap_or(usize::MAX);
+            self.scan_row_versions(row, max_versions)?
+        };
 
-        Ok(result)
+        Ok(finish_filtered_row(result, filter_set))
     }
 
     /// Scan multiple rows with a filter set applied
@@ -627,9 +2326,13 @@ impl ColumnFamily {
     ) -> IoResult<BTreeMap<RowKey, BTreeMap<Column, Vec<(Timestamp, Vec<u8>)>>>> {
         let mut result = BTreeMap::new();
 
-        let row_keys = self.get_row_keys_in_range(start_row, end_row)?;
+        let row_keys = self.filtered_row_keys(start_row, end_row, filter_set)?;
+        let max_rows = filter_set.max_rows.unwrap_or(usize::MAX);
 
         for row_key in row_keys {
+            if result.len() == max_rows {
+                break;
+            }
             let row_result = self.scan_row_with_filter(&row_key, filter_set)?;
             if !row_result.is_empty() {
                 result.insert(row_key, row_result);
@@ -639,7 +2342,225 @@ impl ColumnFamily {
         Ok(result)
     }
 
-    /// Helper method to get all row keys in a range
+    /// Scan multiple rows with a filter set applied, like scan_with_filter, but return the
+    /// rows as a Vec in the requested order. Rows without any matching version are omitted.
+    ///
+    /// # Arguments
+    /// * `start_row` - The starting row key (inclusive)
+    /// * `end_row` - The ending row key (inclusive)
+    /// * `filter_set` - The filter set to apply
+    /// * `order` - Whether rows come lowest or highest key first
+    pub fn scan_ordered(
+        &self,
+        start_row: &[u8],
+        end_row: &[u8],
+        filter_set: &FilterSet,
+        order: ScanOrder,
+    ) -> IoResult<Vec<(RowKey, RowVersions)>> {
+        let mut row_keys = self.filtered_row_keys(start_row, end_row, filter_set)?;
+        if order == ScanOrder::Descending {
+            row_keys.reverse();
+        }
+
+        let max_rows = filter_set.max_rows.unwrap_or(usize::MAX);
+        let mut result = Vec::new();
+        for row_key in row_keys {
+            if result.len() == max_rows {
+                break;
+            }
+            let row_result = self.scan_row_with_filter(&row_key, filter_set)?;
+            if !row_result.is_empty() {
+                result.push((row_key, row_result));
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Scan multiple rows with a filter set applied, like scan_with_filter, but write each
+    /// matching version to writer as a ScanRecord as soon as its row has been read, instead
+    /// of collecting the whole result in memory. Records come in row, then column order,
+    /// newest version first. Returns the number of records written. Fails with InvalidInput
+    /// if a record is too large for its 4-byte length prefix.
+    ///
+    /// # Arguments
+    /// * `start_row` - The starting row key (inclusive)
+    /// * `end_row` - The ending row key (inclusive)
+    /// * `filter_set` - The filter set to apply
+    /// * `writer` - Where the records are written; read them back with ScanRecord::read_from
+    pub fn scan_to_writer(
+        &self,
+        start_row: &[u8],
+        end_row: &[u8],
+        filter_set: &FilterSet,
+        writer: impl Write,
+    ) -> IoResult<u64> {
+        let mut w = std::io::BufWriter::new(writer);
+        let mut count = 0u64;
+        let mut rows_left = filter_set.max_rows.unwrap_or(usize::MAX);
+
+        for row in self.filtered_row_keys(start_row, end_row, filter_set)? {
+            if rows_left == 0 {
+                break;
+            }
+            let row_result = self.scan_row_with_filter(&row, filter_set)?;
+            if !row_result.is_empty() {
+                rows_left -= 1;
+            }
+            for (column, versions) in row_result {
+                for (timestamp, value) in versions {
+                    let record = ScanRecord { row: row.clone(), column: column.clone(), timestamp, value };
+                    let buf = bincode::serialize(&record)
+                        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+                    let len = u32::try_from(buf.len()).map_err(|_| {
+                        std::io::Error::new(std::io::ErrorKind::InvalidInput, "scan record too large")
+                    })?;
+                    w.write_all(&len.to_be_bytes())?;
+                    w.write_all(&buf)?;
+                    count += 1;
+                }
+            }
+        }
+
+        w.flush()?;
+        Ok(count)
+    }
+
+    /// Scan a window of rows and timestamps in a single pass.
+    /// Only versions whose row lies in [start_row, end_row] and whose timestamp lies in
+    /// [start_ts, end_ts] are returned, newest first, up to max_versions_per_column per column.
+    /// - Tombstone versions are skipped.
+    /// - SSTables whose footer shows no overlap with the window are not read at all.
+    ///
+    /// # Arguments
+    /// * `start_row` - The starting row key (inclusive)
+    /// * `end_row` - The ending row key (inclusive)
+    /// * `start_ts` - The oldest timestamp to include (inclusive)
+    /// * `end_ts` - The newest timestamp to include (inclusive)
+    /// * `max_versions_per_column` - Maximum number of versions to return per column
+    pub fn scan_window(
+        &self,
+        start_row: &[u8],
+        end_row: &[u8],
+        start_ts: Timestamp,
+        end_ts: Timestamp,
+        max_versions_per_column: usize,
+    ) -> IoResult<BTreeMap<RowKey, RowVersions>> {
+        let mut per_cell: BTreeMap<(RowKey, Column), Vec<(Timestamp, CellValue)>> = BTreeMap::new();
+        let mut collect = |entries: Vec<(EntryKey, CellValue)>| {
+            entries.into_iter()
+                .filter(|(key, _)| key.timestamp >= start_ts && key.timestamp <= end_ts)
+                .for_each(|(key, cell)| {
+                    per_cell.entry((key.row, key.column)).or_default().push((key.timestamp, cell));
+                });
+        };
+
+        {
+            let sst_list = self.sst_files.lock().unwrap();
+            for sst_path in sst_list.iter() {
+                if let Some(footer) = SSTableReader::read_footer_with_fs(&*self.fs, sst_path)? {
+                    if !footer.overlaps_rows(start_row, end_row)
+                        || !footer.overlaps_timestamps(start_ts, end_ts) {
+                        continue;
+                    }
+                }
+                let mut reader = self.open_sstable(sst_path)?;
+                collect(reader.scan_range(start_row, end_row)?);
+            }
+        }
+
+        {
+            let ms = self.memstore.lock().unwrap();
+            collect(ms.scan_range(start_row, end_row));
+        }
+
+        let mut result = BTreeMap::new();
+        for ((row, column), mut versions) in per_cell {
+            sort_newest_first(&mut versions);
+
+            let kept: Vec<(Timestamp, Vec<u8>)> = versions.into_iter()
+                .filter_map(|(ts, cell)| self.live_value(ts, cell).map(|v| (ts, v)))
+                .take(max_versions_per_column)
+                .collect();
+
+            if !kept.is_empty() {
+                result.entry(row).or_insert_with(BTreeMap::new).insert(column, kept);
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Return the changes made to rows in [start_row, end_row] after since_ts, for
+    /// incremental consumers that poll for what's new.
+    /// Every version written strictly after since_ts is returned as stored, newest first per
+    /// column. Tombstones are included, so a row whose only change is a delete still shows
+    /// up, carrying the CellValue::Delete marker. Rows with no newer versions are omitted.
+    /// SSTables holding nothing newer than since_ts are skipped without being read.
+    pub fn scan_modified_since(
+        &self,
+        start_row: &[u8],
+        end_row: &[u8],
+        since_ts: Timestamp,
+    ) -> IoResult<BTreeMap<RowKey, RowCells>> {
+        let mut result: BTreeMap<RowKey, RowCells> = BTreeMap::new();
+        let mut collect = |entries: Vec<(EntryKey, CellValue)>| {
+            entries.into_iter()
+                .filter(|(key, _)| key.timestamp > since_ts)
+                .for_each(|(key, cell)| {
+                    result.entry(key.row).or_default()
+                        .entry(key.column).or_default()
+                        .push((key.timestamp, cell));
+                });
+        };
+
+        {
+            let sst_list = self.sst_files.lock().unwrap();
+            for sst_path in sst_list.iter() {
+                if let Some(footer) = SSTableReader::read_footer_with_fs(&*self.fs, sst_path)? {
+                    if !footer.overlaps_rows(start_row, end_row)
+                        || footer.max_timestamp <= since_ts {
+                        continue;
+                    }
+                }
+                let mut reader = self.open_sstable(sst_path)?;
+                collect(reader.scan_range(start_row, end_row)?);
+            }
+        }
+
+        {
+            let ms = self.memstore.lock().unwrap();
+            collect(ms.scan_range(start_row, end_row));
+        }
+
+        for columns in result.values_mut() {
+            for versions in columns.values_mut() {
+                sort_newest_first(versions);
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// The rows of get_row_keys_in_range that pass filter_set.row_prefix. A prefix that
+    /// sorts after start_row moves the start of the range up to it.
+    fn filtered_row_keys(&self, start_row: &[u8], end_row: &[u8], filter_set: &FilterSet) -> IoResult<Vec<RowKey>> {
+        let start_row = match &filter_set.row_prefix {
+            Some(prefix) if prefix.as_slice() > start_row => prefix.as_slice(),
+            _ => start_row,
+        };
+        if start_row > end_row {
+            return Ok(Vec::new());
+        }
+        let mut row_keys = self.get_row_keys_in_range(start_row, end_row)?;
+        row_keys.retain(|row| filter_set.row_matches(row));
+        Ok(row_keys)
+    }
+
+    /// Every row in [start_row, end_row] with at least one entry in the MemStore or in any
+    /// SSTable, so rows that only exist on disk (e.g. after a flush and reopen) are found too.
+    /// The SSTable list stays locked while it is read, so compaction cannot remove a file
+    /// halfway through.
     fn get_row_keys_in_range(&self, start_row: &[u8], end_row: &[u8]) -> IoResult<Vec<RowKey>> {
         let mut row_keys = BTreeMap::new();
 
@@ -653,7 +2574,7 @@ impl ColumnFamily {
 
         let sst_list = self.sst_files.lock().unwrap();
         for sst_path in sst_list.iter() {
-            let mut reader = SSTableReader::open(sst_path)?;
+            let mut reader = self.open_sstable(sst_path)?;
             for row_key in reader.get_row_keys_in_range(start_row, end_row)? {
                 row_keys.insert(row_key, ());
             }
@@ -674,41 +2595,164 @@ impl ColumnFamily {
         filter_set: Option<&FilterSet>,
         aggregation_set: &AggregationSet,
     ) -> IoResult<BTreeMap<Column, AggregationResult>> {
-        let data = if let Some(fs) = filter_set {
-            self.scan_row_with_filter(row, fs)?
-        } else {
-            self.scan_row_versions(row, usize::MAX)?
-        };
+        let per_column = self.row_cells_by_column(row)?;
+        let counters = counter_versions(&per_column);
+        let data = self.aggregation_versions(per_column, filter_set);
+
+        Ok(aggregation_set.apply_with_counters(&data, &counters))
+    }
+
+    /// Perform aggregations on multiple rows.
+    /// Only SSTables whose key range overlaps [start_row, end_row] are read.
+    /// 
+    /// # Arguments
+    /// * `start_row` - The starting row key (inclusive)
+    /// * `end_row` - The ending row key (inclusive)
+    /// * `filter_set` - Optional filter set to apply before aggregation
+    /// * `aggregation_set` - The aggregations to perform
+    pub fn aggregate_range(
+        &self,
+        start_row: &[u8],
+        end_row: &[u8],
+        filter_set: Option<&FilterSet>,
+        aggregation_set: &AggregationSet,
+    ) -> IoResult<BTreeMap<RowKey, BTreeMap<Column, AggregationResult>>> {
+        Ok(self.aggregate_range_with_limit(start_row, end_row, filter_set, aggregation_set, None)?.groups)
+    }
+
+    /// Like aggregate_range, but stops once max_groups rows have results, so a wide range
+    /// cannot produce an arbitrarily large result. RangeAggregation::truncated reports whether
+    /// rows past the limit were left out. A max_groups of None means no limit.
+    pub fn aggregate_range_with_limit(
+        &self,
+        start_row: &[u8],
+        end_row: &[u8],
+        filter_set: Option<&FilterSet>,
+        aggregation_set: &AggregationSet,
+        max_groups: Option<usize>,
+    ) -> IoResult<RangeAggregation> {
+        let per_row = self.range_cells_by_row(start_row, end_row)?;
+        let max_groups = max_groups.unwrap_or(usize::MAX);
+
+        let mut result = RangeAggregation::default();
+        for (row_key, per_column) in per_row {
+            if filter_set.is_some_and(|fs| !fs.row_matches(&row_key)) {
+                continue;
+            }
+            let counters = counter_versions(&per_column);
+            let data = self.aggregation_versions(per_column, filter_set);
+
+            let row_result = aggregation_set.apply_with_counters(&data, &counters);
+            if !row_result.is_empty() {
+                if result.groups.len() == max_groups {
+                    result.truncated = true;
+                    break;
+                }
+                result.groups.insert(row_key, row_result);
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Like aggregate_range, but aggregates groups of rows rather than single rows: rows are
+    /// bucketed by their first group_prefix_len bytes (the whole key if it is shorter), and
+    /// the filtered versions of every row in a bucket are pooled per column before the
+    /// aggregations run. E.g. with keys "t1:a", "t1:b" and "t2:a" and a prefix length of 2,
+    /// Sum gives one total for "t1" and one for "t2".
+    pub fn aggregate_grouped(
+        &self,
+        start_row: &[u8],
+        end_row: &[u8],
+        group_prefix_len: usize,
+        filter_set: Option<&FilterSet>,
+        aggregation_set: &AggregationSet,
+    ) -> IoResult<BTreeMap<Vec<u8>, BTreeMap<Column, AggregationResult>>> {
+        let per_row = self.range_cells_by_row(start_row, end_row)?;
+
+        let mut per_group: BTreeMap<Vec<u8>, (RowVersions, CounterVersions)> = BTreeMap::new();
+        for (row_key, per_column) in per_row {
+            if filter_set.is_some_and(|fs| !fs.row_matches(&row_key)) {
+                continue;
+            }
+            let counters = counter_versions(&per_column);
+            let data = self.aggregation_versions(per_column, filter_set);
+
+            let group_key = row_key[..group_prefix_len.min(row_key.len())].to_vec();
+            let (group, group_counters) = per_group.entry(group_key).or_default();
+            for (column, versions) in data {
+                group.entry(column).or_default().extend(versions);
+            }
+            for (column, timestamps) in counters {
+                group_counters.entry(column).or_default().extend(timestamps);
+            }
+        }
+
+        Ok(per_group
+            .into_iter()
+            .map(|(group_key, (data, counters))| (group_key, aggregation_set.apply_with_counters(&data, &counters)))
+            .filter(|(_, group_result)| !group_result.is_empty())
+            .collect())
+    }
 
-        Ok(aggregation_set.apply(&data))
+    /// The versions of one row that aggregations see under filter_set: the live versions,
+    /// at most max_versions per column (just the newest, and only if it is live, with
+    /// first_version_only), passed through the filters. keys_only is ignored, since
+    /// aggregations need the values.
+    fn aggregation_versions(&self, per_column: RowCells, filter_set: Option<&FilterSet>) -> RowVersions {
+        match filter_set {
+            Some(fs) => {
+                let versions = if fs.first_version_only {
+                    self.newest_live_by_column(per_column)
+                } else {
+                    self.live_versions_by_column(per_column, fs.max_versions.unwrap_or(usize::MAX))
+                };
+                apply_filter_set(versions, fs)
+            }
+            None => self.live_versions_by_column(per_column, usize::MAX),
+        }
     }
 
-    /// Perform aggregations on multiple rows
-    /// 
-    /// # Arguments
-    /// * `start_row` - The starting row key (inclusive)
-    /// * `end_row` - The ending row key (inclusive)
-    /// * `filter_set` - Optional filter set to apply before aggregation
-    /// * `aggregation_set` - The aggregations to perform
-    pub fn aggregate_range(
+    /// Every version in [start_row, end_row], tombstones included, by row and column.
+    /// The range is read once from each relevant source instead of once per row.
+    fn range_cells_by_row(
         &self,
         start_row: &[u8],
         end_row: &[u8],
-        filter_set: Option<&FilterSet>,
-        aggregation_set: &AggregationSet,
-    ) -> IoResult<BTreeMap<RowKey, BTreeMap<Column, AggregationResult>>> {
-        let mut result = BTreeMap::new();
+    ) -> IoResult<BTreeMap<RowKey, RowCells>> {
+        let mut per_row: BTreeMap<RowKey, RowCells> = BTreeMap::new();
+        let mut collect = |entries: Vec<(EntryKey, CellValue)>| {
+            for (key, cell) in entries {
+                per_row.entry(key.row).or_default()
+                    .entry(key.column).or_default()
+                    .push((key.timestamp, cell));
+            }
+        };
 
-        let row_keys = self.get_row_keys_in_range(start_row, end_row)?;
+        for sst_path in self.sstables_overlapping(start_row, end_row)? {
+            let mut reader = self.open_sstable(&sst_path)?;
+            collect(reader.scan_range(start_row, end_row)?);
+        }
+        {
+            let ms = self.memstore.lock().unwrap();
+            collect(ms.scan_range(start_row, end_row));
+        }
+        Ok(per_row)
+    }
 
-        for row_key in row_keys {
-            let row_result = self.aggregate(&row_key, filter_set, aggregation_set)?;
-            if !row_result.is_empty() {
-                result.insert(row_key, row_result);
+    /// The SSTables that may hold rows in [start_row, end_row], oldest first.
+    /// Tables whose footer shows a disjoint key range are left out without being opened;
+    /// legacy tables without a footer are always included.
+    fn sstables_overlapping(&self, start_row: &[u8], end_row: &[u8]) -> IoResult<Vec<PathBuf>> {
+        let sst_list = self.sst_files.lock().unwrap().clone();
+        let mut overlapping = Vec::new();
+        for sst_path in sst_list {
+            match SSTableReader::read_footer_with_fs(&*self.fs, &sst_path)? {
+                Some(footer) if !footer.overlaps_rows(start_row, end_row) => {}
+                _ => overlapping.push(sst_path),
             }
         }
-
-        Ok(result)
+        Ok(overlapping)
     }
 
     /// *Compact* SSTables with the specified options.
@@ -716,65 +2760,318 @@ impl ColumnFamily {
     /// # Arguments
     /// * `options` - Options controlling the compaction process
     pub fn compact_with_options(&self, options: CompactionOptions) -> IoResult<()> {
-        let current_paths = {
-            let guard = self.sst_files.lock().unwrap();
-            guard.clone()
-        };
+        self.ensure_writable()?;
+        let _compaction = self.compaction_lock.lock().unwrap();
+        self.compact_locked(&options)
+    }
 
-        if current_paths.len() <= 1 && options.compaction_type == CompactionType::Minor {
+    /// Flush the MemStore and then run a compaction with the specified options, in one
+    /// synchronous call. No other compaction can run in between, so the SSTable written
+    /// by the flush is included in the compaction whenever options select it.
+    ///
+    /// # Arguments
+    /// * `options` - Options controlling the compaction process
+    pub fn flush_and_compact(&self, options: CompactionOptions) -> IoResult<()> {
+        self.ensure_writable()?;
+        let _compaction = self.compaction_lock.lock().unwrap();
+        self.flush()?;
+        self.compact_locked(&options)
+    }
+
+    /// The body of compact_with_options. The caller must hold compaction_lock.
+    fn compact_locked(&self, options: &CompactionOptions) -> IoResult<()> {
+        if options.compaction_type == CompactionType::Cleanup {
+            return self.cleanup_sstables(options);
+        }
+        let tables_to_compact = self.select_tables(options);
+        if tables_to_compact.is_empty() {
             return Ok(());
         }
 
-        let mut max_seq: u64 = 0;
-        for path in current_paths.iter() {
-            if let Some(fname) = path.file_name().and_then(|os| os.to_str()) {
-                if let Some(stripped) = fname.strip_suffix(".sst") {
-                    if let Ok(seq) = stripped.parse::<u64>() {
-                        max_seq = max_seq.max(seq);
-                    }
-                }
+        self.merge_sstables(&tables_to_compact, options)
+    }
+
+    /// Rewrite each SSTable holding expired entries without them, keeping its name and so
+    /// its place in the read order, and remove the ones left empty. See CompactionType::Cleanup.
+    /// Callers must hold compaction_lock.
+    fn cleanup_sstables(&self, options: &CompactionOptions) -> IoResult<()> {
+        let started = Instant::now();
+        let mut sst_list = self.sst_files.lock().unwrap();
+
+        let mut inputs = Vec::new();
+        let mut input_bytes = 0;
+        let mut outputs = Vec::new();
+        for sst_path in sst_list.iter() {
+            let entries = self.read_entries(std::slice::from_ref(sst_path))?;
+            let entry_count = entries.len();
+            let kept = self.drop_expired_entries(entries, options);
+            if kept.len() == entry_count {
+                continue;
+            }
+            inputs.push(sst_path.clone());
+            input_bytes += file_size(&*self.fs, sst_path);
+            if kept.is_empty() {
+                self.fs.remove_file(sst_path)?;
+                continue;
             }
+            // Write beside the original and swap it in, so readers never see a partial file.
+            let tmp_path = sst_path.with_extension("sst.tmp");
+            self.write_sstable(&tmp_path, &kept)?;
+            self.fs.rename(&tmp_path, sst_path)?;
+            self.sync_sstable_dir()?;
+            outputs.push(sst_path.clone());
+        }
+        if inputs.is_empty() {
+            return Ok(());
+        }
+        sst_list.retain(|path| !inputs.contains(path) || outputs.contains(path));
+        drop(sst_list);
+
+        let duration = started.elapsed();
+        self.notify_compaction(|| CompactionInfo {
+            cf_name: self.name.clone(),
+            compaction_type: options.compaction_type,
+            output_bytes: outputs.iter().map(|path| file_size(&*self.fs, path)).sum(),
+            inputs,
+            outputs,
+            input_bytes,
+            duration,
+        });
+        Ok(())
+    }
+
+    /// The entries a cleanup keeps: everything except puts and counters past the column
+    /// family's ttl_ms or options.max_age_ms and, with options.cleanup_tombstones, tombstones
+    /// whose TTL has run out. Each entry is judged on its own, so the order is unchanged.
+    fn drop_expired_entries(&self, entries: Vec<Entry>, options: &CompactionOptions) -> Vec<Entry> {
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        entries.into_iter()
+            .filter(|entry| match entry.value {
+                CellValue::Put(_) | CellValue::Counter(_) => {
+                    !self.is_expired(entry.key.timestamp)
+                        && options.max_age_ms
+                            .map(|max_age| now.saturating_sub(entry.key.timestamp) <= max_age)
+                            .unwrap_or(true)
+                }
+                CellValue::Delete(Some(ttl_ms)) if options.cleanup_tombstones => {
+                    entry.key.timestamp + ttl_ms > now
+                }
+                CellValue::Delete(_) => true,
+            })
+            .collect()
+    }
+
+    /// Work out what compact_with_options(options) would do right now, without writing
+    /// or removing anything: which SSTables it would merge, how much it would write and
+    /// how many versions and tombstones it would drop. The inputs are read in full.
+    pub fn compaction_plan(&self, options: &CompactionOptions) -> IoResult<CompactionPlan> {
+        let _compaction = self.compaction_lock.lock().unwrap();
+
+        let inputs = self.select_tables(options);
+        if inputs.is_empty() {
+            return Ok(CompactionPlan {
+                inputs,
+                input_bytes: 0,
+                input_entries: 0,
+                output_entries: 0,
+                estimated_output_bytes: 0,
+                dropped_versions: 0,
+                dropped_tombstones: 0,
+            });
+        }
+        let input_bytes = inputs.iter().map(|path| file_size(&*self.fs, path)).sum();
+        let input = self.read_entries(&inputs)?;
+        let output = match options.compaction_type {
+            CompactionType::Cleanup => self.drop_expired_entries(input.clone(), options),
+            _ => self.filter_entries(input.clone(), options),
+        };
+
+        let tombstones = |entries: &[Entry]| {
+            entries.iter().filter(|entry| matches!(entry.value, CellValue::Delete(_))).count()
+        };
+        let dropped_tombstones = tombstones(&input) - tombstones(&output);
+        let dropped_versions = input.len() - output.len() - dropped_tombstones;
+        let output_entries = output.len();
+        let estimated_output_bytes = split_by_size(output, options.target_output_bytes).iter()
+            .map(|entries| SSTable::encoded_size(entries))
+            .sum();
+
+        Ok(CompactionPlan {
+            inputs,
+            input_bytes,
+            input_entries: input.len(),
+            output_entries,
+            estimated_output_bytes,
+            dropped_versions,
+            dropped_tombstones,
+        })
+    }
+
+    /// The SSTables a compaction with options would merge, or none if it has nothing to do.
+    fn select_tables(&self, options: &CompactionOptions) -> Vec<PathBuf> {
+        let current_paths = self.sst_files.lock().unwrap().clone();
+
+        if current_paths.len() <= 1 && options.compaction_type == CompactionType::Minor {
+            return Vec::new();
         }
-        let new_seq = max_seq + 1;
-        let new_fname = format!("{:010}.sst", new_seq);
-        let new_sst_path = self.path.join(&new_fname);
 
-        let tables_to_compact = match options.compaction_type {
-            CompactionType::Major => current_paths.clone(),
+        match options.compaction_type {
+            CompactionType::Major | CompactionType::Cleanup => current_paths,
             CompactionType::Minor => {
-                let mut tables = current_paths.clone();
+                let mut tables = current_paths;
                 tables.sort();
                 let count = (tables.len() / 2).max(2).min(tables.len());
                 tables[0..count].to_vec()
             }
-        };
+        }
+    }
 
-        if tables_to_compact.is_empty() {
+    /// Trim the versions of a single cell down to the newest max_versions values, rewriting
+    /// only the SSTables that hold the dropped versions. Much cheaper than a compaction for
+    /// one hot cell. Tombstones are kept and don't count towards max_versions. Versions still
+    /// in the MemStore are flushed first so they can be trimmed too.
+    pub fn compact_cell(&self, row: &[u8], column: &[u8], max_versions: usize) -> IoResult<()> {
+        self.ensure_writable()?;
+        if self.memstore.lock().unwrap().version_count(row, column) > 0 {
+            self.flush()?;
+        }
+
+        let _compaction = self.compaction_lock.lock().unwrap();
+        let mut sst_list = self.sst_files.lock().unwrap();
+
+        let mut versions: Vec<(Timestamp, CellValue)> = Vec::new();
+        for sst_path in sst_list.iter() {
+            let mut reader = self.open_sstable(sst_path)?;
+            versions.extend(reader.get_versions_full(row, column)?);
+        }
+        versions.sort_by_key(|(ts, _)| std::cmp::Reverse(*ts));
+        let dropped: Vec<Timestamp> = versions.iter()
+            .filter(|(_, cell)| !matches!(cell, CellValue::Delete(_)))
+            .skip(max_versions)
+            .map(|(ts, _)| *ts)
+            .collect();
+        if dropped.is_empty() {
             return Ok(());
         }
 
-        let mut merged: Vec<Entry> = Vec::new();
-        {
-            let entries: IoResult<Vec<_>> = tables_to_compact.iter()
-                .map(|path| {
-                    let reader = SSTableReader::open(path)?;
-                    let table_entries: Vec<Entry> = reader.scan_all()?
-                        .into_iter()
-                        .map(|(entry_key, cell)| Entry {
-                            key: entry_key.clone(),
-                            value: cell.clone(),
-                        })
-                        .collect();
-                    Ok(table_entries)
-                })
+        let is_dropped = |key: &EntryKey| {
+            key.row.as_slice() == row && key.column.as_slice() == column && dropped.contains(&key.timestamp)
+        };
+        let mut emptied = Vec::new();
+        for sst_path in sst_list.iter() {
+            let entries = self.open_sstable(sst_path)?.scan_all()?;
+            if !entries.iter().any(|(key, _)| is_dropped(key)) {
+                continue;
+            }
+            let kept: Vec<Entry> = entries.into_iter()
+                .filter(|(key, _)| !is_dropped(key))
+                .map(|(key, value)| Entry { key, value })
+                .collect();
+            if kept.is_empty() {
+                self.fs.remove_file(sst_path)?;
+                emptied.push(sst_path.clone());
+                continue;
+            }
+            // Write beside the original and swap it in, so readers never see a partial file.
+            let tmp_path = sst_path.with_extension("sst.tmp");
+            self.write_sstable(&tmp_path, &kept)?;
+            self.fs.rename(&tmp_path, sst_path)?;
+            self.sync_sstable_dir()?;
+        }
+        sst_list.retain(|path| !emptied.contains(path));
+        Ok(())
+    }
+
+    /// Rewrite every SSTable written in an older format (without a version header or a
+    /// footer) in the current format, so files from older builds get the newer metadata.
+    /// Unlike a compaction nothing is merged or dropped: each file is copied entry for entry
+    /// and keeps its name, so the read order is unchanged. Current files are left alone.
+    pub fn rewrite_sstables(&self) -> IoResult<()> {
+        self.ensure_writable()?;
+        let _compaction = self.compaction_lock.lock().unwrap();
+        let sst_list = self.sst_files.lock().unwrap().clone();
+
+        for sst_path in &sst_list {
+            if SSTableReader::is_current_format_with_fs(&*self.fs, sst_path)? {
+                continue;
+            }
+            let entries: Vec<Entry> = self.open_sstable(sst_path)?.scan_all()?
+                .into_iter()
+                .map(|(key, value)| Entry { key, value })
                 .collect();
+            // Write beside the original and swap it in, so readers never see a partial file.
+            let tmp_path = sst_path.with_extension("sst.tmp");
+            self.write_sstable(&tmp_path, &entries)?;
+            self.fs.rename(&tmp_path, sst_path)?;
+            self.sync_sstable_dir()?;
+        }
+        Ok(())
+    }
 
-            merged.extend(entries?.into_iter().flatten());
+    /// Merge the given SSTables into new SSTables (one, unless options.target_output_bytes
+    /// asks for a split), applying the cleanup rules in options, and swap them into the
+    /// SSTable list in place of their inputs. All outputs share one sequence number, so they
+    /// take the same place in the read order. Callers must hold compaction_lock.
+    fn merge_sstables(&self, tables_to_compact: &[PathBuf], options: &CompactionOptions) -> IoResult<()> {
+        let base_sst_path = self.next_sst_path();
+        let started = Instant::now();
+        let input_bytes: u64 = tables_to_compact.iter().map(|path| file_size(&*self.fs, path)).sum();
+
+        let merged = self.read_entries(tables_to_compact)?;
+        let merged = self.filter_entries(merged, options);
+
+        let mut new_sst_paths = Vec::new();
+        for (part, entries) in split_by_size(merged, options.target_output_bytes).iter().enumerate() {
+            let path = sst_part_path(&base_sst_path, part);
+            self.write_sstable(&path, entries)?;
+            new_sst_paths.push(path);
         }
 
+        let mut list_guard = self.sst_files.lock().unwrap();
+
+        tables_to_compact.iter().for_each(|old_path| {
+            let _ = self.fs.remove_file(old_path);
+        });
+
+        list_guard.retain(|path| !tables_to_compact.contains(path));
+        list_guard.extend(new_sst_paths.iter().cloned());
+        list_guard.sort();
+        drop(list_guard);
+
+        let duration = started.elapsed();
+        self.notify_compaction(|| CompactionInfo {
+            cf_name: self.name.clone(),
+            compaction_type: options.compaction_type,
+            inputs: tables_to_compact.to_vec(),
+            output_bytes: new_sst_paths.iter().map(|path| file_size(&*self.fs, path)).sum(),
+            outputs: new_sst_paths,
+            input_bytes,
+            duration,
+        });
+        Ok(())
+    }
+
+    /// Every entry of the given SSTables, sorted by key.
+    fn read_entries(&self, tables: &[PathBuf]) -> IoResult<Vec<Entry>> {
+        let mut merged: Vec<Entry> = Vec::new();
+        for path in tables {
+            let reader = self.open_sstable(path)?;
+            merged.extend(reader.scan_all()?
+                .into_iter()
+                .map(|(key, value)| Entry { key, value }));
+        }
         merged.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(merged)
+    }
 
-        if options.max_versions.is_some() || options.max_age_ms.is_some() || options.cleanup_tombstones {
+    /// Apply the cleanup rules in options (version limits, max age, tombstone cleanup)
+    /// to entries sorted by key, returning the entries to keep, still sorted by key.
+    fn filter_entries(&self, mut merged: Vec<Entry>, options: &CompactionOptions) -> Vec<Entry> {
+        if options.max_versions.is_some()
+            || options.max_versions_per_column.is_some()
+            || options.max_age_ms.is_some()
+            || options.cleanup_tombstones
+        {
             let now = chrono::Utc::now().timestamp_millis() as u64;
 
             let grouped: BTreeMap<(Vec<u8>, Vec<u8>), Vec<Entry>> = merged
@@ -786,19 +3083,20 @@ impl ColumnFamily {
                 });
 
             let filtered: Vec<Entry> = grouped.into_iter()
-                .flat_map(|(_, mut entries)| {
+                .flat_map(|((_, column), mut entries)| {
                     entries.sort_by(|a, b| b.key.timestamp.cmp(&a.key.timestamp));
+                    let max_versions = options.max_versions_for(&column);
 
                     entries.into_iter()
                         .fold((Vec::new(), false), |(mut kept, mut seen_non_tombstone), entry| {
                             let keep = match &entry.value {
-                                CellValue::Put(_) => {
-                                    let within_version_limit = options.max_versions
+                                CellValue::Put(_) | CellValue::Counter(_) => {
+                                    let within_version_limit = max_versions
                                         .map(|max| kept.len() < max)
                                         .unwrap_or(true);
 
                                     let within_age_limit = options.max_age_ms
-                                        .map(|max_age| now - entry.key.timestamp <= max_age)
+                                        .map(|max_age| now.saturating_sub(entry.key.timestamp) <= max_age)
                                         .unwrap_or(true);
 
                                     within_version_limit && within_age_limit
@@ -820,7 +3118,7 @@ impl ColumnFamily {
                             };
 
                             if keep {
-                                if let CellValue::Put(_) = entry.value {
+                                if !matches!(entry.value, CellValue::Delete(_)) {
                                     seen_non_tombstone = true;
                                 }
                                 kept.push(entry);
@@ -832,27 +3130,263 @@ impl ColumnFamily {
                 })
                 .collect();
 
+            // Filtering walks each cell newest-first; SSTables must stay in key order.
             merged = filtered;
+            merged.sort_by(|a, b| a.key.cmp(&b.key));
         }
 
-        SSTable::create(&new_sst_path, &merged)?;
+        merged
+    }
+
+    /// Reserve the path of the next SSTable file for this column family.
+    fn next_sst_path(&self) -> PathBuf {
+        let seq = self.next_sst_seq.fetch_add(1, Ordering::SeqCst);
+        self.path.join(format!("{:010}.sst", seq))
+    }
 
-        let mut list_guard = self.sst_files.lock().unwrap();
+    /// Block until no compaction is running or scheduled on this column family: waits for
+    /// an in-progress compaction (manual, periodic or read-triggered) to finish, and for a
+    /// read-triggered compaction that has been scheduled but not started yet to run.
+    /// Compactions started after this returns are not waited for.
+    pub fn await_compaction(&self) -> IoResult<()> {
+        loop {
+            drop(self.compaction_lock.lock().unwrap());
+            if !self.read_compaction_pending.load(Ordering::SeqCst) {
+                return Ok(());
+            }
+            // Scheduled but not yet holding compaction_lock; give it a moment to take it.
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
 
-        tables_to_compact.iter().for_each(|old_path| {
-            let _ = std::fs::remove_file(old_path);
+    /// Schedule a background compaction when a read for a single key had to consult more
+    /// SSTables than options.read_compaction_threshold allows. The compaction merges the
+    /// oldest consulted SSTable together with every newer one, so the merged file can safely
+    /// take the newest sequence number. At most one read-triggered compaction runs at a time.
+    fn maybe_schedule_read_compaction(&self, files_consulted: usize, oldest_consulted: &Path) {
+        if self.read_only
+            || !self.options.read_triggered_compaction
+            || files_consulted <= self.options.read_compaction_threshold
+        {
+            return;
+        }
+        if self.read_compaction_pending.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let cf = self.clone();
+        let oldest_consulted = oldest_consulted.to_path_buf();
+        thread::spawn(move || {
+            let result = cf.compact_from(&oldest_consulted);
+            cf.read_compaction_pending.store(false, Ordering::SeqCst);
+            if let Err(err) = result {
+                eprintln!(
+                    "[ColumnFamily::read_compaction] error in CF '{}': {:?}",
+                    cf.name, err
+                );
+            }
         });
+    }
 
-        if options.compaction_type == CompactionType::Major {
-            *list_guard = vec![new_sst_path];
-        } else {
-            list_guard.retain(|path| !tables_to_compact.contains(path));
-            list_guard.push(new_sst_path);
-            list_guard.sort(); 
+    /// Merge oldest and every newer SSTable into one, keeping all versions and tombstones.
+    fn compact_from(&self, oldest: &Path) -> IoResult<()> {
+        let _compaction = self.compaction_lock.lock().unwrap();
+        let current_paths = self.sst_files.lock().unwrap().clone();
+
+        let start = match current_paths.iter().position(|path| path == oldest) {
+            Some(start) => start,
+            None => return Ok(()),
+        };
+        if current_paths.len() - start < 2 {
+            return Ok(());
         }
 
-        Ok(())
+        let options = CompactionOptions {
+            compaction_type: CompactionType::Minor,
+            max_versions: None,
+            max_age_ms: None,
+            cleanup_tombstones: false,
+            target_output_bytes: None,
+            max_versions_per_column: None,
+        };
+        self.merge_sstables(&current_paths[start..], &options)
+    }
+}
+
+/// Apply the timestamp range, column restriction and value filters of filter_set to the
+/// versions of one row. Its max_versions must already have been applied by the caller.
+fn apply_filter_set(
+    result: BTreeMap<Column, Vec<(Timestamp, Vec<u8>)>>,
+    filter_set: &FilterSet,
+) -> BTreeMap<Column, Vec<(Timestamp, Vec<u8>)>> {
+    filter_and_limit(result, filter_set).0
+}
+
+/// The timestamps of the versions in per_column that are native counters.
+fn counter_versions(per_column: &RowCells) -> CounterVersions {
+    per_column.iter()
+        .filter_map(|(column, versions)| {
+            let timestamps: BTreeSet<Timestamp> = versions.iter()
+                .filter(|(_, cell)| matches!(cell, CellValue::Counter(_)))
+                .map(|(ts, _)| *ts)
+                .collect();
+            (!timestamps.is_empty()).then(|| (column.clone(), timestamps))
+        })
+        .collect()
+}
+
+/// Apply filter_set to one row's versions as scan_row_with_filter does, blanking the
+/// values for keys_only. The flag is true if columns were dropped for max_columns.
+fn finish_filtered_row(result: RowVersions, filter_set: &FilterSet) -> (RowVersions, bool) {
+    let (mut result, truncated) = filter_and_limit(result, filter_set);
+    if filter_set.keys_only {
+        for (_, value) in result.values_mut().flatten() {
+            *value = Vec::new();
+        }
+    }
+    (result, truncated)
+}
+
+/// Apply filter_set to one row's versions. The flag is true if columns were dropped
+/// to respect filter_set.max_columns.
+fn filter_and_limit(mut result: RowVersions, filter_set: &FilterSet) -> (RowVersions, bool) {
+    if filter_set.timestamp_range.is_some() {
+        result.retain(|_, versions| {
+            versions.retain(|(ts, _)| filter_set.timestamp_matches(*ts));
+            !versions.is_empty()
+        });
+    }
+
+    if !filter_set.column_filters.is_empty() {
+        let filter_columns: Vec<Vec<u8>> = filter_set.column_filters
+            .iter()
+            .map(|cf| cf.column.clone())
+            .collect();
+
+        result.retain(|column, _| filter_columns.contains(column));
+    }
+
+    for column_filter in &filter_set.column_filters {
+        if let Some(versions) = result.get_mut(&column_filter.column) {
+            let filtered_versions: Vec<(Timestamp, Vec<u8>)> = versions
+                .iter()
+                .filter(|(_, value)| column_filter.matches(value))
+                .cloned()
+                .collect();
+
+            if filtered_versions.is_empty() {
+                result.remove(&column_filter.column);
+            } else {
+                *versions = filtered_versions;
+            }
+        }
     }
+
+    let first_dropped = filter_set.max_columns
+        .and_then(|max_columns| result.keys().nth(max_columns).cloned());
+    match first_dropped {
+        Some(column) => {
+            result.split_off(&column);
+            (result, true)
+        }
+        None => (result, false),
+    }
+}
+
+/// The newest version of each (row, column) among cells. cells must be gathered source by
+/// source, oldest source first, so that on a timestamp tie the newer source wins as in get.
+fn newest_per_cell(cells: Vec<(EntryKey, CellValue)>) -> BTreeMap<(RowKey, Column), (Timestamp, CellValue)> {
+    let mut newest: BTreeMap<(RowKey, Column), (Timestamp, CellValue)> = BTreeMap::new();
+    for (key, cell) in cells {
+        match newest.get(&(key.row.clone(), key.column.clone())) {
+            Some((ts, _)) if *ts > key.timestamp => {}
+            _ => {
+                newest.insert((key.row, key.column), (key.timestamp, cell));
+            }
+        }
+    }
+    newest
+}
+
+/// value as a fixed-size array, or InvalidData naming the expected type if its length is wrong.
+fn fixed_bytes<const N: usize>(value: Vec<u8>, type_name: &str) -> IoResult<[u8; N]> {
+    value.try_into().map_err(|_| std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        format!("cell does not hold a {}-byte {}", N, type_name),
+    ))
+}
+
+/// The smallest row key greater than every key starting with prefix, or None when there
+/// is none (prefix is empty or all 0xFF).
+fn prefix_upper_bound(prefix: &[u8]) -> Option<Vec<u8>> {
+    let last = prefix.iter().rposition(|&b| b != 0xFF)?;
+    let mut upper = prefix[..=last].to_vec();
+    upper[last] += 1;
+    Some(upper)
+}
+
+/// The timestamp for a new version of a cell whose newest stored version is at latest: the
+/// current time, or one past latest if that is later, so the new version sorts after it
+/// even when the cell was already written within this millisecond.
+fn next_timestamp(latest: Option<Timestamp>) -> Timestamp {
+    let now = chrono::Utc::now().timestamp_millis() as u64;
+    latest.map_or(now, |latest| now.max(latest + 1))
+}
+
+/// Sort versions of a cell newest first. versions must have been gathered source by source,
+/// oldest source first (SSTables in sequence order, then the MemStore). Versions with equal
+/// timestamps then come out newest source first, the same precedence get uses, so the order
+/// is deterministic.
+fn sort_newest_first<T>(versions: &mut [(Timestamp, T)]) {
+    versions.reverse();
+    versions.sort_by_key(|(ts, _)| std::cmp::Reverse(*ts));
+}
+
+/// Size of a file in bytes, or 0 if it can't be read.
+fn file_size(fs: &dyn FileSystem, path: &Path) -> u64 {
+    fs.file_size(path).unwrap_or(0)
+}
+
+/// Parse the sequence number out of an SSTable file name ("0000000042.sst" -> 42).
+/// Extra parts of a split compaction output share it ("0000000042_0001.sst" -> 42).
+fn sst_seq(path: &Path) -> Option<u64> {
+    path.file_name()
+        .and_then(|os| os.to_str())
+        .and_then(|fname| fname.strip_suffix(".sst"))
+        .and_then(|stripped| stripped.split('_').next())
+        .and_then(|seq| seq.parse::<u64>().ok())
+}
+
+/// Path of part `part` of a compaction output whose first file is base.
+/// Later parts sort right after base and before the next sequence number.
+fn sst_part_path(base: &Path, part: usize) -> PathBuf {
+    if part == 0 {
+        return base.to_path_buf();
+    }
+    let stem = base.file_stem().and_then(|os| os.to_str()).unwrap_or_default();
+    base.with_file_name(format!("{}_{:04}.sst", stem, part))
+}
+
+/// Split sorted entries into consecutive groups of roughly target_bytes serialized bytes each,
+/// only ever splitting between rows. With no target, or no entries, there is a single group.
+fn split_by_size(entries: Vec<Entry>, target_bytes: Option<u64>) -> Vec<Vec<Entry>> {
+    let Some(target_bytes) = target_bytes else { return vec![entries] };
+
+    let mut groups: Vec<Vec<Entry>> = vec![Vec::new()];
+    let mut group_bytes = 0u64;
+    for entry in entries {
+        let current = groups.last_mut().unwrap();
+        let new_row = current.last().is_some_and(|last| last.key.row != entry.key.row);
+        if new_row && group_bytes >= target_bytes {
+            groups.push(Vec::new());
+            group_bytes = 0;
+        }
+        group_bytes += 8
+            + bincode::serialized_size(&entry.key).unwrap_or(0)
+            + bincode::serialized_size(&entry.value).unwrap_or(0);
+        groups.last_mut().unwrap().push(entry);
+    }
+    groups
 }
 
 /// A Table is a directory containing one or more ColumnFamily subdirectories.
@@ -860,40 +3394,95 @@ impl ColumnFamily {
 pub struct Table {
     path: PathBuf,
     column_families: BTreeMap<String, ColumnFamily>,
+    read_only: bool,
+    fs: Arc<dyn FileSystem>,
 }
 
 impl Table {
     /// Open (or create) a table directory.
     pub fn open(table_dir: impl AsRef<Path>) -> IoResult<Self> {
+        Self::open_with_fs(table_dir, Arc::new(OsFileSystem))
+    }
+
+    /// Open (or create) a table whose files all live on fs instead of the local disk,
+    /// e.g. a MemFileSystem in tests. Column families created later use the same fs.
+    pub fn open_with_fs(table_dir: impl AsRef<Path>, fs: Arc<dyn FileSystem>) -> IoResult<Self> {
         let tbl_path = table_dir.as_ref().to_path_buf();
-        fs::create_dir_all(&tbl_path)?;
+        fs.create_dir_all(&tbl_path)?;
         let mut cfs = BTreeMap::new();
 
-        fs::read_dir(&tbl_path)?.try_for_each(|entry_result| -> IoResult<()> {
-            let entry = entry_result?;
-            if entry.file_type()?.is_dir() {
-                let name = entry.file_name().into_string().unwrap();
-                let cf = ColumnFamily::open(&tbl_path, &name)?;
+        for path in fs.read_dir(&tbl_path)? {
+            if fs.is_dir(&path) {
+                let name = path.file_name().unwrap().to_string_lossy().into_owned();
+                let cf = ColumnFamily::open_with_fs(&tbl_path, &name, fs.clone())?;
                 cfs.insert(name, cf);
             }
-            Ok(())
-        })?;
+        }
+
+        Ok(Table {
+            path: tbl_path,
+            column_families: cfs,
+            read_only: false,
+            fs,
+        })
+    }
+
+    /// Open a new, empty table that lives entirely in memory, on its own MemFileSystem.
+    /// WAL, SSTables and options files are all kept in memory and nothing touches the disk,
+    /// so flushes and compactions work as usual but are cheap. Everything is lost once the
+    /// table and its column families are dropped. Handy for caches and tests.
+    pub fn open_in_memory() -> IoResult<Self> {
+        Self::open_with_fs("/memory", Arc::new(MemFileSystem::new()))
+    }
+
+    /// Open an existing table directory without creating or writing anything on disk.
+    /// Every column family is opened with ColumnFamily::open_read_only, and no new
+    /// column families can be created.
+    pub fn open_read_only(table_dir: impl AsRef<Path>) -> IoResult<Self> {
+        Self::open_read_only_with_fs(table_dir, Arc::new(OsFileSystem))
+    }
+
+    /// Like open_read_only, but reads the table from fs, e.g. a MemFileSystem in tests.
+    pub fn open_read_only_with_fs(table_dir: impl AsRef<Path>, fs: Arc<dyn FileSystem>) -> IoResult<Self> {
+        let tbl_path = table_dir.as_ref().to_path_buf();
+        let mut cfs = BTreeMap::new();
+
+        for path in fs.read_dir(&tbl_path)? {
+            if fs.is_dir(&path) {
+                let name = path.file_name().unwrap().to_string_lossy().into_owned();
+                let cf = ColumnFamily::open_read_only_with_fs(&tbl_path, &name, fs.clone())?;
+                cfs.insert(name, cf);
+            }
+        }
 
         Ok(Table {
             path: tbl_path,
             column_families: cfs,
+            read_only: true,
+            fs,
         })
     }
 
     /// Create a new column family named cf_name. Fails if it already exists.
     pub fn create_cf(&mut self, cf_name: &str) -> IoResult<()> {
+        self.create_cf_with_options(cf_name, ColumnFamilyOptions::default())
+    }
+
+    /// Create a new column family named cf_name with the given options. Fails if it already exists.
+    pub fn create_cf_with_options(&mut self, cf_name: &str, options: ColumnFamilyOptions) -> IoResult<()> {
+        if self.read_only {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                format!("cannot create ColumnFamily {} in a read-only table", cf_name),
+            ));
+        }
         if self.column_families.contains_key(cf_name) {
             return Err(std::io::Error::new(
                 std::io::ErrorKind::AlreadyExists,
                 format!("ColumnFamily {} already exists", cf_name),
             ));
         }
-        let cf = ColumnFamily::open(&self.path, cf_name)?;
+        let cf = ColumnFamily::open_in(self.fs.clone(), &self.path, cf_name, options)?;
         self.column_families.insert(cf_name.to_string(), cf);
         Ok(())
     }
@@ -902,4 +3491,145 @@ impl Table {
     pub fn cf(&self, cf_name: &str) -> Option<ColumnFamily> {
         self.column_families.get(cf_name).cloned()
     }
+
+    /// Run a major compaction on every column family. Column families are compacted
+    /// concurrently, at most MAX_PARALLEL_COMPACTIONS at a time. Every column family is
+    /// attempted even if another fails; the first error encountered is returned.
+    pub fn compact_all(&self) -> IoResult<()> {
+        let pending = Mutex::new(self.column_families.values());
+        let first_error = Mutex::new(None);
+        let workers = MAX_PARALLEL_COMPACTIONS.min(self.column_families.len());
+
+        thread::scope(|scope| {
+            for _ in 0..workers {
+                scope.spawn(|| loop {
+                    let Some(cf) = pending.lock().unwrap().next() else { return };
+                    if let Err(e) = cf.major_compact() {
+                        first_error.lock().unwrap().get_or_insert(e);
+                    }
+                });
+            }
+        });
+
+        match first_error.into_inner().unwrap() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    /// Look up (row, column) in each column family in order of name and return the first
+    /// live value found, together with the name of the column family that holds it.
+    pub fn get_any(&self, row: &[u8], column: &[u8]) -> IoResult<Option<(String, Vec<u8>)>> {
+        for (name, cf) in &self.column_families {
+            if let Some(value) = cf.get(row, column)? {
+                return Ok(Some((name.clone(), value)));
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_background_compaction_stops_quietly_when_dir_removed() {
+        let dir = tempdir().unwrap();
+        let cf = ColumnFamily::open(dir.path(), "test_cf").unwrap();
+
+        for i in 1..=2 {
+            cf.put(b"row1".to_vec(), b"col1".to_vec(), format!("value{}", i).into_bytes()).unwrap();
+            cf.flush().unwrap();
+        }
+        assert!(cf.background_compaction());
+
+        cf.put(b"row1".to_vec(), b"col1".to_vec(), b"value3".to_vec()).unwrap();
+        cf.flush().unwrap();
+
+        drop(dir);
+
+        assert!(cf.compact().is_err());
+        assert!(!cf.is_live());
+        assert!(!cf.background_compaction());
+    }
+
+    #[test]
+    fn test_close_stops_background_compaction() {
+        let dir = tempdir().unwrap();
+        let cf = ColumnFamily::open(dir.path(), "test_cf").unwrap();
+        assert!(cf.is_live());
+
+        let other_handle = cf.clone();
+        other_handle.close();
+
+        assert!(!cf.is_live());
+        cf.put(b"row1".to_vec(), b"col1".to_vec(), b"value1".to_vec()).unwrap();
+        assert_eq!(cf.get(b"row1", b"col1").unwrap().unwrap(), b"value1");
+    }
+
+    #[test]
+    fn test_aggregate_range_skips_disjoint_sstables() {
+        use crate::aggregation::{AggregationSet, AggregationType};
+
+        let dir = tempdir().unwrap();
+        let cf = ColumnFamily::open(dir.path(), "test_cf").unwrap();
+
+        for prefix in ["a", "m"] {
+            for i in 1..=3 {
+                cf.put(format!("{}{}", prefix, i).into_bytes(), b"count".to_vec(), i.to_string().into_bytes()).unwrap();
+            }
+            cf.flush().unwrap();
+        }
+        cf.put(b"a2".to_vec(), b"count".to_vec(), b"10".to_vec()).unwrap();
+
+        let overlapping = cf.sstables_overlapping(b"a1", b"a9").unwrap();
+        assert_eq!(overlapping.len(), 1);
+        assert_eq!(overlapping[0], cf.sst_files.lock().unwrap()[0]);
+        assert_eq!(cf.sstables_overlapping(b"a1", b"z9").unwrap().len(), 2);
+        assert!(cf.sstables_overlapping(b"x", b"z").unwrap().is_empty());
+
+        let mut aggregations = AggregationSet::new();
+        aggregations.add_aggregation(b"count".to_vec(), AggregationType::Sum);
+
+        let result = cf.aggregate_range(b"a1", b"a9", None, &aggregations).unwrap();
+        assert_eq!(result.len(), 3);
+        for (row, expected) in [(&b"a1"[..], "1"), (b"a2", "12"), (b"a3", "3")] {
+            assert_eq!(result[row][&b"count".to_vec()].to_string(), expected);
+            let single_row = cf.aggregate(row, None, &aggregations).unwrap();
+            assert_eq!(single_row[&b"count".to_vec()].to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_equal_timestamp_versions_newest_source_first() {
+        let dir = tempdir().unwrap();
+        let cf = ColumnFamily::open(dir.path(), "test_cf").unwrap();
+
+        let ts = chrono::Utc::now().timestamp_millis() as u64;
+        let put_at = |value: &[u8]| Entry {
+            key: EntryKey { row: b"row1".to_vec(), column: b"col1".to_vec(), timestamp: ts },
+            value: CellValue::Put(value.to_vec()),
+        };
+
+        // The same (row, column, timestamp) in two SSTables and the MemStore.
+        cf.write_entries(vec![put_at(b"oldest")]).unwrap();
+        cf.flush().unwrap();
+        cf.write_entries(vec![put_at(b"older")]).unwrap();
+        cf.flush().unwrap();
+        cf.write_entries(vec![put_at(b"newest")]).unwrap();
+
+        assert_eq!(cf.get(b"row1", b"col1").unwrap().unwrap(), b"newest");
+
+        let expected: Vec<(Timestamp, Vec<u8>)> = [&b"newest"[..], b"older", b"oldest"]
+            .iter()
+            .map(|v| (ts, v.to_vec()))
+            .collect();
+        for _ in 0..5 {
+            let scanned = cf.scan_row_versions(b"row1", 3).unwrap();
+            assert_eq!(scanned[&b"col1".to_vec()], expected);
+            assert_eq!(cf.get_versions(b"row1", b"col1", 3).unwrap(), expected);
+        }
+    }
 }
@@ -1,25 +1,97 @@
 use std::{
-    collections::BTreeMap,
+    collections::{BTreeMap, BTreeSet, HashMap, VecDeque},
     io::Result as IoResult,
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{Arc, Mutex},
+    time::Duration,
 };
-use tokio::task;
+use tokio::{sync::{Mutex as AsyncMutex, OwnedMutexGuard}, task, time};
 use futures::future::{self, Future};
+use futures::stream::{self, Stream};
 
 use crate::api::{
     Table as SyncTable, 
     ColumnFamily as SyncColumnFamily,
-    RowKey, Column, Timestamp, CellValue, CompactionOptions, Put, Get
+    RowKey, Column, Timestamp, CellValue, ColumnFamilyOptions, CompactionOptions, CompactionPlan, Put, Get, ReadConsistency, ScanOrder
 };
-use crate::aggregation::AggregationResult;
+use crate::aggregation::{AggregationResult, RangeAggregation};
 use crate::filter::{Filter, FilterSet};
 use crate::aggregation::AggregationSet;
 
+/// Thresholds for the size-triggered major compaction task started by
+/// `ColumnFamily::spawn_size_compaction`. A threshold of `None` is not checked.
+#[derive(Debug, Clone)]
+pub struct SizeCompactionTrigger {
+    /// Compact once there are more than this many SSTables.
+    pub max_sstable_count: Option<usize>,
+    /// Compact once the SSTables take up more than this many bytes in total.
+    pub max_total_bytes: Option<u64>,
+    /// How often the SSTable usage is checked.
+    pub check_interval: Duration,
+}
+
+impl Default for SizeCompactionTrigger {
+    fn default() -> Self {
+        Self {
+            max_sstable_count: Some(8),
+            max_total_bytes: None,
+            check_interval: Duration::from_secs(60),
+        }
+    }
+}
+
+impl SizeCompactionTrigger {
+    /// Whether a major compaction should run for the given SSTable count and size.
+    /// A single SSTable is never compacted, since merging it would not shrink anything.
+    pub fn should_compact(&self, sstable_count: usize, total_bytes: u64) -> bool {
+        if sstable_count < 2 {
+            return false;
+        }
+        self.max_sstable_count.is_some_and(|max| sstable_count > max)
+            || self.max_total_bytes.is_some_and(|max| total_bytes > max)
+    }
+}
+
+/// Number of rows fetched per blocking read by `ColumnFamily::full_scan_stream`.
+const FULL_SCAN_BATCH_ROWS: usize = 256;
+
+/// Per-row async locks, created on demand and removed once nobody holds or waits on them.
+type RowLocks = Arc<Mutex<HashMap<RowKey, Arc<AsyncMutex<()>>>>>;
+
+/// Exclusive hold on one row key, returned by `ColumnFamily::lock_row`.
+/// The lock is released when the guard is dropped.
+pub struct RowGuard {
+    row: RowKey,
+    locks: RowLocks,
+    guard: Option<OwnedMutexGuard<()>>,
+}
+
+impl RowGuard {
+    /// The row key this guard holds.
+    pub fn row(&self) -> &[u8] {
+        &self.row
+    }
+}
+
+impl Drop for RowGuard {
+    fn drop(&mut self) {
+        let Some(guard) = self.guard.take() else { return };
+        let mutex = Arc::clone(OwnedMutexGuard::mutex(&guard));
+        drop(guard);
+
+        let mut locks = self.locks.lock().unwrap();
+        // Only the map and our local clone are left: nobody else holds or awaits this row.
+        if Arc::strong_count(&mutex) == 2 {
+            locks.remove(&self.row);
+        }
+    }
+}
+
 /// Async wrapper around the synchronous ColumnFamily
 #[derive(Clone)]
 pub struct ColumnFamily {
     inner: Arc<SyncColumnFamily>,
+    row_locks: RowLocks,
 }
 
 impl ColumnFamily {
@@ -27,6 +99,27 @@ impl ColumnFamily {
     pub fn new(cf: SyncColumnFamily) -> Self {
         Self {
             inner: Arc::new(cf),
+            row_locks: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Wait for exclusive access to row and return a guard that holds it until dropped.
+    /// Use it to keep a read-modify-write spanning several awaits from interleaving
+    /// with other tasks doing the same on that row.
+    ///
+    /// The lock is advisory and in-process only: it is shared by clones of this handle,
+    /// does not stop plain writes that don't take it, and is not persisted.
+    pub async fn lock_row(&self, row: &[u8]) -> RowGuard {
+        let mutex = {
+            let mut locks = self.row_locks.lock().unwrap();
+            locks.entry(row.to_vec()).or_default().clone()
+        };
+        let guard = mutex.lock_owned().await;
+
+        RowGuard {
+            row: row.to_vec(),
+            locks: self.row_locks.clone(),
+            guard: Some(guard),
         }
     }
 
@@ -38,6 +131,30 @@ impl ColumnFamily {
         }).await.unwrap()
     }
 
+    /// Write an i64 as 8 big-endian bytes.
+    pub async fn put_i64(&self, row: RowKey, column: Column, value: i64) -> IoResult<()> {
+        let cf = self.inner.clone();
+        task::spawn_blocking(move || {
+            cf.put_i64(row, column, value)
+        }).await.unwrap()
+    }
+
+    /// Write an f64 as 8 little-endian IEEE 754 bytes.
+    pub async fn put_f64(&self, row: RowKey, column: Column, value: f64) -> IoResult<()> {
+        let cf = self.inner.clone();
+        task::spawn_blocking(move || {
+            cf.put_f64(row, column, value)
+        }).await.unwrap()
+    }
+
+    /// Write a bool as a single 0 or 1 byte.
+    pub async fn put_bool(&self, row: RowKey, column: Column, value: bool) -> IoResult<()> {
+        let cf = self.inner.clone();
+        task::spawn_blocking(move || {
+            cf.put_bool(row, column, value)
+        }).await.unwrap()
+    }
+
     /// Execute a Put operation with multiple columns.
     /// This is similar to the HBase/Java Put API.
     pub async fn execute_put(&self, put: Put) -> IoResult<()> {
@@ -47,6 +164,14 @@ impl ColumnFamily {
         }).await.unwrap()
     }
 
+    /// Write cells in order, each timestamped after the cell's newest version.
+    pub(crate) async fn write_cells(&self, cells: Vec<(RowKey, Column, CellValue)>) -> IoResult<()> {
+        let cf = self.inner.clone();
+        task::spawn_blocking(move || {
+            cf.write_cells(cells)
+        }).await.unwrap()
+    }
+
     /// Mark (row, column) as deleted by writing a tombstone at the current timestamp.
     pub async fn delete(&self, row: RowKey, column: Column) -> IoResult<()> {
         let cf = self.inner.clone();
@@ -63,6 +188,90 @@ impl ColumnFamily {
         }).await.unwrap()
     }
 
+    /// Atomically write value to (row, column) if its current value equals expected.
+    /// An expected value of None means the cell must be absent or deleted.
+    /// Returns Ok(true) if the put was applied, Ok(false) if the check failed.
+    pub async fn check_and_put(
+        &self,
+        row: RowKey,
+        column: Column,
+        expected: Option<&[u8]>,
+        value: Vec<u8>,
+    ) -> IoResult<bool> {
+        let cf = self.inner.clone();
+        let expected = expected.map(|e| e.to_vec());
+        task::spawn_blocking(move || {
+            cf.check_and_put(row, column, expected.as_deref(), value)
+        }).await.unwrap()
+    }
+
+    /// Atomically apply put if the current value of (row, column) equals expected.
+    /// An expected value of None means the cell must be absent or deleted.
+    /// Returns Ok(true) if the put was applied, Ok(false) if the check failed.
+    pub async fn check_and_execute_put(
+        &self,
+        row: RowKey,
+        column: Column,
+        expected: Option<&[u8]>,
+        put: Put,
+    ) -> IoResult<bool> {
+        let cf = self.inner.clone();
+        let expected = expected.map(|e| e.to_vec());
+        task::spawn_blocking(move || {
+            cf.check_and_execute_put(row, column, expected.as_deref(), put)
+        }).await.unwrap()
+    }
+
+    /// Write value to (row, column) and return the value that was live before it.
+    pub async fn put_and_get_previous(&self, row: RowKey, column: Column, value: Vec<u8>) -> IoResult<Option<Vec<u8>>> {
+        let cf = self.inner.clone();
+        task::spawn_blocking(move || {
+            cf.put_and_get_previous(row, column, value)
+        }).await.unwrap()
+    }
+
+    /// Delete (row, column) and return the value that was live before the delete.
+    pub async fn delete_and_get_previous(&self, row: RowKey, column: Column) -> IoResult<Option<Vec<u8>>> {
+        let cf = self.inner.clone();
+        task::spawn_blocking(move || {
+            cf.delete_and_get_previous(row, column)
+        }).await.unwrap()
+    }
+
+    /// Atomically add delta to the counter at (row, column) and return the new total.
+    pub async fn increment(&self, row: RowKey, column: Column, delta: i64) -> IoResult<i64> {
+        let cf = self.inner.clone();
+        task::spawn_blocking(move || {
+            cf.increment(row, column, delta)
+        }).await.unwrap()
+    }
+
+    /// Atomically overwrite row so it holds exactly the given columns.
+    pub async fn replace_row(&self, row: RowKey, columns: HashMap<Column, Vec<u8>>) -> IoResult<()> {
+        let cf = self.inner.clone();
+        task::spawn_blocking(move || {
+            cf.replace_row(row, columns)
+        }).await.unwrap()
+    }
+
+    /// Atomically add each delta to its counter in row and return the new totals.
+    pub async fn increment_columns(&self, row: RowKey, deltas: HashMap<Column, i64>) -> IoResult<HashMap<Column, i64>> {
+        let cf = self.inner.clone();
+        task::spawn_blocking(move || {
+            cf.increment_columns(row, deltas)
+        }).await.unwrap()
+    }
+
+    /// Read the counter at (row, column).
+    pub async fn get_counter(&self, row: &[u8], column: &[u8]) -> IoResult<Option<i64>> {
+        let cf = self.inner.clone();
+        let row = row.to_vec();
+        let column = column.to_vec();
+        task::spawn_blocking(move || {
+            cf.get_counter(&row, &column)
+        }).await.unwrap()
+    }
+
     /// Get the single latest value for (row, column).
     pub async fn get(&self, row: &[u8], column: &[u8]) -> IoResult<Option<Vec<u8>>> {
         let cf = self.inner.clone();
@@ -73,6 +282,87 @@ impl ColumnFamily {
         }).await.unwrap()
     }
 
+    /// Read a value written with put_i64.
+    pub async fn get_i64(&self, row: &[u8], column: &[u8]) -> IoResult<Option<i64>> {
+        let cf = self.inner.clone();
+        let row = row.to_vec();
+        let column = column.to_vec();
+        task::spawn_blocking(move || {
+            cf.get_i64(&row, &column)
+        }).await.unwrap()
+    }
+
+    /// Read a value written with put_f64.
+    pub async fn get_f64(&self, row: &[u8], column: &[u8]) -> IoResult<Option<f64>> {
+        let cf = self.inner.clone();
+        let row = row.to_vec();
+        let column = column.to_vec();
+        task::spawn_blocking(move || {
+            cf.get_f64(&row, &column)
+        }).await.unwrap()
+    }
+
+    /// Read a value written with put_bool.
+    pub async fn get_bool(&self, row: &[u8], column: &[u8]) -> IoResult<Option<bool>> {
+        let cf = self.inner.clone();
+        let row = row.to_vec();
+        let column = column.to_vec();
+        task::spawn_blocking(move || {
+            cf.get_bool(&row, &column)
+        }).await.unwrap()
+    }
+
+    /// Like get, but consults at most max_sstables of the newest SSTables; the flag is true
+    /// when the cell was not found and older SSTables were left unread.
+    pub async fn get_bounded(&self, row: &[u8], column: &[u8], max_sstables: usize) -> IoResult<(Option<Vec<u8>>, bool)> {
+        let cf = self.inner.clone();
+        let row = row.to_vec();
+        let column = column.to_vec();
+        task::spawn_blocking(move || {
+            cf.get_bounded(&row, &column, max_sstables)
+        }).await.unwrap()
+    }
+
+    /// Whether get would return a value for (row, column), without copying the value.
+    pub async fn exists(&self, row: &[u8], column: &[u8]) -> IoResult<bool> {
+        let cf = self.inner.clone();
+        let row = row.to_vec();
+        let column = column.to_vec();
+        task::spawn_blocking(move || {
+            cf.exists(&row, &column)
+        }).await.unwrap()
+    }
+
+    /// Like get, but may answer from a cache holding results up to max_staleness old.
+    pub async fn get_cached(&self, row: &[u8], column: &[u8], max_staleness: Duration) -> IoResult<Option<Vec<u8>>> {
+        let cf = self.inner.clone();
+        let row = row.to_vec();
+        let column = column.to_vec();
+        task::spawn_blocking(move || {
+            cf.get_cached(&row, &column, max_staleness)
+        }).await.unwrap()
+    }
+
+    /// Get the latest value of (row, column) and the timestamps of all its live versions.
+    pub async fn get_with_versions_meta(&self, row: &[u8], column: &[u8]) -> IoResult<Option<(Vec<u8>, Vec<Timestamp>)>> {
+        let cf = self.inner.clone();
+        let row = row.to_vec();
+        let column = column.to_vec();
+        task::spawn_blocking(move || {
+            cf.get_with_versions_meta(&row, &column)
+        }).await.unwrap()
+    }
+
+    /// Get the oldest live version of (row, column).
+    pub async fn get_oldest(&self, row: &[u8], column: &[u8]) -> IoResult<Option<(Timestamp, Vec<u8>)>> {
+        let cf = self.inner.clone();
+        let row = row.to_vec();
+        let column = column.to_vec();
+        task::spawn_blocking(move || {
+            cf.get_oldest(&row, &column)
+        }).await.unwrap()
+    }
+
     /// Return up to max_versions recent (timestamp, value) for (row, column).
     pub async fn get_versions(
         &self,
@@ -88,6 +378,37 @@ impl ColumnFamily {
         }).await.unwrap()
     }
 
+    /// Like get, but only consulting the sources the consistency level allows.
+    pub async fn get_with_consistency(
+        &self,
+        row: &[u8],
+        column: &[u8],
+        consistency: ReadConsistency,
+    ) -> IoResult<Option<Vec<u8>>> {
+        let cf = self.inner.clone();
+        let row = row.to_vec();
+        let column = column.to_vec();
+        task::spawn_blocking(move || {
+            cf.get_with_consistency(&row, &column, consistency)
+        }).await.unwrap()
+    }
+
+    /// Like get_versions, but only consulting the sources the consistency level allows.
+    pub async fn get_versions_with_consistency(
+        &self,
+        row: &[u8],
+        column: &[u8],
+        max_versions: usize,
+        consistency: ReadConsistency,
+    ) -> IoResult<Vec<(Timestamp, Vec<u8>)>> {
+        let cf = self.inner.clone();
+        let row = row.to_vec();
+        let column = column.to_vec();
+        task::spawn_blocking(move || {
+            cf.get_versions_with_consistency(&row, &column, max_versions, consistency)
+        }).await.unwrap()
+    }
+
     /// Return versions within a specific time range for (row, column).
     pub async fn get_versions_with_time_range(
         &self,
@@ -113,6 +434,23 @@ impl ColumnFamily {
         }).await.unwrap()
     }
 
+    /// The number of columns in row that currently hold a live value.
+    pub async fn cell_count(&self, row: &[u8]) -> IoResult<usize> {
+        let cf = self.inner.clone();
+        let row = row.to_vec();
+        task::spawn_blocking(move || {
+            cf.cell_count(&row)
+        }).await.unwrap()
+    }
+
+    /// Execute several Gets at once, returning their results in the order of gets.
+    pub async fn multi_get(&self, gets: Vec<Get>) -> IoResult<Vec<BTreeMap<Column, Vec<(Timestamp, Vec<u8>)>>>> {
+        let cf = self.inner.clone();
+        task::spawn_blocking(move || {
+            cf.multi_get(&gets)
+        }).await.unwrap()
+    }
+
     /// Execute a Get operation for a specific column.
     pub async fn execute_get_column(&self, get: Get, column: &[u8]) -> IoResult<Vec<(Timestamp, Vec<u8>)>> {
         let cf = self.inner.clone();
@@ -135,6 +473,34 @@ impl ColumnFamily {
         }).await.unwrap()
     }
 
+    /// Stream the newest live value of every cell in the column family, in (row, column) order.
+    /// Rows are read a batch at a time on the blocking thread pool, so the whole column family
+    /// is never held in memory at once. Writes made while the stream is running may or may not
+    /// be seen, depending on whether their row has already been passed.
+    pub fn full_scan_stream(&self) -> impl Stream<Item = IoResult<(RowKey, Column, Timestamp, Vec<u8>)>> {
+        let start = (self.inner.clone(), None::<RowKey>, VecDeque::new());
+        stream::unfold(Some(start), |state| async move {
+            let (cf, mut after, mut buffered) = state?;
+            while buffered.is_empty() {
+                let cf = cf.clone();
+                let from = after.clone();
+                let page = task::spawn_blocking(move || {
+                    cf.scan_latest_after(from.as_deref(), FULL_SCAN_BATCH_ROWS)
+                }).await.unwrap();
+                match page {
+                    Ok((cells, Some(last_row))) => {
+                        buffered.extend(cells);
+                        after = Some(last_row);
+                    }
+                    Ok((_, None)) => return None,
+                    Err(e) => return Some((Err(e), None)),
+                }
+            }
+            let cell = buffered.pop_front()?;
+            Some((Ok(cell), Some((cf, after, buffered))))
+        })
+    }
+
     /// Flush the MemStore into a new SSTable file, then clear the MemStore + WAL.
     pub async fn flush(&self) -> IoResult<()> {
         let cf = self.inner.clone();
@@ -143,6 +509,58 @@ impl ColumnFamily {
         }).await.unwrap()
     }
 
+    /// Copy every live version held by other into this column family, keeping timestamps.
+    pub async fn ingest_from(&self, other: &ColumnFamily) -> IoResult<()> {
+        let cf = self.inner.clone();
+        let other = other.inner.clone();
+        task::spawn_blocking(move || {
+            cf.ingest_from(&other)
+        }).await.unwrap()
+    }
+
+    /// Flush the MemStore and leave the WAL empty.
+    pub async fn checkpoint(&self) -> IoResult<()> {
+        let cf = self.inner.clone();
+        task::spawn_blocking(move || {
+            cf.checkpoint()
+        }).await.unwrap()
+    }
+
+    /// Number of on-disk SSTables and their combined size in bytes.
+    pub async fn sstable_usage(&self) -> IoResult<(usize, u64)> {
+        let cf = self.inner.clone();
+        task::spawn_blocking(move || {
+            cf.sstable_usage()
+        }).await.unwrap()
+    }
+
+    /// Spawn a tokio task that checks the SSTable usage every `check_interval` and runs a
+    /// major compaction whenever `trigger` says so. Errors are logged and the task keeps going.
+    ///
+    /// The task runs until the returned handle is aborted, which should be done on shutdown.
+    pub fn spawn_size_compaction(&self, trigger: SizeCompactionTrigger) -> task::JoinHandle<()> {
+        let cf = self.clone();
+        tokio::spawn(async move {
+            let mut interval = time::interval(trigger.check_interval);
+            interval.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                let (count, bytes) = match cf.sstable_usage().await {
+                    Ok(usage) => usage,
+                    Err(e) => {
+                        eprintln!("[ColumnFamily::spawn_size_compaction] usage check failed: {:?}", e);
+                        continue;
+                    }
+                };
+                if trigger.should_compact(count, bytes) {
+                    if let Err(e) = cf.major_compact().await {
+                        eprintln!("[ColumnFamily::spawn_size_compaction] compaction failed: {:?}", e);
+                    }
+                }
+            }
+        })
+    }
+
     /// Compact all on-disk SSTables into one, preserving all versions (no dropping).
     pub async fn compact(&self) -> IoResult<()> {
         let cf = self.inner.clone();
@@ -200,6 +618,30 @@ impl ColumnFamily {
         }).await.unwrap()
     }
 
+    /// Scan a row with a filter set applied, also reporting whether max_columns cut it short
+    pub async fn scan_row_with_filter_limited(
+        &self,
+        row: &[u8],
+        filter_set: &FilterSet,
+    ) -> IoResult<(BTreeMap<Column, Vec<(Timestamp, Vec<u8>)>>, bool)> {
+        let cf = self.inner.clone();
+        let row = row.to_vec();
+        let filter_set = filter_set.clone();
+        task::spawn_blocking(move || {
+            cf.scan_row_with_filter_limited(&row, &filter_set)
+        }).await.unwrap()
+    }
+
+    /// Every column qualifier with a live value in a row of [start_row, end_row]
+    pub async fn distinct_columns(&self, start_row: &[u8], end_row: &[u8]) -> IoResult<BTreeSet<Column>> {
+        let cf = self.inner.clone();
+        let start_row = start_row.to_vec();
+        let end_row = end_row.to_vec();
+        task::spawn_blocking(move || {
+            cf.distinct_columns(&start_row, &end_row)
+        }).await.unwrap()
+    }
+
     /// Scan multiple rows with a filter set applied
     pub async fn scan_with_filter(
         &self,
@@ -216,6 +658,23 @@ impl ColumnFamily {
         }).await.unwrap()
     }
 
+    /// Scan multiple rows with a filter set applied, returning the rows in the given order.
+    pub async fn scan_ordered(
+        &self,
+        start_row: &[u8],
+        end_row: &[u8],
+        filter_set: &FilterSet,
+        order: ScanOrder,
+    ) -> IoResult<Vec<(RowKey, BTreeMap<Column, Vec<(Timestamp, Vec<u8>)>>)>> {
+        let cf = self.inner.clone();
+        let start_row = start_row.to_vec();
+        let end_row = end_row.to_vec();
+        let filter_set = filter_set.clone();
+        task::spawn_blocking(move || {
+            cf.scan_ordered(&start_row, &end_row, &filter_set, order)
+        }).await.unwrap()
+    }
+
     /// Perform aggregations on query results
     pub async fn aggregate(
         &self,
@@ -250,6 +709,78 @@ impl ColumnFamily {
         }).await.unwrap()
     }
 
+    /// Perform aggregations on multiple rows, stopping once max_groups rows have results.
+    pub async fn aggregate_range_with_limit(
+        &self,
+        start_row: &[u8],
+        end_row: &[u8],
+        filter_set: Option<&FilterSet>,
+        aggregation_set: &AggregationSet,
+        max_groups: Option<usize>,
+    ) -> IoResult<RangeAggregation> {
+        let cf = self.inner.clone();
+        let start_row = start_row.to_vec();
+        let end_row = end_row.to_vec();
+        let filter_set = filter_set.cloned();
+        let aggregation_set = aggregation_set.clone();
+        task::spawn_blocking(move || {
+            cf.aggregate_range_with_limit(&start_row, &end_row, filter_set.as_ref(), &aggregation_set, max_groups)
+        }).await.unwrap()
+    }
+
+    /// Perform aggregations over groups of rows sharing their first group_prefix_len bytes
+    pub async fn aggregate_grouped(
+        &self,
+        start_row: &[u8],
+        end_row: &[u8],
+        group_prefix_len: usize,
+        filter_set: Option<&FilterSet>,
+        aggregation_set: &AggregationSet,
+    ) -> IoResult<BTreeMap<Vec<u8>, BTreeMap<Column, AggregationResult>>> {
+        let cf = self.inner.clone();
+        let start_row = start_row.to_vec();
+        let end_row = end_row.to_vec();
+        let filter_set = filter_set.cloned();
+        let aggregation_set = aggregation_set.clone();
+        task::spawn_blocking(move || {
+            cf.aggregate_grouped(&start_row, &end_row, group_prefix_len, filter_set.as_ref(), &aggregation_set)
+        }).await.unwrap()
+    }
+
+    /// Wait until no compaction is running or scheduled on this column family.
+    pub async fn await_compaction(&self) -> IoResult<()> {
+        let cf = self.inner.clone();
+        task::spawn_blocking(move || {
+            cf.await_compaction()
+        }).await.unwrap()
+    }
+
+    /// Trim the versions of a single cell down to the newest max_versions values.
+    pub async fn compact_cell(&self, row: &[u8], column: &[u8], max_versions: usize) -> IoResult<()> {
+        let cf = self.inner.clone();
+        let row = row.to_vec();
+        let column = column.to_vec();
+        task::spawn_blocking(move || {
+            cf.compact_cell(&row, &column, max_versions)
+        }).await.unwrap()
+    }
+
+    /// Rewrite SSTables written in an older format in the current format.
+    pub async fn rewrite_sstables(&self) -> IoResult<()> {
+        let cf = self.inner.clone();
+        task::spawn_blocking(move || {
+            cf.rewrite_sstables()
+        }).await.unwrap()
+    }
+
+    /// Work out what compact_with_options(options) would do, without doing it.
+    pub async fn compaction_plan(&self, options: CompactionOptions) -> IoResult<CompactionPlan> {
+        let cf = self.inner.clone();
+        task::spawn_blocking(move || {
+            cf.compaction_plan(&options)
+        }).await.unwrap()
+    }
+
     /// Compact SSTables with the specified options.
     pub async fn compact_with_options(&self, options: CompactionOptions) -> IoResult<()> {
         let cf = self.inner.clone();
@@ -257,6 +788,14 @@ impl ColumnFamily {
             cf.compact_with_options(options)
         }).await.unwrap()
     }
+
+    /// Flush the MemStore and then compact with the specified options, in one call.
+    pub async fn flush_and_compact(&self, options: CompactionOptions) -> IoResult<()> {
+        let cf = self.inner.clone();
+        task::spawn_blocking(move || {
+            cf.flush_and_compact(options)
+        }).await.unwrap()
+    }
 }
 
 /// Async wrapper around the synchronous Table
@@ -293,6 +832,18 @@ impl Table {
         }).await.unwrap()
     }
 
+    /// Create a new column family named cf_name with the given options asynchronously.
+    /// Fails if it already exists.
+    pub async fn create_cf_with_options(&self, cf_name: &str, options: ColumnFamilyOptions) -> IoResult<()> {
+        let inner = self.inner.clone();
+        let cf_name = cf_name.to_string();
+
+        task::spawn_blocking(move || {
+            let mut table = inner.as_ref().clone();
+            table.create_cf_with_options(&cf_name, options)
+        }).await.unwrap()
+    }
+
     /// Retrieve a handle to an existing ColumnFamily (or None if it doesn't exist).
     /// If the column family doesn't exist but was created earlier in the same process,
     /// this method will attempt to find it by opening the table directory again.
@@ -4,7 +4,7 @@ use std::{
     sync::Arc,
 };
 
-use crate::api::{ColumnFamily as SyncColumnFamily, RowKey, Column, Timestamp, Get, Put};
+use crate::api::{ColumnFamily as SyncColumnFamily, CellValue, RowKey, Column, Timestamp, Get, Put};
 use crate::async_api::ColumnFamily as AsyncColumnFamily;
 
 /// A wrapper for Get that implements Debug and Clone
@@ -85,6 +85,31 @@ pub enum BatchOperation {
     PutRow(BatchPut),
 }
 
+impl BatchOperation {
+    /// The cells this operation writes, or None if it only reads. They are timestamped when
+    /// written, each after the newest stored version of its cell, so writes to the same
+    /// cell are ordered as submitted and the last one in the batch wins.
+    fn cells(&self) -> Option<Vec<(RowKey, Column, CellValue)>> {
+        match self {
+            BatchOperation::Put(row, column, value) => {
+                Some(vec![(row.clone(), column.clone(), CellValue::Put(value.clone()))])
+            }
+            BatchOperation::Delete(row, column) => {
+                Some(vec![(row.clone(), column.clone(), CellValue::Delete(None))])
+            }
+            BatchOperation::DeleteWithTTL(row, column, ttl_ms) => {
+                Some(vec![(row.clone(), column.clone(), CellValue::Delete(*ttl_ms))])
+            }
+            BatchOperation::GetRow(_) => None,
+            BatchOperation::PutRow(batch_put) => Some(
+                batch_put.columns.iter()
+                    .map(|(column, value)| (batch_put.row.clone(), column.clone(), CellValue::Put(value.clone())))
+                    .collect(),
+            ),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Batch {
     operations: VecDeque<BatchOperation>,
@@ -160,6 +185,22 @@ impl Default for Batch {
     }
 }
 
+/// Append operations in iteration order, e.g. `batch.extend(ops)`.
+impl Extend<BatchOperation> for Batch {
+    fn extend<I: IntoIterator<Item = BatchOperation>>(&mut self, ops: I) {
+        self.operations.extend(ops);
+    }
+}
+
+/// Build a batch from a collection of operations, e.g. `ops.into_iter().collect::<Batch>()`.
+impl FromIterator<BatchOperation> for Batch {
+    fn from_iter<I: IntoIterator<Item = BatchOperation>>(ops: I) -> Self {
+        Self {
+            operations: ops.into_iter().collect(),
+        }
+    }
+}
+
 pub trait SyncBatchExt {
     fn execute_batch(&self, batch: &Batch) -> IoResult<()>;
     fn execute_batch_with_results(&self, batch: &Batch) -> IoResult<Vec<BatchResult>>;
@@ -173,25 +214,12 @@ pub enum BatchResult {
 }
 
 impl SyncBatchExt for SyncColumnFamily {
+    /// Apply the batch's writes in order. Gets are skipped.
+    /// Writes to the same cell are timestamped in submission order (see BatchOperation::cells).
     fn execute_batch(&self, batch: &Batch) -> IoResult<()> {
         for op in &batch.operations {
-            match op {
-                BatchOperation::Put(row, column, value) => {
-                    self.put(row.clone(), column.clone(), value.clone())?;
-                }
-                BatchOperation::Delete(row, column) => {
-                    self.delete(row.clone(), column.clone())?;
-                }
-                BatchOperation::DeleteWithTTL(row, column, ttl_ms) => {
-                    self.delete_with_ttl(row.clone(), column.clone(), *ttl_ms)?;
-                }
-                BatchOperation::GetRow(_) => {
-                    // Get operations don't modify state, so skipped
-                }
-                BatchOperation::PutRow(batch_put) => {
-                    let put = batch_put.to_put();
-                    self.execute_put(put)?;
-                }
+            if let Some(cells) = op.cells() {
+                self.write_cells(cells)?;
             }
         }
         Ok(())
@@ -202,26 +230,15 @@ impl SyncBatchExt for SyncColumnFamily {
 
         for op in &batch.operations {
             match op {
-                BatchOperation::Put(row, column, value) => {
-                    self.put(row.clone(), column.clone(), value.clone())?;
-                    results.push(BatchResult::Success);
-                }
-                BatchOperation::Delete(row, column) => {
-                    self.delete(row.clone(), column.clone())?;
-                    results.push(BatchResult::Success);
-                }
-                BatchOperation::DeleteWithTTL(row, column, ttl_ms) => {
-                    self.delete_with_ttl(row.clone(), column.clone(), *ttl_ms)?;
-                    results.push(BatchResult::Success);
-                }
                 BatchOperation::GetRow(batch_get) => {
                     let get = batch_get.to_get();
                     let row_data = self.execute_get(&get)?;
                     results.push(BatchResult::RowData(row_data));
                 }
-                BatchOperation::PutRow(batch_put) => {
-                    let put = batch_put.to_put();
-                    self.execute_put(put)?;
+                _ => {
+                    if let Some(cells) = op.cells() {
+                        self.write_cells(cells)?;
+                    }
                     results.push(BatchResult::Success);
                 }
             }
@@ -237,25 +254,12 @@ pub trait AsyncBatchExt {
 }
 
 impl AsyncBatchExt for AsyncColumnFamily {
+    /// Apply the batch's writes in order. Gets are skipped.
+    /// Writes to the same cell are timestamped in submission order (see BatchOperation::cells).
     async fn execute_batch(&self, batch: &Batch) -> IoResult<()> {
         for op in &batch.operations {
-            match op {
-                BatchOperation::Put(row, column, value) => {
-                    self.put(row.clone(), column.clone(), value.clone()).await?;
-                }
-                BatchOperation::Delete(row, column) => {
-                    self.delete(row.clone(), column.clone()).await?;
-                }
-                BatchOperation::DeleteWithTTL(row, column, ttl_ms) => {
-                    self.delete_with_ttl(row.clone(), column.clone(), *ttl_ms).await?;
-                }
-                BatchOperation::GetRow(_) => {
-                    // Get operations don't modify state, so skipped.
-                }
-                BatchOperation::PutRow(batch_put) => {
-                    let put = batch_put.to_put();
-                    self.execute_put(put).await?;
-                }
+            if let Some(cells) = op.cells() {
+                self.write_cells(cells).await?;
             }
         }
         Ok(())
@@ -266,26 +270,15 @@ impl AsyncBatchExt for AsyncColumnFamily {
 
         for op in &batch.operations {
             match op {
-                BatchOperation::Put(row, column, value) => {
-                    self.put(row.clone(), column.clone(), value.clone()).await?;
-                    results.push(BatchResult::Success);
-                }
-                BatchOperation::Delete(row, column) => {
-                    self.delete(row.clone(), column.clone()).await?;
-                    results.push(BatchResult::Success);
-                }
-                BatchOperation::DeleteWithTTL(row, column, ttl_ms) => {
-                    self.delete_with_ttl(row.clone(), column.clone(), *ttl_ms).await?;
-                    results.push(BatchResult::Success);
-                }
                 BatchOperation::GetRow(batch_get) => {
                     let get = batch_get.to_get();
                     let row_data = self.execute_get(get).await?;
                     results.push(BatchResult::RowData(row_data));
                 }
-                BatchOperation::PutRow(batch_put) => {
-                    let put = batch_put.to_put();
-                    self.execute_put(put).await?;
+                _ => {
+                    if let Some(cells) = op.cells() {
+                        self.write_cells(cells).await?;
+                    }
                     results.push(BatchResult::Success);
                 }
             }
@@ -390,6 +383,112 @@ mod tests {
         assert_eq!(cf.get(b"row1", b"col2").unwrap().unwrap(), b"value2");
     }
 
+    #[test]
+    fn test_sync_batch_from_iter() {
+        let dir = tempdir().unwrap();
+        let table_path = dir.path();
+
+        let mut table = Table::open(table_path).unwrap();
+        table.create_cf("test_cf").unwrap();
+        let cf = table.cf("test_cf").unwrap();
+
+        let ops: Vec<BatchOperation> = (0..100)
+            .map(|i| BatchOperation::Put(
+                format!("row{:03}", i).into_bytes(),
+                b"col1".to_vec(),
+                format!("value{}", i).into_bytes(),
+            ))
+            .collect();
+
+        let mut batch = Batch::from_iter(ops);
+        assert_eq!(batch.len(), 100);
+
+        batch.extend(vec![
+            BatchOperation::Delete(b"row000".to_vec(), b"col1".to_vec()),
+            BatchOperation::Put(b"row100".to_vec(), b"col1".to_vec(), b"value100".to_vec()),
+        ]);
+        assert_eq!(batch.len(), 102);
+
+        cf.execute_batch(&batch).unwrap();
+
+        assert!(cf.get(b"row000", b"col1").unwrap().is_none());
+        for i in 1..=100 {
+            let row = format!("row{:03}", i).into_bytes();
+            assert_eq!(cf.get(&row, b"col1").unwrap().unwrap(), format!("value{}", i).into_bytes());
+        }
+
+        let collected: Batch = (0..3)
+            .map(|i| BatchOperation::Delete(format!("row{:03}", i).into_bytes(), b"col1".to_vec()))
+            .collect();
+        assert_eq!(collected.len(), 3);
+    }
+
+    #[test]
+    fn test_sync_batch_same_cell_last_write_wins() {
+        let dir = tempdir().unwrap();
+        let table_path = dir.path();
+
+        let mut table = Table::open(table_path).unwrap();
+        table.create_cf("test_cf").unwrap();
+        let cf = table.cf("test_cf").unwrap();
+
+        let mut batch = Batch::new();
+        batch.put(b"row1".to_vec(), b"col1".to_vec(), b"first".to_vec())
+             .put(b"row1".to_vec(), b"col1".to_vec(), b"second".to_vec())
+             .put(b"row2".to_vec(), b"col1".to_vec(), b"other".to_vec())
+             .put(b"row1".to_vec(), b"col1".to_vec(), b"third".to_vec());
+
+        let results = cf.execute_batch_with_results(&batch).unwrap();
+        assert_eq!(results.len(), 4);
+
+        assert_eq!(cf.get(b"row1", b"col1").unwrap().unwrap(), b"third");
+        let versions = cf.get_versions(b"row1", b"col1", 10).unwrap();
+        let values: Vec<&[u8]> = versions.iter().map(|(_, value)| value.as_slice()).collect();
+        assert_eq!(values, vec![&b"third"[..], b"second", b"first"]);
+        assert!(versions.windows(2).all(|pair| pair[0].0 > pair[1].0));
+
+        // A put followed by a delete in the same batch leaves the cell deleted.
+        let mut batch = Batch::new();
+        batch.put(b"row2".to_vec(), b"col1".to_vec(), b"again".to_vec())
+             .delete(b"row2".to_vec(), b"col1".to_vec());
+        cf.execute_batch(&batch).unwrap();
+        assert!(cf.get(b"row2", b"col1").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_sync_batch_orders_with_surrounding_writes() {
+        let dir = tempdir().unwrap();
+        let table_path = dir.path();
+
+        let mut table = Table::open(table_path).unwrap();
+        table.create_cf("test_cf").unwrap();
+        let cf = table.cf("test_cf").unwrap();
+
+        // A put right after a batch that wrote the cell several times still wins.
+        let mut batch = Batch::new();
+        batch.put(b"row1".to_vec(), b"col1".to_vec(), b"first".to_vec())
+             .put(b"row1".to_vec(), b"col1".to_vec(), b"second".to_vec())
+             .put(b"row1".to_vec(), b"col1".to_vec(), b"third".to_vec());
+        cf.execute_batch(&batch).unwrap();
+        cf.put(b"row1".to_vec(), b"col1".to_vec(), b"after".to_vec()).unwrap();
+        assert_eq!(cf.get(b"row1", b"col1").unwrap().unwrap(), b"after");
+        assert_eq!(cf.get_versions(b"row1", b"col1", 10).unwrap().len(), 4);
+
+        // Even once those versions are flushed.
+        cf.execute_batch(&batch).unwrap();
+        cf.flush().unwrap();
+        cf.delete(b"row1".to_vec(), b"col1".to_vec()).unwrap();
+        assert!(cf.get(b"row1", b"col1").unwrap().is_none());
+
+        // A batch write right after an increment still wins.
+        cf.increment(b"row2".to_vec(), b"hits".to_vec(), 1).unwrap();
+        cf.increment(b"row2".to_vec(), b"hits".to_vec(), 1).unwrap();
+        let mut batch = Batch::new();
+        batch.put(b"row2".to_vec(), b"hits".to_vec(), b"reset".to_vec());
+        cf.execute_batch(&batch).unwrap();
+        assert_eq!(cf.get(b"row2", b"hits").unwrap().unwrap(), b"reset");
+    }
+
     #[tokio::test]
     async fn test_async_batch_operations() {
         use crate::async_api::Table as AsyncTable;
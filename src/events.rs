@@ -0,0 +1,51 @@
+use std::{path::PathBuf, time::Duration};
+
+use crate::api::CompactionType;
+
+/// Details of a completed MemStore flush.
+#[derive(Debug, Clone)]
+pub struct FlushInfo {
+    /// Name of the column family that was flushed
+    pub cf_name: String,
+    /// The SSTable written by the flush
+    pub sstable: PathBuf,
+    /// Number of entries written
+    pub entries: usize,
+    /// Size of the new SSTable in bytes
+    pub bytes: u64,
+    /// Time taken to write the SSTable
+    pub duration: Duration,
+}
+
+/// Details of a completed compaction.
+#[derive(Debug, Clone)]
+pub struct CompactionInfo {
+    /// Name of the column family that was compacted
+    pub cf_name: String,
+    /// Whether this was a minor or major compaction
+    pub compaction_type: CompactionType,
+    /// The SSTables that were merged (and removed)
+    pub inputs: Vec<PathBuf>,
+    /// The SSTables that replaced them: one, unless the compaction split its output
+    pub outputs: Vec<PathBuf>,
+    /// Combined size of the input SSTables in bytes
+    pub input_bytes: u64,
+    /// Combined size of the output SSTables in bytes
+    pub output_bytes: u64,
+    /// Time taken to merge and write the output
+    pub duration: Duration,
+}
+
+/// Receives flush and compaction events from a ColumnFamily.
+/// Register one with `ColumnFamily::add_listener`.
+///
+/// Events are delivered on a separate thread after the operation has finished,
+/// so a slow listener never stalls writes. Events from concurrent operations
+/// may arrive in any order.
+pub trait EventListener: Send + Sync {
+    /// Called after a flush has written its SSTable.
+    fn on_flush(&self, _info: FlushInfo) {}
+
+    /// Called after a compaction has replaced its input SSTables.
+    fn on_compaction(&self, _info: CompactionInfo) {}
+}
@@ -0,0 +1,248 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::{self, File, OpenOptions},
+    io::{Cursor, Error, ErrorKind, Read, Result as IoResult, Seek, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+/// A file opened for reading through a FileSystem.
+pub trait ReadFile: Read + Seek + Send {}
+
+impl<T: Read + Seek + Send> ReadFile for T {}
+
+/// A file opened for writing through a FileSystem.
+pub trait WriteFile: Write + Send {}
+
+impl<T: Write + Send> WriteFile for T {}
+
+/// The file operations the engine needs from its storage backend.
+///
+/// Tables, column families, MemStores and SSTables do all their I/O through this trait,
+/// so they can run on something other than the local disk. `OsFileSystem` is the default;
+/// `MemFileSystem` keeps everything in memory, which is handy in tests.
+pub trait FileSystem: Send + Sync {
+    /// Open an existing file for reading.
+    fn open(&self, path: &Path) -> IoResult<Box<dyn ReadFile>>;
+
+    /// Create a file for writing, truncating it if it already exists.
+    fn create(&self, path: &Path) -> IoResult<Box<dyn WriteFile>>;
+
+    /// Open a file for appending, creating it if it doesn't exist.
+    fn append(&self, path: &Path) -> IoResult<Box<dyn WriteFile>>;
+
+    /// Paths of the files and directories directly inside a directory.
+    fn read_dir(&self, path: &Path) -> IoResult<Vec<PathBuf>>;
+
+    /// Create a directory and any missing parents.
+    fn create_dir_all(&self, path: &Path) -> IoResult<()>;
+
+    fn remove_file(&self, path: &Path) -> IoResult<()>;
+
+    /// Move a file, replacing the destination if it exists.
+    fn rename(&self, from: &Path, to: &Path) -> IoResult<()>;
+
+    fn exists(&self, path: &Path) -> bool;
+
+    fn is_dir(&self, path: &Path) -> bool;
+
+    /// Size of a file in bytes.
+    fn file_size(&self, path: &Path) -> IoResult<u64>;
+
+    /// Make a file's contents durable, e.g. with fsync. Backends with nothing to make
+    /// durable can keep this default, which does nothing.
+    fn sync_file(&self, _path: &Path) -> IoResult<()> {
+        Ok(())
+    }
+
+    /// Make a directory's entries durable, so files just created or renamed in it survive
+    /// a crash. Does nothing by default.
+    fn sync_dir(&self, _path: &Path) -> IoResult<()> {
+        Ok(())
+    }
+}
+
+/// The local filesystem, via std::fs.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OsFileSystem;
+
+impl FileSystem for OsFileSystem {
+    fn open(&self, path: &Path) -> IoResult<Box<dyn ReadFile>> {
+        Ok(Box::new(File::open(path)?))
+    }
+
+    fn create(&self, path: &Path) -> IoResult<Box<dyn WriteFile>> {
+        Ok(Box::new(File::create(path)?))
+    }
+
+    fn append(&self, path: &Path) -> IoResult<Box<dyn WriteFile>> {
+        Ok(Box::new(OpenOptions::new().create(true).append(true).open(path)?))
+    }
+
+    fn read_dir(&self, path: &Path) -> IoResult<Vec<PathBuf>> {
+        fs::read_dir(path)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect()
+    }
+
+    fn create_dir_all(&self, path: &Path) -> IoResult<()> {
+        fs::create_dir_all(path)
+    }
+
+    fn remove_file(&self, path: &Path) -> IoResult<()> {
+        fs::remove_file(path)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> IoResult<()> {
+        fs::rename(from, to)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        path.exists()
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    fn file_size(&self, path: &Path) -> IoResult<u64> {
+        Ok(fs::metadata(path)?.len())
+    }
+
+    fn sync_file(&self, path: &Path) -> IoResult<()> {
+        File::open(path)?.sync_all()
+    }
+
+    fn sync_dir(&self, path: &Path) -> IoResult<()> {
+        // Directories can only be opened and synced like this on Unix; elsewhere the
+        // file sync has to do.
+        if cfg!(unix) {
+            File::open(path)?.sync_all()?;
+        }
+        Ok(())
+    }
+}
+
+/// A filesystem held entirely in memory. Nothing is persisted: all files are gone once
+/// the last handle to it is dropped. Share one between opens with an Arc.
+///
+/// Readers see a snapshot of a file taken when it was opened.
+#[derive(Default)]
+pub struct MemFileSystem {
+    state: Mutex<MemState>,
+}
+
+#[derive(Default)]
+struct MemState {
+    files: BTreeMap<PathBuf, Arc<Mutex<Vec<u8>>>>,
+    dirs: BTreeSet<PathBuf>,
+}
+
+impl MemState {
+    /// Fail with NotFound unless the directory that would hold path exists.
+    fn check_parent(&self, path: &Path) -> IoResult<()> {
+        match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() && !self.dirs.contains(parent) => {
+                Err(not_found(parent))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Writes to a file held by a MemFileSystem.
+struct MemFile(Arc<Mutex<Vec<u8>>>);
+
+impl Write for MemFile {
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        Ok(())
+    }
+}
+
+fn not_found(path: &Path) -> Error {
+    Error::new(ErrorKind::NotFound, format!("{} does not exist", path.display()))
+}
+
+impl MemFileSystem {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl FileSystem for MemFileSystem {
+    fn open(&self, path: &Path) -> IoResult<Box<dyn ReadFile>> {
+        let state = self.state.lock().unwrap();
+        let file = state.files.get(path).ok_or_else(|| not_found(path))?;
+        let contents = file.lock().unwrap().clone();
+        Ok(Box::new(Cursor::new(contents)))
+    }
+
+    fn create(&self, path: &Path) -> IoResult<Box<dyn WriteFile>> {
+        let mut state = self.state.lock().unwrap();
+        state.check_parent(path)?;
+        let file = Arc::new(Mutex::new(Vec::new()));
+        state.files.insert(path.to_path_buf(), file.clone());
+        Ok(Box::new(MemFile(file)))
+    }
+
+    fn append(&self, path: &Path) -> IoResult<Box<dyn WriteFile>> {
+        let mut state = self.state.lock().unwrap();
+        state.check_parent(path)?;
+        let file = state.files.entry(path.to_path_buf()).or_default().clone();
+        Ok(Box::new(MemFile(file)))
+    }
+
+    fn read_dir(&self, path: &Path) -> IoResult<Vec<PathBuf>> {
+        let state = self.state.lock().unwrap();
+        if !state.dirs.contains(path) {
+            return Err(not_found(path));
+        }
+        let in_dir = |p: &&PathBuf| p.parent() == Some(path);
+        Ok(state.files.keys().filter(in_dir)
+            .chain(state.dirs.iter().filter(in_dir))
+            .cloned()
+            .collect())
+    }
+
+    fn create_dir_all(&self, path: &Path) -> IoResult<()> {
+        let mut state = self.state.lock().unwrap();
+        for dir in path.ancestors().filter(|dir| !dir.as_os_str().is_empty()) {
+            state.dirs.insert(dir.to_path_buf());
+        }
+        Ok(())
+    }
+
+    fn remove_file(&self, path: &Path) -> IoResult<()> {
+        let mut state = self.state.lock().unwrap();
+        state.files.remove(path).map(|_| ()).ok_or_else(|| not_found(path))
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> IoResult<()> {
+        let mut state = self.state.lock().unwrap();
+        state.check_parent(to)?;
+        let file = state.files.remove(from).ok_or_else(|| not_found(from))?;
+        state.files.insert(to.to_path_buf(), file);
+        Ok(())
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        let state = self.state.lock().unwrap();
+        state.files.contains_key(path) || state.dirs.contains(path)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.state.lock().unwrap().dirs.contains(path)
+    }
+
+    fn file_size(&self, path: &Path) -> IoResult<u64> {
+        let state = self.state.lock().unwrap();
+        let file = state.files.get(path).ok_or_else(|| not_found(path))?;
+        let len = file.lock().unwrap().len() as u64;
+        Ok(len)
+    }
+}
@@ -1,3 +1,5 @@
+use std::{fmt, io};
+
 use serde::{Deserialize, Serialize};
 use regex::Regex as RegexPattern;
 
@@ -11,19 +13,42 @@ pub enum Filter {
     GreaterThanOrEqual(Vec<u8>),
     LessThan(Vec<u8>),
     LessThanOrEqual(Vec<u8>),
+    /// Match values between low and high, both bounds inclusive (lexicographic)
+    Between(Vec<u8>, Vec<u8>),
+    /// Match values between lower and upper (lexicographic). Both bounds are included when
+    /// inclusive is set and both are excluded otherwise, so with equal bounds an inclusive
+    /// Range matches exactly that value and an exclusive one matches nothing.
+    Range { lower: Vec<u8>, upper: Vec<u8>, inclusive: bool },
     Contains(Vec<u8>),
+    /// Match values that begin with the given bytes. A plain byte comparison, much
+    /// cheaper than an equivalent Regex.
     StartsWith(Vec<u8>),
+    /// Match values that end with the given bytes
     EndsWith(Vec<u8>),
     /// Match values that match the given regex pattern
     /// The value must be valid UTF-8 and the pattern must be a valid regex
     /// Returns false if the value is not valid UTF-8 or the pattern is not a valid regex
     Regex(String),
-    /// Combine multiple filters with AND logic (all must match)
+    /// Combine multiple filters with AND logic (all must match).
+    /// Evaluation stops at the first filter that doesn't match.
     And(Vec<Filter>),
-    /// Combine multiple filters with OR logic (any must match)
+    /// Combine multiple filters with OR logic (any must match).
+    /// Evaluation stops at the first filter that matches.
     Or(Vec<Filter>),
     /// Negate the result of the contained filter
     Not(Box<Filter>),
+    /// Match values that parse as a UTF-8 decimal integer v with low <= v < high,
+    /// or low <= v <= high when include_high is set. Values that don't parse never match.
+    NumericBetween { low: i64, high: i64, include_high: bool },
+    /// Match values that parse as a UTF-8 decimal integer greater than the operand,
+    /// so b"100" matches NumericGreaterThan(50) and b"9" does not. Values that don't
+    /// parse never match.
+    NumericGreaterThan(i64),
+    /// Match values that parse as a UTF-8 decimal integer less than the operand
+    NumericLessThan(i64),
+    /// Match values that parse as a UTF-8 decimal integer equal to the operand,
+    /// e.g. b"007" matches NumericEqual(7)
+    NumericEqual(i64),
 }
 
 impl Filter {
@@ -36,6 +61,13 @@ impl Filter {
             Filter::GreaterThanOrEqual(target) => value >= target.as_slice(),
             Filter::LessThan(target) => value < target.as_slice(),
             Filter::LessThanOrEqual(target) => value <= target.as_slice(),
+            Filter::Between(low, high) => value >= low.as_slice() && value <= high.as_slice(),
+            Filter::Range { lower, upper, inclusive: true } => {
+                value >= lower.as_slice() && value <= upper.as_slice()
+            },
+            Filter::Range { lower, upper, inclusive: false } => {
+                value > lower.as_slice() && value < upper.as_slice()
+            },
             Filter::Contains(target) => contains_subsequence(value, target),
             Filter::StartsWith(target) => value.starts_with(target),
             Filter::EndsWith(target) => value.ends_with(target),
@@ -53,10 +85,132 @@ impl Filter {
             Filter::And(filters) => filters.iter().all(|f| f.matches(value)),
             Filter::Or(filters) => filters.iter().any(|f| f.matches(value)),
             Filter::Not(filter) => !filter.matches(value),
+            Filter::NumericBetween { low, high, include_high } => {
+                match parse_integer(value) {
+                    Some(v) if *include_high => *low <= v && v <= *high,
+                    Some(v) => *low <= v && v < *high,
+                    None => false,
+                }
+            },
+            Filter::NumericGreaterThan(target) => parse_integer(value).is_some_and(|v| v > *target),
+            Filter::NumericLessThan(target) => parse_integer(value).is_some_and(|v| v < *target),
+            Filter::NumericEqual(target) => parse_integer(value) == Some(*target),
+        }
+    }
+
+    /// Apply the filter treating both the value and the filter operands as numbers.
+    /// Comparison filters parse their operands as UTF-8 decimal numbers; a value or
+    /// operand that does not parse never matches. Non-comparison filters (Contains,
+    /// Regex, ...) fall back to their byte-wise behavior.
+    pub fn matches_numeric(&self, value: &[u8]) -> bool {
+        let compare = |target: &[u8], accept: fn(f64, f64) -> bool| {
+            match (parse_number(value), parse_number(target)) {
+                (Some(v), Some(t)) => accept(v, t),
+                _ => false,
+            }
+        };
+
+        match self {
+            Filter::Equal(target) => compare(target, |v, t| v == t),
+            Filter::NotEqual(target) => compare(target, |v, t| v != t),
+            Filter::GreaterThan(target) => compare(target, |v, t| v > t),
+            Filter::GreaterThanOrEqual(target) => compare(target, |v, t| v >= t),
+            Filter::LessThan(target) => compare(target, |v, t| v < t),
+            Filter::LessThanOrEqual(target) => compare(target, |v, t| v <= t),
+            Filter::Between(low, high) => {
+                compare(low, |v, t| v >= t) && compare(high, |v, t| v <= t)
+            },
+            Filter::Range { lower, upper, inclusive: true } => {
+                compare(lower, |v, t| v >= t) && compare(upper, |v, t| v <= t)
+            },
+            Filter::Range { lower, upper, inclusive: false } => {
+                compare(lower, |v, t| v > t) && compare(upper, |v, t| v < t)
+            },
+            Filter::And(filters) => filters.iter().all(|f| f.matches_numeric(value)),
+            Filter::Or(filters) => filters.iter().any(|f| f.matches_numeric(value)),
+            Filter::Not(filter) => !filter.matches_numeric(value),
+            _ => self.matches(value),
+        }
+    }
+
+    /// Check that the filter can be evaluated as intended: every Regex pattern compiles and,
+    /// when numeric is set, every comparison operand parses as a number. Filters that fail
+    /// these checks silently match nothing at scan time.
+    pub fn validate(&self, numeric: bool) -> Result<(), FilterError> {
+        let check_number = |operand: &Vec<u8>| {
+            if numeric && parse_number(operand).is_none() {
+                Err(FilterError::InvalidNumericOperand(operand.clone()))
+            } else {
+                Ok(())
+            }
+        };
+
+        match self {
+            Filter::Regex(pattern) => RegexPattern::new(pattern)
+                .map(|_| ())
+                .map_err(|e| FilterError::InvalidRegex { pattern: pattern.clone(), message: e.to_string() }),
+            Filter::Equal(target)
+            | Filter::NotEqual(target)
+            | Filter::GreaterThan(target)
+            | Filter::GreaterThanOrEqual(target)
+            | Filter::LessThan(target)
+            | Filter::LessThanOrEqual(target) => check_number(target),
+            Filter::Between(low, high) | Filter::Range { lower: low, upper: high, .. } => {
+                check_number(low)?;
+                check_number(high)
+            },
+            Filter::And(filters) | Filter::Or(filters) => {
+                filters.iter().try_for_each(|f| f.validate(numeric))
+            },
+            Filter::Not(filter) => filter.validate(numeric),
+            _ => Ok(()),
         }
     }
 }
 
+/// Why FilterSet::validate rejected a filter set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterError {
+    /// A Filter::Regex pattern that doesn't compile, with the regex crate's explanation
+    InvalidRegex { pattern: String, message: String },
+    /// An operand of a numeric column filter that doesn't parse as a number
+    InvalidNumericOperand(Vec<u8>),
+    /// A filter on the given column failed one of the checks above
+    Column { column: Vec<u8>, error: Box<FilterError> },
+}
+
+impl fmt::Display for FilterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FilterError::InvalidRegex { pattern, message } => {
+                write!(f, "invalid regex {:?}: {}", pattern, message)
+            },
+            FilterError::InvalidNumericOperand(operand) => {
+                write!(f, "numeric filter operand {:?} is not a number", String::from_utf8_lossy(operand))
+            },
+            FilterError::Column { column, error } => {
+                write!(f, "filter on column {:?}: {}", String::from_utf8_lossy(column), error)
+            },
+        }
+    }
+}
+
+impl std::error::Error for FilterError {}
+
+impl From<FilterError> for io::Error {
+    fn from(e: FilterError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidInput, e.to_string())
+    }
+}
+
+fn parse_number(bytes: &[u8]) -> Option<f64> {
+    std::str::from_utf8(bytes).ok()?.trim().parse::<f64>().ok()
+}
+
+fn parse_integer(bytes: &[u8]) -> Option<i64> {
+    std::str::from_utf8(bytes).ok()?.trim().parse::<i64>().ok()
+}
+
 fn contains_subsequence(value: &[u8], subsequence: &[u8]) -> bool {
     if subsequence.is_empty() {
         return true;
@@ -77,6 +231,20 @@ fn contains_subsequence(value: &[u8], subsequence: &[u8]) -> bool {
 pub struct ColumnFilter {
     pub column: Vec<u8>,
     pub filter: Filter,
+    /// Compare values as numbers rather than lexicographically
+    #[serde(default)]
+    pub numeric: bool,
+}
+
+impl ColumnFilter {
+    /// Apply the filter to a value, honoring the numeric flag
+    pub fn matches(&self, value: &[u8]) -> bool {
+        if self.numeric {
+            self.filter.matches_numeric(value)
+        } else {
+            self.filter.matches(value)
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -84,6 +252,24 @@ pub struct FilterSet {
     pub column_filters: Vec<ColumnFilter>,
     pub timestamp_range: Option<(Option<u64>, Option<u64>)>,
     pub max_versions: Option<usize>,
+    /// At most this many columns are returned per row, the first ones in column order
+    #[serde(default)]
+    pub max_columns: Option<usize>,
+    /// Only rows whose key starts with this prefix are returned by multi-row scans
+    #[serde(default)]
+    pub row_prefix: Option<Vec<u8>>,
+    /// Return every matching version with an empty value, like HBase's KeyOnlyFilter.
+    /// Value filters still see the real values. Aggregations ignore this flag.
+    #[serde(default)]
+    pub keys_only: bool,
+    /// Multi-row scans stop after this many rows with matching versions, in scan order.
+    /// Rows left out by the other filters don't count.
+    #[serde(default)]
+    pub max_rows: Option<usize>,
+    /// Keep only the newest version of each column, whatever max_versions says, like
+    /// HBase's FirstKeyOnlyFilter. A column whose newest version is a delete is left out.
+    #[serde(default)]
+    pub first_version_only: bool,
 }
 
 impl FilterSet {
@@ -92,11 +278,29 @@ impl FilterSet {
             column_filters: Vec::new(),
             timestamp_range: None,
             max_versions: None,
+            max_columns: None,
+            row_prefix: None,
+            keys_only: false,
+            max_rows: None,
+            first_version_only: false,
         }
     }
 
+    /// A filter set that matches every column and every version of a row.
+    /// Same as FilterSet::new(); use it where "no filtering" is the intent.
+    pub fn match_all() -> Self {
+        Self::new()
+    }
+
     pub fn add_column_filter(&mut self, column: Vec<u8>, filter: Filter) -> &mut Self {
-        self.column_filters.push(ColumnFilter { column, filter });
+        self.column_filters.push(ColumnFilter { column, filter, numeric: false });
+        self
+    }
+
+    /// Add a filter whose comparisons parse the column's values as numbers,
+    /// so that e.g. "15000" is greater than "2000".
+    pub fn add_numeric_column_filter(&mut self, column: Vec<u8>, filter: Filter) -> &mut Self {
+        self.column_filters.push(ColumnFilter { column, filter, numeric: true });
         self
     }
 
@@ -110,6 +314,53 @@ impl FilterSet {
         self
     }
 
+    /// Cap the number of columns returned per row, as a guard against very wide rows.
+    pub fn with_max_columns(&mut self, max_columns: usize) -> &mut Self {
+        self.max_columns = Some(max_columns);
+        self
+    }
+
+    /// Restrict multi-row scans to rows whose key starts with prefix, e.g. b"tenant42:".
+    /// The prefix narrows the scan's start/end range rather than replacing it.
+    pub fn with_row_prefix(&mut self, prefix: Vec<u8>) -> &mut Self {
+        self.row_prefix = Some(prefix);
+        self
+    }
+
+    /// Strip the values from scan results, keeping rows, columns and timestamps.
+    pub fn with_keys_only(&mut self) -> &mut Self {
+        self.keys_only = true;
+        self
+    }
+
+    /// Return at most max_rows matching rows from multi-row scans, like HBase's PageFilter.
+    pub fn with_max_rows(&mut self, max_rows: usize) -> &mut Self {
+        self.max_rows = Some(max_rows);
+        self
+    }
+
+    /// Return just the newest version of each column, e.g. for cheap existence checks.
+    pub fn with_first_version_only(&mut self) -> &mut Self {
+        self.first_version_only = true;
+        self
+    }
+
+    /// Check every column filter with Filter::validate, so a bad query can be rejected up
+    /// front instead of quietly returning no rows. The first failure is returned.
+    pub fn validate(&self) -> Result<(), FilterError> {
+        self.column_filters.iter().try_for_each(|cf| {
+            cf.filter.validate(cf.numeric).map_err(|error| FilterError::Column {
+                column: cf.column.clone(),
+                error: Box::new(error),
+            })
+        })
+    }
+
+    /// Whether a row key passes row_prefix (always true when no prefix is set).
+    pub fn row_matches(&self, row: &[u8]) -> bool {
+        self.row_prefix.as_ref().is_none_or(|prefix| row.starts_with(prefix))
+    }
+
     pub fn timestamp_matches(&self, timestamp: u64) -> bool {
         if let Some((min, max)) = self.timestamp_range {
             let min_match = min.map_or(true, |min_ts| timestamp >= min_ts);
@@ -0,0 +1,192 @@
+use std::io::{Error, ErrorKind, Result as IoResult};
+
+use crate::api::RowKey;
+
+/// Escapes a 0x00 byte inside an encoded string or byte field.
+const ESCAPE: u8 = 0xFF;
+/// Terminates an encoded string or byte field. Sorts below every escaped byte,
+/// so a field sorts before any longer field it is a prefix of.
+const TERMINATOR: u8 = 0x01;
+
+/// Builds composite row keys whose byte order matches the logical order of their parts.
+///
+/// Parts are compared left to right, just like a tuple:
+/// - integers are stored big-endian (signed ones with the sign bit flipped), so they sort numerically;
+/// - strings and byte fields are escaped and terminated, so they sort lexicographically
+///   and a shorter field sorts before a longer one that starts with it.
+///
+/// Read a key back with `KeyReader`, using the same sequence of parts.
+#[derive(Debug, Clone, Default)]
+pub struct KeyBuilder {
+    buf: Vec<u8>,
+}
+
+impl KeyBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append an unsigned integer.
+    pub fn add_u64(&mut self, value: u64) -> &mut Self {
+        self.buf.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    /// Append a signed integer. Negative values sort before positive ones.
+    pub fn add_i64(&mut self, value: i64) -> &mut Self {
+        self.add_u64((value as u64) ^ (1 << 63))
+    }
+
+    /// Append a string.
+    pub fn add_str(&mut self, value: &str) -> &mut Self {
+        self.add_bytes(value.as_bytes())
+    }
+
+    /// Append an arbitrary byte field.
+    pub fn add_bytes(&mut self, value: &[u8]) -> &mut Self {
+        for &b in value {
+            self.buf.push(b);
+            if b == 0 {
+                self.buf.push(ESCAPE);
+            }
+        }
+        self.buf.extend_from_slice(&[0, TERMINATOR]);
+        self
+    }
+
+    /// The encoded key.
+    pub fn build(&self) -> RowKey {
+        self.buf.clone()
+    }
+}
+
+/// Decodes a key written by `KeyBuilder`, one part at a time in the order they were added.
+/// Every read fails with `InvalidData` if the key does not hold the requested part.
+#[derive(Debug, Clone)]
+pub struct KeyReader<'a> {
+    key: &'a [u8],
+}
+
+impl<'a> KeyReader<'a> {
+    pub fn new(key: &'a [u8]) -> Self {
+        Self { key }
+    }
+
+    /// Read an unsigned integer written by `KeyBuilder::add_u64`.
+    pub fn read_u64(&mut self) -> IoResult<u64> {
+        if self.key.len() < 8 {
+            return Err(Error::new(ErrorKind::InvalidData, "key too short for an integer"));
+        }
+        let (int, rest) = self.key.split_at(8);
+        self.key = rest;
+        Ok(u64::from_be_bytes(int.try_into().unwrap()))
+    }
+
+    /// Read a signed integer written by `KeyBuilder::add_i64`.
+    pub fn read_i64(&mut self) -> IoResult<i64> {
+        Ok((self.read_u64()? ^ (1 << 63)) as i64)
+    }
+
+    /// Read a string written by `KeyBuilder::add_str`.
+    pub fn read_str(&mut self) -> IoResult<String> {
+        String::from_utf8(self.read_bytes()?)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))
+    }
+
+    /// Read a byte field written by `KeyBuilder::add_bytes`.
+    pub fn read_bytes(&mut self) -> IoResult<Vec<u8>> {
+        let mut value = Vec::new();
+        let mut i = 0;
+        loop {
+            match self.key.get(i..i + 2) {
+                Some([0, ESCAPE]) => {
+                    value.push(0);
+                    i += 2;
+                }
+                Some([0, TERMINATOR]) => {
+                    self.key = &self.key[i + 2..];
+                    return Ok(value);
+                }
+                _ => match self.key.get(i) {
+                    Some(&b) if b != 0 => {
+                        value.push(b);
+                        i += 1;
+                    }
+                    _ => {
+                        return Err(Error::new(ErrorKind::InvalidData, "unterminated or malformed key field"));
+                    }
+                },
+            }
+        }
+    }
+
+    /// True once every part of the key has been read.
+    pub fn is_empty(&self) -> bool {
+        self.key.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event_key(user_id: u64, timestamp: u64, event: &str) -> RowKey {
+        KeyBuilder::new()
+            .add_u64(user_id)
+            .add_u64(timestamp)
+            .add_str(event)
+            .build()
+    }
+
+    #[test]
+    fn test_keys_sort_in_logical_order() {
+        let logical = vec![
+            (1, 5, "click"),
+            (1, 5, "click\0x"),
+            (1, 5, "clicked"),
+            (1, 5, "view"),
+            (1, 256, ""),
+            (1, 256, "a"),
+            (2, 0, "a"),
+            (255, 1, "a"),
+            (256, 0, ""),
+            (u64::MAX, u64::MAX, "z"),
+        ];
+        let keys: Vec<RowKey> = logical.iter()
+            .map(|&(user_id, timestamp, event)| event_key(user_id, timestamp, event))
+            .collect();
+
+        let mut sorted = keys.clone();
+        sorted.sort();
+        assert_eq!(sorted, keys);
+
+        let signed: Vec<RowKey> = [i64::MIN, -256, -1, 0, 1, 255, i64::MAX].iter()
+            .map(|&v| KeyBuilder::new().add_i64(v).build())
+            .collect();
+        let mut sorted = signed.clone();
+        sorted.sort();
+        assert_eq!(sorted, signed);
+    }
+
+    #[test]
+    fn test_keys_decode_exactly() {
+        let key = KeyBuilder::new()
+            .add_u64(42)
+            .add_i64(-7)
+            .add_str("sign\0up")
+            .add_bytes(&[0, 0xFF, 1, 0])
+            .add_str("")
+            .build();
+
+        let mut reader = KeyReader::new(&key);
+        assert_eq!(reader.read_u64().unwrap(), 42);
+        assert_eq!(reader.read_i64().unwrap(), -7);
+        assert_eq!(reader.read_str().unwrap(), "sign\0up");
+        assert_eq!(reader.read_bytes().unwrap(), vec![0, 0xFF, 1, 0]);
+        assert_eq!(reader.read_str().unwrap(), "");
+        assert!(reader.is_empty());
+
+        assert_eq!(reader.read_u64().unwrap_err().kind(), ErrorKind::InvalidData);
+        assert!(KeyReader::new(b"abc").read_str().is_err());
+    }
+}
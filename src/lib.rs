@@ -5,5 +5,8 @@ pub mod filter;
 pub mod aggregation;
 pub mod async_api;
 pub mod batch;
+pub mod events;
+pub mod filesystem;
+pub mod keys;
 pub mod pool;
 pub mod rest;
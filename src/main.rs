@@ -64,6 +64,8 @@ fn main() -> std::io::Result<()> {
         max_versions: Some(3),
         max_age_ms: Some(24 * 3600 * 1000),
         cleanup_tombstones: true,
+        target_output_bytes: None,
+        max_versions_per_column: None,
     };
     cf.compact_with_options(options)?;
     println!("Ran custom compaction");
@@ -2,39 +2,72 @@ use bincode;
 use serde::{Deserialize, Serialize};
 use std::{
     collections::BTreeMap,
-    fs::{File, OpenOptions},
-    io::{BufReader, Read, Result as IoResult, Seek, SeekFrom, Write},
-    path::Path,
+    io::{BufReader, ErrorKind, Read, Result as IoResult, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
 };
 use crate::api::{CellValue, Entry, EntryKey, Timestamp};
+use crate::filesystem::{FileSystem, OsFileSystem, WriteFile};
 
 /// A single WAL record: binary‐encoded Entry.
 #[derive(Serialize, Deserialize, Debug)]
 pub struct WalEntry(Entry);
 
 /// MemStore holds an in‐memory BTreeMap<EntryKey, CellValue> plus an append‐only WAL file.
+/// A read-only MemStore has no WAL and rejects writes.
 pub struct MemStore {
     map: BTreeMap<EntryKey, CellValue>,
-    wal: File,
-    wal_path: String,
+    wal: Option<Box<dyn WriteFile>>,
+    wal_path: PathBuf,
+    fs: Arc<dyn FileSystem>,
 }
 
 impl MemStore {
     /// Open (or create) a WAL at wal_path and replay it to rebuild map.
     pub fn open(wal_path: impl AsRef<Path>) -> IoResult<Self> {
-        let path_str = wal_path.as_ref().to_string_lossy().into_owned();
-        let wal = OpenOptions::new()
-            .create(true)
-            .read(true)
-            .append(true)
-            .open(&wal_path)?;
+        Self::open_with_fs(Arc::new(OsFileSystem), wal_path)
+    }
+
+    /// Like open, but keeps the WAL on fs.
+    pub fn open_with_fs(fs: Arc<dyn FileSystem>, wal_path: impl AsRef<Path>) -> IoResult<Self> {
+        let wal = fs.append(wal_path.as_ref())?;
+        let mut store = MemStore {
+            map: BTreeMap::new(),
+            wal: None,
+            wal_path: wal_path.as_ref().to_path_buf(),
+            fs,
+        };
+
+        store.replay()?;
+        store.wal = Some(wal);
+        Ok(store)
+    }
+
+    /// Open a MemStore without creating or writing anything on disk.
+    /// An existing WAL at wal_path is replayed so its entries stay readable;
+    /// a missing WAL leaves the MemStore empty. Writes fail with PermissionDenied.
+    pub fn open_read_only(wal_path: impl AsRef<Path>) -> IoResult<Self> {
+        Self::open_read_only_with_fs(Arc::new(OsFileSystem), wal_path)
+    }
+
+    /// Like open_read_only, but reads the WAL from fs.
+    pub fn open_read_only_with_fs(fs: Arc<dyn FileSystem>, wal_path: impl AsRef<Path>) -> IoResult<Self> {
         let mut store = MemStore {
             map: BTreeMap::new(),
-            wal,
-            wal_path: path_str.clone(),
+            wal: None,
+            wal_path: wal_path.as_ref().to_path_buf(),
+            fs,
         };
+        match store.replay() {
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            result => result?,
+        }
+        Ok(store)
+    }
 
-        let mut reader = BufReader::new(store.wal.try_clone()?);
+    /// Rebuild map from the records of the WAL at wal_path.
+    fn replay(&mut self) -> IoResult<()> {
+        let mut reader = BufReader::new(self.fs.open(&self.wal_path)?);
         loop {
             let mut len_buf = [0u8; 4];
             if reader.read_exact(&mut len_buf).is_err() {
@@ -44,10 +77,16 @@ impl MemStore {
             let mut buf = vec![0u8; len];
             reader.read_exact(&mut buf)?;
             let WalEntry(entry) = bincode::deserialize(&buf).unwrap();
-            store.map.insert(entry.key, entry.value);
+            self.map.insert(entry.key, entry.value);
         }
-        store.wal.seek(SeekFrom::End(0))?;
-        Ok(store)
+        Ok(())
+    }
+
+    /// The WAL, or a PermissionDenied error if this MemStore is read-only.
+    fn wal_mut(&mut self) -> IoResult<&mut Box<dyn WriteFile>> {
+        self.wal.as_mut().ok_or_else(|| {
+            std::io::Error::new(ErrorKind::PermissionDenied, "MemStore is read-only")
+        })
     }
 
     /// Number of entries in the in-memory map
@@ -63,9 +102,10 @@ impl MemStore {
     pub fn append(&mut self, entry: Entry) -> IoResult<()> {
         let buf = bincode::serialize(&WalEntry(entry.clone())).unwrap();
         let len = (buf.len() as u32).to_be_bytes();
-        self.wal.write_all(&len)?;
-        self.wal.write_all(&buf)?;
-        self.wal.flush()?;
+        let wal = self.wal_mut()?;
+        wal.write_all(&len)?;
+        wal.write_all(&buf)?;
+        wal.flush()?;
 
         self.map.insert(entry.key, entry.value);
         Ok(())
@@ -73,6 +113,11 @@ impl MemStore {
 
     /// Get the *latest* CellValue for (row, column) from in‐memory map (if any).
     pub fn get_full(&self, row: &[u8], column: &[u8]) -> Option<&CellValue> {
+        self.get_latest(row, column).map(|(_ts, v)| v)
+    }
+
+    /// Like get_full, but also returns the timestamp of the latest version.
+    pub fn get_latest(&self, row: &[u8], column: &[u8]) -> Option<(Timestamp, &CellValue)> {
         let range_start = EntryKey {
             row: row.to_vec(),
             column: column.to_vec(),
@@ -86,7 +131,43 @@ impl MemStore {
         self.map
             .range(range_start..=range_end)
             .last()
-            .map(|(_k, v)| v)
+            .map(|(k, v)| (k.timestamp, v))
+    }
+
+    /// Number of versions (puts and tombstones) held in memory for (row, column).
+    pub fn version_count(&self, row: &[u8], column: &[u8]) -> usize {
+        let range_start = EntryKey {
+            row: row.to_vec(),
+            column: column.to_vec(),
+            timestamp: 0,
+        };
+        let range_end = EntryKey {
+            row: row.to_vec(),
+            column: column.to_vec(),
+            timestamp: u64::MAX,
+        };
+        self.map.range(range_start..=range_end).count()
+    }
+
+    /// Versions of (row, column) held in memory, oldest first, without copying them.
+    pub fn versions_oldest_first<'a>(
+        &'a self,
+        row: &[u8],
+        column: &[u8],
+    ) -> impl Iterator<Item = (Timestamp, &'a CellValue)> + 'a {
+        let range_start = EntryKey {
+            row: row.to_vec(),
+            column: column.to_vec(),
+            timestamp: 0,
+        };
+        let range_end = EntryKey {
+            row: row.to_vec(),
+            column: column.to_vec(),
+            timestamp: u64::MAX,
+        };
+        self.map
+            .range(range_start..=range_end)
+            .map(|(k, v)| (k.timestamp, v))
     }
 
     /// *MVCC helper*: return all versions (timestamp + CellValue) for (row, column), sorted descending by timestamp.
@@ -110,25 +191,40 @@ impl MemStore {
         versions
     }
 
+    /// Remove and return every entry, sorted by key, and empty the WAL.
     pub fn drain_all(&mut self) -> IoResult<Vec<Entry>> {
-        let mut all: Vec<Entry> = self.map.iter()
+        self.wal_mut()?;
+        let all = self.entries();
+        self.clear()?;
+        Ok(all)
+    }
+
+    /// Every entry, sorted by key. The MemStore and its WAL are left as they are.
+    pub fn entries(&self) -> Vec<Entry> {
+        self.map.iter()
             .map(|(k, v)| Entry {
                 key: k.clone(),
                 value: v.clone(),
             })
-            .collect();
+            .collect()
+    }
 
-        all.sort_by(|a, b| a.key.cmp(&b.key));
+    /// Drop every entry and empty the WAL.
+    /// Only safe once everything it holds is stored elsewhere.
+    pub fn clear(&mut self) -> IoResult<()> {
+        self.wal_mut()?;
         self.map.clear();
+        self.reset_wal()
+    }
 
-        //drop(&self.wal);
-        std::fs::remove_file(&self.wal_path)?;
-        self.wal = OpenOptions::new()
-            .create(true)
-            .read(true)
-            .append(true)
-            .open(&self.wal_path)?;
-        Ok(all)
+    /// Replace the WAL with an empty one, so nothing is replayed on the next open.
+    /// Only safe once everything it holds is stored elsewhere. If the WAL can't be
+    /// truncated, the current one is kept and writes carry on appending to it.
+    pub fn reset_wal(&mut self) -> IoResult<()> {
+        self.wal_mut()?;
+        let wal = self.fs.create(&self.wal_path)?;
+        self.wal = Some(wal);
+        Ok(())
     }
 
     /// For scanning: return all (EntryKey, CellValue) for a given row (in-memory).  
@@ -152,6 +248,34 @@ impl MemStore {
             .collect()
     }
 
+    /// Return every (EntryKey, CellValue) currently held in memory, in key order.
+    pub fn scan_all(&self) -> Vec<(EntryKey, CellValue)> {
+        self.map.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
+    }
+
+    /// Return every (EntryKey, CellValue) of the first max_rows rows that sort after `after`
+    /// (or from the first row when None), in key order.
+    pub fn scan_rows_after(&self, after: Option<&[u8]>, max_rows: usize) -> Vec<(EntryKey, CellValue)> {
+        let range_start = EntryKey {
+            row: after.unwrap_or_default().to_vec(),
+            column: vec![],
+            timestamp: 0,
+        };
+        let mut rows_seen = 0;
+        let mut last_row: Option<&[u8]> = None;
+        self.map.range(range_start..)
+            .filter(|(k, _)| after.is_none_or(|after| k.row.as_slice() > after))
+            .take_while(|(k, _)| {
+                if last_row != Some(k.row.as_slice()) {
+                    last_row = Some(k.row.as_slice());
+                    rows_seen += 1;
+                }
+                rows_seen <= max_rows
+            })
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect()
+    }
+
     /// Scan a range of rows and return all (EntryKey, CellValue) pairs.
     /// The range is inclusive of start_row and end_row.
     pub fn scan_range(&self, start_row: &[u8], end_row: &[u8]) -> Vec<(EntryKey, CellValue)> {
@@ -191,7 +315,9 @@ impl MemStore {
 mod tests {
     use super::*;
     use crate::api::{CellValue, Entry, EntryKey};
+    use crate::filesystem::{MemFileSystem, ReadFile};
     use std::fs;
+    use std::sync::atomic::{AtomicBool, Ordering};
     use std::path::PathBuf;
     use tempfile::tempdir;
 
@@ -441,4 +567,83 @@ mod tests {
         drop(store);
         drop(dir);
     }
+
+    /// A MemFileSystem whose create fails while fail_create is set.
+    #[derive(Default)]
+    struct FailingCreateFs {
+        inner: MemFileSystem,
+        fail_create: AtomicBool,
+    }
+
+    impl FileSystem for FailingCreateFs {
+        fn open(&self, path: &Path) -> IoResult<Box<dyn ReadFile>> {
+            self.inner.open(path)
+        }
+
+        fn create(&self, path: &Path) -> IoResult<Box<dyn WriteFile>> {
+            if self.fail_create.load(Ordering::SeqCst) {
+                return Err(std::io::Error::other("create failed"));
+            }
+            self.inner.create(path)
+        }
+
+        fn append(&self, path: &Path) -> IoResult<Box<dyn WriteFile>> {
+            self.inner.append(path)
+        }
+
+        fn read_dir(&self, path: &Path) -> IoResult<Vec<PathBuf>> {
+            self.inner.read_dir(path)
+        }
+
+        fn create_dir_all(&self, path: &Path) -> IoResult<()> {
+            self.inner.create_dir_all(path)
+        }
+
+        fn remove_file(&self, path: &Path) -> IoResult<()> {
+            self.inner.remove_file(path)
+        }
+
+        fn rename(&self, from: &Path, to: &Path) -> IoResult<()> {
+            self.inner.rename(from, to)
+        }
+
+        fn exists(&self, path: &Path) -> bool {
+            self.inner.exists(path)
+        }
+
+        fn is_dir(&self, path: &Path) -> bool {
+            self.inner.is_dir(path)
+        }
+
+        fn file_size(&self, path: &Path) -> IoResult<u64> {
+            self.inner.file_size(path)
+        }
+    }
+
+    #[test]
+    fn test_memstore_reset_wal_failure_keeps_wal() {
+        let fs = Arc::new(FailingCreateFs::default());
+        fs.create_dir_all(Path::new("/mem")).unwrap();
+        let wal_path = PathBuf::from("/mem/test.wal");
+        let entry = |row: &[u8]| Entry {
+            key: EntryKey { row: row.to_vec(), column: b"col1".to_vec(), timestamp: 100 },
+            value: CellValue::Put(b"value".to_vec()),
+        };
+
+        let mut store = MemStore::open_with_fs(fs.clone(), &wal_path).unwrap();
+        store.append(entry(b"row1")).unwrap();
+
+        // A failed reset leaves the old WAL in place, and writes keep going to it.
+        fs.fail_create.store(true, Ordering::SeqCst);
+        assert!(store.reset_wal().is_err());
+        store.append(entry(b"row2")).unwrap();
+        assert_eq!(MemStore::open_read_only_with_fs(fs.clone(), &wal_path).unwrap().len(), 2);
+
+        fs.fail_create.store(false, Ordering::SeqCst);
+        store.reset_wal().unwrap();
+        store.append(entry(b"row3")).unwrap();
+        let reopened = MemStore::open_read_only_with_fs(fs, &wal_path).unwrap();
+        assert_eq!(reopened.len(), 1);
+        assert!(reopened.get_full(b"row3", b"col1").is_some());
+    }
 }
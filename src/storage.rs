@@ -1,29 +1,126 @@
-use crate::api::{Entry, EntryKey, CellValue, Column, Timestamp};
+use crate::api::{Entry, EntryKey, CellValue, Column, RowKey, Timestamp};
+use crate::filesystem::{FileSystem, OsFileSystem};
 use bincode;
 use serde::{Deserialize, Serialize};
 use std::{
-    fs::File,
-    io::{BufReader, BufWriter, Read, Result as IoResult, Write},
+    io::{BufReader, BufWriter, ErrorKind, Read, Result as IoResult, Seek, SeekFrom, Write},
     path::Path,
 };
 
+/// Marks the start of an SSTable that carries a format version.
+const SSTABLE_MAGIC: u32 = 0x5242_5354;
+/// Current version of the SSTable format. Readers refuse files with a newer version.
+const SSTABLE_VERSION: u8 = 1;
+/// Default size of the buffer SSTables are written through, the same as BufWriter's.
+pub const DEFAULT_WRITE_BUFFER_BYTES: usize = 8 * 1024;
+/// Marks the end of a file that carries an SSTableFooter.
+const FOOTER_MAGIC: u32 = 0x5242_4654;
+/// Marks the start of a dump written by `Dump::write`.
+const DUMP_MAGIC: u32 = 0x5242_4450;
+/// Current version of the dump format.
+const DUMP_VERSION: u32 = 1;
+/// Tag preceding each record in a dump.
+const DUMP_RECORD_TAG: u8 = 1;
+/// Tag preceding the trailer of a dump.
+const DUMP_END_TAG: u8 = 0;
+
+/// Summary of the key and timestamp bounds of an SSTable.
+/// Lets callers decide whether a file is relevant to a query without loading it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SSTableFooter {
+    pub min_row: RowKey,
+    pub max_row: RowKey,
+    pub min_timestamp: Timestamp,
+    pub max_timestamp: Timestamp,
+}
+
+impl SSTableFooter {
+    /// Build a footer from a sorted slice of Entry. Returns None for an empty slice.
+    pub fn from_entries(entries: &[Entry]) -> Option<Self> {
+        let first = entries.first()?;
+        let last = entries.last()?;
+        let (min_timestamp, max_timestamp) = entries.iter()
+            .fold((u64::MAX, 0), |(min, max), e| (min.min(e.key.timestamp), max.max(e.key.timestamp)));
+
+        Some(SSTableFooter {
+            min_row: first.key.row.clone(),
+            max_row: last.key.row.clone(),
+            min_timestamp,
+            max_timestamp,
+        })
+    }
+
+    /// True if any row in [start_row, end_row] may be present in the table.
+    pub fn overlaps_rows(&self, start_row: &[u8], end_row: &[u8]) -> bool {
+        self.min_row.as_slice() <= end_row && self.max_row.as_slice() >= start_row
+    }
+
+    /// True if any version in [start_ts, end_ts] may be present in the table.
+    pub fn overlaps_timestamps(&self, start_ts: Timestamp, end_ts: Timestamp) -> bool {
+        self.min_timestamp <= end_ts && self.max_timestamp >= start_ts
+    }
+}
+
 /// An on-disk SSTable.
 /// Format (all big-endian u32 for lengths):
 ///
-/// 1) [u32: number_of_entries]
+/// 1) Header:
+///    a) [u32: SSTABLE_MAGIC]
+///    b) [u8: SSTABLE_VERSION]
+///    c) [u32: number_of_entries]
 /// 2) For each entry:
 ///    a) [u32: length of serialized EntryKey]
 ///    b) [bytes: bincode(serialized EntryKey)]
 ///    c) [u32: length of serialized CellValue]
 ///    d) [bytes: bincode(serialized CellValue)]
+/// 3) Footer (non-empty tables only):
+///    a) [bytes: bincode(serialized SSTableFooter)]
+///    b) [u32: length of serialized SSTableFooter]
+///    c) [u32: FOOTER_MAGIC]
+///
+/// Readers stop after number_of_entries, so the footer is invisible to them.
+/// Files written before the header had a magic number start directly with number_of_entries
+/// and are still readable.
 pub struct SSTable;
 
 impl SSTable {
     /// Create an SSTable at path from a sorted slice of Entry.
     pub fn create(path: impl AsRef<Path>, entries: &[Entry]) -> IoResult<()> {
-        let f = File::create(path)?;
-        let mut w = BufWriter::new(f);
+        Self::create_with_fs(&OsFileSystem, path, entries)
+    }
 
+    /// Exact size in bytes of the file create would write for entries.
+    pub fn encoded_size(entries: &[Entry]) -> u64 {
+        let header = 4 + 1 + 4;
+        let body: u64 = entries.iter()
+            .map(|entry| {
+                8 + bincode::serialized_size(&entry.key).unwrap()
+                    + bincode::serialized_size(&entry.value).unwrap()
+            })
+            .sum();
+        let footer = SSTableFooter::from_entries(entries)
+            .map_or(0, |footer| bincode::serialized_size(&footer).unwrap() + 8);
+        header + body + footer
+    }
+
+    /// Like create, but writes the file through fs.
+    pub fn create_with_fs(fs: &dyn FileSystem, path: impl AsRef<Path>, entries: &[Entry]) -> IoResult<()> {
+        Self::create_with_buffer(fs, path, entries, DEFAULT_WRITE_BUFFER_BYTES)
+    }
+
+    /// Like create_with_fs, but writes through a buffer of buffer_bytes bytes.
+    /// A bigger buffer means fewer, larger writes to the file.
+    pub fn create_with_buffer(
+        fs: &dyn FileSystem,
+        path: impl AsRef<Path>,
+        entries: &[Entry],
+        buffer_bytes: usize,
+    ) -> IoResult<()> {
+        let f = fs.create(path.as_ref())?;
+        let mut w = BufWriter::with_capacity(buffer_bytes, f);
+
+        w.write_all(&SSTABLE_MAGIC.to_be_bytes())?;
+        w.write_all(&[SSTABLE_VERSION])?;
         let count = (entries.len() as u32).to_be_bytes();
         w.write_all(&count)?;
 
@@ -38,6 +135,13 @@ impl SSTable {
             w.write_all(&val_len)?;
             w.write_all(&val_ser)?;
         }
+
+        if let Some(footer) = SSTableFooter::from_entries(entries) {
+            let footer_ser = bincode::serialize(&footer).unwrap();
+            w.write_all(&footer_ser)?;
+            w.write_all(&(footer_ser.len() as u32).to_be_bytes())?;
+            w.write_all(&FOOTER_MAGIC.to_be_bytes())?;
+        }
         w.flush()?;
         Ok(())
     }
@@ -52,12 +156,16 @@ pub struct SSTableReader {
 impl SSTableReader {
     /// Open an SSTable file, read all entries (key + CellValue) into memory.
     pub fn open(path: impl AsRef<Path>) -> IoResult<Self> {
-        let f = File::open(path)?;
+        Self::open_with_fs(&OsFileSystem, path)
+    }
+
+    /// Like open, but reads the file through fs.
+    pub fn open_with_fs(fs: &dyn FileSystem, path: impl AsRef<Path>) -> IoResult<Self> {
+        let f = fs.open(path.as_ref())?;
         let mut r = BufReader::new(f);
 
+        let count = Self::read_header(&mut r)? as usize;
         let mut buf4 = [0u8; 4];
-        r.read_exact(&mut buf4)?;
-        let count = u32::from_be_bytes(buf4) as usize;
 
         let entries = (0..count)
             .map(|_| -> IoResult<(EntryKey, CellValue)> {
@@ -65,13 +173,15 @@ impl SSTableReader {
                 let key_len = u32::from_be_bytes(buf4) as usize;
                 let mut key_buf = vec![0u8; key_len];
                 r.read_exact(&mut key_buf)?;
-                let key: EntryKey = bincode::deserialize(&key_buf).unwrap();
+                let key: EntryKey = bincode::deserialize(&key_buf)
+                    .map_err(|e| std::io::Error::new(ErrorKind::InvalidData, e))?;
 
                 r.read_exact(&mut buf4)?;
                 let val_len = u32::from_be_bytes(buf4) as usize;
                 let mut val_buf = vec![0u8; val_len];
                 r.read_exact(&mut val_buf)?;
-                let cell: CellValue = bincode::deserialize(&val_buf).unwrap();
+                let cell: CellValue = bincode::deserialize(&val_buf)
+                    .map_err(|e| std::io::Error::new(ErrorKind::InvalidData, e))?;
 
                 Ok((key, cell))
             })
@@ -79,14 +189,107 @@ impl SSTableReader {
         Ok(SSTableReader { entries })
     }
 
+    /// Read the entry count from the SSTable header without parsing any entries.
+    pub fn entry_count(path: impl AsRef<Path>) -> IoResult<u32> {
+        Self::entry_count_with_fs(&OsFileSystem, path)
+    }
+
+    /// Like entry_count, but reads the file through fs.
+    pub fn entry_count_with_fs(fs: &dyn FileSystem, path: impl AsRef<Path>) -> IoResult<u32> {
+        let mut f = fs.open(path.as_ref())?;
+        Self::read_header(&mut f)
+    }
+
+    /// Read the SSTable header and return the entry count.
+    /// Fails with ErrorKind::Unsupported if the file was written in a newer format version.
+    fn read_header(r: &mut impl Read) -> IoResult<u32> {
+        let mut buf4 = [0u8; 4];
+        r.read_exact(&mut buf4)?;
+        if u32::from_be_bytes(buf4) != SSTABLE_MAGIC {
+            // Written before the header carried a version: it starts with the entry count.
+            return Ok(u32::from_be_bytes(buf4));
+        }
+
+        let mut version = [0u8; 1];
+        r.read_exact(&mut version)?;
+        if version[0] > SSTABLE_VERSION {
+            return Err(std::io::Error::new(
+                ErrorKind::Unsupported,
+                format!(
+                    "SSTable format version {} is newer than the supported version {}",
+                    version[0], SSTABLE_VERSION
+                ),
+            ));
+        }
+        r.read_exact(&mut buf4)?;
+        Ok(u32::from_be_bytes(buf4))
+    }
+
+    /// Whether an SSTable was written in the current format: with a version header and,
+    /// unless it is empty, a footer. Older files stay readable but can be upgraded with
+    /// ColumnFamily::rewrite_sstables.
+    pub fn is_current_format(path: impl AsRef<Path>) -> IoResult<bool> {
+        Self::is_current_format_with_fs(&OsFileSystem, path)
+    }
+
+    /// Like is_current_format, but reads the file through fs.
+    pub fn is_current_format_with_fs(fs: &dyn FileSystem, path: impl AsRef<Path>) -> IoResult<bool> {
+        let mut f = fs.open(path.as_ref())?;
+        let mut header = [0u8; 5];
+        if f.read_exact(&mut header).is_err() || header[..4] != SSTABLE_MAGIC.to_be_bytes() {
+            return Ok(false);
+        }
+        if header[4] < SSTABLE_VERSION {
+            return Ok(false);
+        }
+        let count = Self::entry_count_with_fs(fs, path.as_ref())?;
+        Ok(count == 0 || Self::read_footer_with_fs(fs, path)?.is_some())
+    }
+
+    /// Read only the footer of an SSTable, without loading its entries.
+    /// Returns Ok(None) for empty tables and files written before footers existed.
+    pub fn read_footer(path: impl AsRef<Path>) -> IoResult<Option<SSTableFooter>> {
+        Self::read_footer_with_fs(&OsFileSystem, path)
+    }
+
+    /// Like read_footer, but reads the file through fs.
+    pub fn read_footer_with_fs(fs: &dyn FileSystem, path: impl AsRef<Path>) -> IoResult<Option<SSTableFooter>> {
+        let mut f = fs.open(path.as_ref())?;
+        let file_len = f.seek(SeekFrom::End(0))?;
+        if file_len < 12 {
+            return Ok(None);
+        }
+
+        let mut buf4 = [0u8; 4];
+        f.seek(SeekFrom::End(-8))?;
+        f.read_exact(&mut buf4)?;
+        let footer_len = u32::from_be_bytes(buf4) as u64;
+        f.read_exact(&mut buf4)?;
+        if u32::from_be_bytes(buf4) != FOOTER_MAGIC || footer_len + 12 > file_len {
+            return Ok(None);
+        }
+
+        let mut footer_buf = vec![0u8; footer_len as usize];
+        f.seek(SeekFrom::End(-8 - footer_len as i64))?;
+        f.read_exact(&mut footer_buf)?;
+        Ok(bincode::deserialize(&footer_buf).ok())
+    }
+
     /// Look up the latest CellValue for (row, column) by scanning backwards.
     pub fn get_full(&mut self, row: &[u8], column: &[u8]) -> IoResult<Option<CellValue>> {
-        for (key, cell) in self.entries.iter().rev() {
-            if key.row.as_slice() == row && key.column.as_slice() == column {
-                return Ok(Some(cell.clone()));
-            }
-        }
-        Ok(None)
+        Ok(self.get_latest(row, column).map(|(_ts, cell)| cell))
+    }
+
+    /// Like get_full, but also returns the timestamp of the latest version.
+    pub fn get_latest(&self, row: &[u8], column: &[u8]) -> Option<(Timestamp, CellValue)> {
+        self.get_latest_ref(row, column).map(|(ts, cell)| (ts, cell.clone()))
+    }
+
+    /// Like get_latest, but borrows the CellValue instead of copying it.
+    pub fn get_latest_ref(&self, row: &[u8], column: &[u8]) -> Option<(Timestamp, &CellValue)> {
+        self.entries.iter().rev()
+            .find(|(key, _)| key.row.as_slice() == row && key.column.as_slice() == column)
+            .map(|(key, cell)| (key.timestamp, cell))
     }
 
     /// *MVCC helper*: return all versions (timestamp + CellValue) for (row, column), sorted descending by timestamp.
@@ -104,6 +307,20 @@ impl SSTableReader {
         Ok(versions)
     }
 
+    /// Versions of (row, column), oldest first. Finds the first one by binary search
+    /// and copies nothing.
+    pub fn versions_oldest_first<'a>(
+        &'a self,
+        row: &'a [u8],
+        column: &'a [u8],
+    ) -> impl Iterator<Item = (Timestamp, &'a CellValue)> + 'a {
+        let start = self.entries
+            .partition_point(|(key, _)| (key.row.as_slice(), key.column.as_slice()) < (row, column));
+        self.entries[start..].iter()
+            .take_while(move |(key, _)| key.row.as_slice() == row && key.column.as_slice() == column)
+            .map(|(key, cell)| (key.timestamp, cell))
+    }
+
     /// Scan all entries for a given row, returning (column, timestamp, CellValue) tuples.
     pub fn scan_row_full(
         &mut self,
@@ -127,15 +344,35 @@ impl SSTableReader {
     /// Scan a range of rows and return all entries within that range.
     /// The range is inclusive of start_row and end_row.
     pub fn scan_range(&mut self, start_row: &[u8], end_row: &[u8]) -> IoResult<Vec<(EntryKey, CellValue)>> {
-        let mut result = Vec::new();
+        Ok(self.entries[self.range_bounds(start_row, end_row)].to_vec())
+    }
 
-        for (key, cell) in &self.entries {
-            if key.row.as_slice() >= start_row && key.row.as_slice() <= end_row {
-                result.push((key.clone(), cell.clone()));
-            }
-        }
+    /// Return every (EntryKey, CellValue) of the first max_rows rows that sort after `after`
+    /// (or from the first row when None), in key order.
+    pub fn scan_rows_after(&self, after: Option<&[u8]>, max_rows: usize) -> Vec<(EntryKey, CellValue)> {
+        let start = after.map_or(0, |after| {
+            self.entries.partition_point(|(key, _)| key.row.as_slice() <= after)
+        });
+        let mut rows_seen = 0;
+        let mut last_row: Option<&[u8]> = None;
+        self.entries[start..].iter()
+            .take_while(|(key, _)| {
+                if last_row != Some(key.row.as_slice()) {
+                    last_row = Some(key.row.as_slice());
+                    rows_seen += 1;
+                }
+                rows_seen <= max_rows
+            })
+            .cloned()
+            .collect()
+    }
 
-        Ok(result)
+    /// Index range of the entries whose row lies in [start_row, end_row].
+    /// Entries are sorted by EntryKey, so both ends are found by binary search.
+    fn range_bounds(&self, start_row: &[u8], end_row: &[u8]) -> std::ops::Range<usize> {
+        let start = self.entries.partition_point(|(key, _)| key.row.as_slice() < start_row);
+        let end = start + self.entries[start..].partition_point(|(key, _)| key.row.as_slice() <= end_row);
+        start..end
     }
 
     /// Get all unique row keys in a range.
@@ -150,6 +387,117 @@ impl SSTableReader {
     }
 }
 
+/// A portable binary dump of a column family's raw entries, versions and tombstones included.
+/// Format (all big-endian):
+///
+/// 1) [u32: DUMP_MAGIC] [u32: DUMP_VERSION]
+/// 2) For each entry:
+///    a) [u8: DUMP_RECORD_TAG]
+///    b) [u32: length of serialized Entry]
+///    c) [bytes: bincode(serialized Entry)]
+///    d) [u32: CRC32 of the serialized Entry]
+/// 3) [u8: DUMP_END_TAG] [u64: number of records]
+///
+/// A dump without its trailer, or whose record count or checksums don't match,
+/// is rejected by `Dump::read`, so a partially written dump is never mistaken for a complete one.
+pub struct Dump;
+
+impl Dump {
+    /// Write entries as a dump, returning the number of records written.
+    pub fn write<'a>(
+        writer: impl Write,
+        entries: impl IntoIterator<Item = &'a Entry>,
+    ) -> IoResult<u64> {
+        let mut w = BufWriter::new(writer);
+        w.write_all(&DUMP_MAGIC.to_be_bytes())?;
+        w.write_all(&DUMP_VERSION.to_be_bytes())?;
+
+        let mut count = 0u64;
+        for entry in entries {
+            let entry_ser = bincode::serialize(entry).unwrap();
+            w.write_all(&[DUMP_RECORD_TAG])?;
+            w.write_all(&(entry_ser.len() as u32).to_be_bytes())?;
+            w.write_all(&entry_ser)?;
+            w.write_all(&crc32fast::hash(&entry_ser).to_be_bytes())?;
+            count += 1;
+        }
+
+        w.write_all(&[DUMP_END_TAG])?;
+        w.write_all(&count.to_be_bytes())?;
+        w.flush()?;
+        Ok(count)
+    }
+
+    /// Read and validate a whole dump. Fails with `InvalidData` if the dump is
+    /// truncated, has a bad header, a checksum mismatch, or the wrong record count.
+    pub fn read(reader: impl Read) -> IoResult<Vec<Entry>> {
+        let mut r = BufReader::new(reader);
+        let truncated = |e: std::io::Error| {
+            if e.kind() == ErrorKind::UnexpectedEof {
+                std::io::Error::new(ErrorKind::InvalidData, "Dump is truncated")
+            } else {
+                e
+            }
+        };
+
+        let mut buf4 = [0u8; 4];
+        r.read_exact(&mut buf4).map_err(truncated)?;
+        if u32::from_be_bytes(buf4) != DUMP_MAGIC {
+            return Err(std::io::Error::new(ErrorKind::InvalidData, "Not a dump file"));
+        }
+        r.read_exact(&mut buf4).map_err(truncated)?;
+        let version = u32::from_be_bytes(buf4);
+        if version != DUMP_VERSION {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidData,
+                format!("Unsupported dump version {}", version),
+            ));
+        }
+
+        let mut entries = Vec::new();
+        loop {
+            let mut tag = [0u8; 1];
+            r.read_exact(&mut tag).map_err(truncated)?;
+            match tag[0] {
+                DUMP_RECORD_TAG => {
+                    r.read_exact(&mut buf4).map_err(truncated)?;
+                    let len = u32::from_be_bytes(buf4) as usize;
+                    let mut entry_buf = vec![0u8; len];
+                    r.read_exact(&mut entry_buf).map_err(truncated)?;
+                    r.read_exact(&mut buf4).map_err(truncated)?;
+                    if crc32fast::hash(&entry_buf) != u32::from_be_bytes(buf4) {
+                        return Err(std::io::Error::new(
+                            ErrorKind::InvalidData,
+                            format!("Checksum mismatch in dump record {}", entries.len()),
+                        ));
+                    }
+                    let entry: Entry = bincode::deserialize(&entry_buf)
+                        .map_err(|e| std::io::Error::new(ErrorKind::InvalidData, e))?;
+                    entries.push(entry);
+                }
+                DUMP_END_TAG => {
+                    let mut buf8 = [0u8; 8];
+                    r.read_exact(&mut buf8).map_err(truncated)?;
+                    let expected = u64::from_be_bytes(buf8);
+                    if expected != entries.len() as u64 {
+                        return Err(std::io::Error::new(
+                            ErrorKind::InvalidData,
+                            format!("Dump declares {} records but contains {}", expected, entries.len()),
+                        ));
+                    }
+                    return Ok(entries);
+                }
+                other => {
+                    return Err(std::io::Error::new(
+                        ErrorKind::InvalidData,
+                        format!("Unknown dump record tag {}", other),
+                    ));
+                }
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -314,6 +662,69 @@ mod tests {
         drop(dir);
     }
 
+    #[test]
+    fn test_sstable_entry_count() {
+        let dir = tempdir().unwrap();
+        let sst_path = dir.path().join("test.sst");
+
+        let entries: Vec<Entry> = (0..250)
+            .map(|i| Entry {
+                key: EntryKey {
+                    row: format!("row{:04}", i).into_bytes(),
+                    column: b"col1".to_vec(),
+                    timestamp: 100,
+                },
+                value: CellValue::Put(vec![0u8; 64]),
+            })
+            .collect();
+
+        SSTable::create(&sst_path, &entries).unwrap();
+        assert_eq!(SSTableReader::entry_count(&sst_path).unwrap(), 250);
+
+        // The header alone is enough: entries after it are never parsed.
+        let header_only = dir.path().join("header_only.sst");
+        fs::write(&header_only, 250u32.to_be_bytes()).unwrap();
+        assert_eq!(SSTableReader::entry_count(&header_only).unwrap(), 250);
+        assert!(SSTableReader::open(&header_only).is_err());
+
+        let empty_path = dir.path().join("empty.sst");
+        SSTable::create(&empty_path, &[]).unwrap();
+        assert_eq!(SSTableReader::entry_count(&empty_path).unwrap(), 0);
+
+        drop(dir);
+    }
+
+    #[test]
+    fn test_sstable_read_footer() {
+        let dir = tempdir().unwrap();
+        let sst_path = dir.path().join("test.sst");
+
+        let entries = create_test_entries();
+
+        SSTable::create(&sst_path, &entries).unwrap();
+
+        let footer = SSTableReader::read_footer(&sst_path).unwrap().unwrap();
+        assert_eq!(footer.min_row, b"row1");
+        assert_eq!(footer.max_row, b"row2");
+        assert_eq!(footer.min_timestamp, 101);
+        assert_eq!(footer.max_timestamp, 300);
+
+        assert!(footer.overlaps_rows(b"row0", b"row1"));
+        assert!(!footer.overlaps_rows(b"row3", b"row9"));
+        assert!(footer.overlaps_timestamps(250, 400));
+        assert!(!footer.overlaps_timestamps(301, 400));
+
+        let reader = SSTableReader::open(&sst_path).unwrap();
+        assert_eq!(reader.entries.len(), entries.len());
+
+        let empty_path = dir.path().join("empty.sst");
+        SSTable::create(&empty_path, &[]).unwrap();
+        assert!(SSTableReader::read_footer(&empty_path).unwrap().is_none());
+
+        drop(reader);
+        drop(dir);
+    }
+
     #[test]
     fn test_sstable_reader_scan_all() {
         let dir = tempdir().unwrap();
@@ -346,4 +757,101 @@ mod tests {
         drop(reader);
         drop(dir);
     }
+
+    #[test]
+    fn test_sstable_reader_scan_range_matches_linear_scan() {
+        let dir = tempdir().unwrap();
+        let sst_path = dir.path().join("test.sst");
+
+        let mut entries = Vec::new();
+        for r in 0..2_000 {
+            for c in 0..5 {
+                for ts in 0..3 {
+                    entries.push(Entry {
+                        key: EntryKey {
+                            row: format!("row{:05}", r * 2).into_bytes(),
+                            column: format!("col{}", c).into_bytes(),
+                            timestamp: 100 + ts,
+                        },
+                        value: CellValue::Put(format!("value{}-{}-{}", r, c, ts).into_bytes()),
+                    });
+                }
+            }
+        }
+        entries.sort_by(|a, b| a.key.cmp(&b.key));
+        SSTable::create(&sst_path, &entries).unwrap();
+
+        let mut reader = SSTableReader::open(&sst_path).unwrap();
+        let all_entries = reader.scan_all().unwrap();
+
+        let ranges: [(&[u8], &[u8]); 7] = [
+            (b"row00000", b"row03998"),
+            (b"row01000", b"row01000"),
+            (b"row01001", b"row01001"),
+            (b"row00999", b"row01201"),
+            (b"", b"row00003"),
+            (b"row03998", b"row99999"),
+            (b"row02000", b"row01000"),
+        ];
+        for (start_row, end_row) in ranges {
+            let expected: Vec<_> = all_entries
+                .iter()
+                .filter(|(key, _)| key.row.as_slice() >= start_row && key.row.as_slice() <= end_row)
+                .cloned()
+                .collect();
+            assert_eq!(reader.scan_range(start_row, end_row).unwrap(), expected);
+        }
+
+        // A narrow range only touches its own entries, not the whole table.
+        let bounds = reader.range_bounds(b"row01000", b"row01002");
+        assert_eq!(bounds.len(), 2 * 5 * 3);
+        assert_eq!(reader.entries[bounds.start].0.row, b"row01000".to_vec());
+
+        drop(reader);
+        drop(dir);
+    }
+
+    #[test]
+    fn test_sstable_newer_format_version_rejected() {
+        let dir = tempdir().unwrap();
+        let sst_path = dir.path().join("test.sst");
+
+        SSTable::create(&sst_path, &create_test_entries()).unwrap();
+        let mut bytes = fs::read(&sst_path).unwrap();
+        assert_eq!(bytes[4], SSTABLE_VERSION);
+        bytes[4] = SSTABLE_VERSION + 1;
+        fs::write(&sst_path, &bytes).unwrap();
+
+        let err = SSTableReader::open(&sst_path).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+        assert!(err.to_string().contains("newer"));
+        assert_eq!(SSTableReader::entry_count(&sst_path).unwrap_err().kind(), ErrorKind::Unsupported);
+
+        drop(dir);
+    }
+
+    #[test]
+    fn test_sstable_without_version_header_still_readable() {
+        let dir = tempdir().unwrap();
+        let sst_path = dir.path().join("test.sst");
+
+        let entries = create_test_entries();
+        let mut bytes = (entries.len() as u32).to_be_bytes().to_vec();
+        for entry in &entries {
+            let key_ser = bincode::serialize(&entry.key).unwrap();
+            bytes.extend_from_slice(&(key_ser.len() as u32).to_be_bytes());
+            bytes.extend_from_slice(&key_ser);
+            let val_ser = bincode::serialize(&entry.value).unwrap();
+            bytes.extend_from_slice(&(val_ser.len() as u32).to_be_bytes());
+            bytes.extend_from_slice(&val_ser);
+        }
+        fs::write(&sst_path, &bytes).unwrap();
+
+        assert_eq!(SSTableReader::entry_count(&sst_path).unwrap(), entries.len() as u32);
+        let reader = SSTableReader::open(&sst_path).unwrap();
+        assert_eq!(reader.scan_all().unwrap().len(), entries.len());
+        assert_eq!(SSTableReader::read_footer(&sst_path).unwrap(), None);
+
+        drop(dir);
+    }
 }
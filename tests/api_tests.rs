@@ -1,11 +1,18 @@
 use std::{
-    collections::BTreeMap,
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fs,
     path::PathBuf,
+    sync::{mpsc, Arc, Mutex},
     thread,
     time::Duration,
 };
 use tempfile::tempdir;
-use RedBase::api::{Table, ColumnFamily, CompactionOptions, CompactionType, Get, Put};
+use RedBase::api::{Table, CellValue, ColumnFamily, ColumnFamilyOptions, CompactionOptions, CompactionPlan, CompactionType, EntryKey, Get, Put, ReadConsistency, ReadSource, SSTableMeta};
+use RedBase::events::{CompactionInfo, EventListener, FlushInfo};
+use RedBase::filesystem::{FileSystem, MemFileSystem, ReadFile, WriteFile};
+use RedBase::storage::SSTableReader;
+use RedBase::aggregation::{AggregationSet, AggregationType, ValueEncoding};
+use RedBase::filter::FilterSet;
 
 fn temp_table_dir() -> (tempfile::TempDir, PathBuf) {
     let dir = tempdir().unwrap();
@@ -13,6 +20,14 @@ fn temp_table_dir() -> (tempfile::TempDir, PathBuf) {
     (dir, table_path)
 }
 
+fn sst_file_count(cf_path: &std::path::Path) -> usize {
+    std::fs::read_dir(cf_path).unwrap()
+        .filter(|entry| {
+            entry.as_ref().unwrap().path().extension().is_some_and(|ext| ext == "sst")
+        })
+        .count()
+}
+
 #[test]
 fn test_table_open_empty() {
     let (dir, table_path) = temp_table_dir();
@@ -301,6 +316,8 @@ fn test_column_family_version_compaction() {
         max_versions: Some(2),
         max_age_ms: None,
         cleanup_tombstones: true,
+        target_output_bytes: None,
+        max_versions_per_column: None,
     };
     cf.compact_with_options(options).unwrap();
 
@@ -339,6 +356,8 @@ fn test_column_family_custom_compaction() {
         max_versions: Some(2),
         max_age_ms: None,
         cleanup_tombstones: false,
+        target_output_bytes: None,
+        max_versions_per_column: None,
     };
 
     cf.compact_with_options(options).unwrap();
@@ -653,6 +672,145 @@ fn test_column_family_execute_get_with_max_versions() {
     drop(dir);
 }
 
+#[test]
+fn test_column_family_default_get_versions() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    let options = ColumnFamilyOptions::builder()
+        .default_get_versions(3)
+        .build();
+    table.create_cf_with_options("test_cf", options).unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    for i in 1..=4 {
+        cf.put(b"row1".to_vec(), b"col1".to_vec(), format!("value{}", i).into_bytes()).unwrap();
+        thread::sleep(Duration::from_millis(10));
+    }
+    cf.put(b"row1".to_vec(), b"col2".to_vec(), b"only".to_vec()).unwrap();
+
+    let get = Get::new(b"row1".to_vec());
+    let result = cf.execute_get(&get).unwrap();
+    let values: Vec<_> = result[b"col1".as_slice()].iter().map(|(_, v)| v.clone()).collect();
+    assert_eq!(values, vec![b"value4".to_vec(), b"value3".to_vec(), b"value2".to_vec()]);
+    assert_eq!(result[b"col2".as_slice()].len(), 1);
+    assert_eq!(cf.execute_get_column(&get, b"col1").unwrap().len(), 3);
+
+    // A max_versions on the Get still wins.
+    let mut get = Get::new(b"row1".to_vec());
+    get.set_max_versions(1);
+    assert_eq!(cf.execute_get(&get).unwrap()[b"col1".as_slice()].len(), 1);
+
+    assert_eq!(ColumnFamilyOptions::default().default_get_versions, 1);
+    let invalid = ColumnFamilyOptions::builder().default_get_versions(0).build();
+    assert!(table.create_cf_with_options("bad_cf", invalid).is_err());
+
+    drop(dir);
+}
+
+#[test]
+fn test_column_family_cell_count() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    for col in ["a", "b", "c", "d"] {
+        cf.put(b"row1".to_vec(), col.as_bytes().to_vec(), b"v".to_vec()).unwrap();
+    }
+    cf.put(b"row2".to_vec(), b"a".to_vec(), b"v".to_vec()).unwrap();
+    cf.flush().unwrap();
+    thread::sleep(Duration::from_millis(10));
+    cf.delete(b"row1".to_vec(), b"b".to_vec()).unwrap();
+    cf.put(b"row1".to_vec(), b"a".to_vec(), b"v2".to_vec()).unwrap();
+
+    assert_eq!(cf.cell_count(b"row1").unwrap(), 3);
+    assert_eq!(cf.cell_count(b"row2").unwrap(), 1);
+    assert_eq!(cf.cell_count(b"missing").unwrap(), 0);
+
+    drop(dir);
+}
+
+#[test]
+fn test_column_family_multi_get() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    for i in 1..=3 {
+        cf.put(b"row1".to_vec(), b"col1".to_vec(), format!("value{}", i).into_bytes()).unwrap();
+        cf.put(b"row2".to_vec(), b"col1".to_vec(), format!("other{}", i).into_bytes()).unwrap();
+        if i == 2 {
+            cf.flush().unwrap();
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    cf.put(b"row3".to_vec(), b"col2".to_vec(), b"only".to_vec()).unwrap();
+    cf.delete(b"row2".to_vec(), b"col1".to_vec()).unwrap();
+
+    let mut two_versions = Get::new(b"row1".to_vec());
+    two_versions.set_max_versions(2);
+    let mut all_versions = Get::new(b"row2".to_vec());
+    all_versions.set_all_versions();
+    let gets = vec![Get::new(b"row3".to_vec()), two_versions, all_versions, Get::new(b"missing".to_vec())];
+
+    let results = cf.multi_get(&gets).unwrap();
+    assert_eq!(results.len(), gets.len());
+    for (get, result) in gets.iter().zip(&results) {
+        assert_eq!(result, &cf.execute_get(get).unwrap());
+    }
+    assert_eq!(results[0][b"col2".as_slice()][0].1, b"only");
+    assert_eq!(results[1][b"col1".as_slice()].len(), 2);
+    assert_eq!(results[1][b"col1".as_slice()][0].1, b"value3");
+    assert_eq!(results[2][b"col1".as_slice()].len(), 3);
+    assert!(results[3].is_empty());
+
+    drop(dir);
+}
+
+#[test]
+fn test_column_family_execute_get_all_versions() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    for i in 1..=3 {
+        cf.put(
+            b"row1".to_vec(),
+            b"col1".to_vec(),
+            format!("value{}", i).into_bytes()
+        ).unwrap();
+
+        thread::sleep(Duration::from_millis(10));
+    }
+    cf.put(b"row1".to_vec(), b"col2".to_vec(), b"only".to_vec()).unwrap();
+
+    let mut get = Get::new(b"row1".to_vec());
+    get.set_all_versions();
+    assert!(get.all_versions());
+
+    let result = cf.execute_get(&get).unwrap();
+
+    let col1_versions = result.get(b"col1".as_slice()).unwrap();
+    assert_eq!(col1_versions.len(), 3);
+    assert_eq!(String::from_utf8_lossy(&col1_versions[0].1), "value3");
+    assert_eq!(String::from_utf8_lossy(&col1_versions[2].1), "value1");
+    assert_eq!(result.get(b"col2".as_slice()).unwrap().len(), 1);
+
+    get.set_time_range(0, u64::MAX);
+    let ranged = cf.execute_get(&get).unwrap();
+    assert_eq!(ranged.get(b"col1".as_slice()).unwrap().len(), 3);
+
+    assert_eq!(cf.execute_get_column(&get, b"col1").unwrap().len(), 3);
+
+    drop(dir);
+}
+
 #[test]
 fn test_column_family_execute_get_with_time_range() {
     let (dir, table_path) = temp_table_dir();
@@ -762,3 +920,2249 @@ fn test_column_family_get_versions_with_time_range() {
 
     drop(dir);
 }
+
+#[test]
+fn test_column_family_scan_window() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    for round in 1..=3 {
+        for i in 1..=4 {
+            cf.put(
+                format!("row{}", i).into_bytes(),
+                b"col1".to_vec(),
+                format!("row{}_round{}", i, round).into_bytes()
+            ).unwrap();
+        }
+        if round < 3 {
+            cf.flush().unwrap();
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+
+    let round_ts: Vec<u64> = cf.get_versions(b"row1", b"col1", 10).unwrap()
+        .into_iter()
+        .rev()
+        .map(|(ts, _)| ts)
+        .collect();
+    assert_eq!(round_ts.len(), 3);
+
+    let result = cf.scan_window(b"row2", b"row3", round_ts[1], round_ts[2] - 1, 10).unwrap();
+    assert_eq!(result.len(), 2);
+    for i in 2..=3 {
+        let versions = result.get(&format!("row{}", i).into_bytes()).unwrap()
+            .get(&b"col1".to_vec()).unwrap();
+        assert_eq!(versions.len(), 1);
+        assert_eq!(String::from_utf8_lossy(&versions[0].1), format!("row{}_round2", i));
+    }
+
+    let result = cf.scan_window(b"row2", b"row3", round_ts[1], u64::MAX, 10).unwrap();
+    assert_eq!(result.len(), 2);
+    let versions = result.get(&b"row3".to_vec()).unwrap().get(&b"col1".to_vec()).unwrap();
+    assert_eq!(versions.len(), 2);
+    assert_eq!(String::from_utf8_lossy(&versions[0].1), "row3_round3");
+    assert_eq!(String::from_utf8_lossy(&versions[1].1), "row3_round2");
+
+    let result = cf.scan_window(b"row1", b"row4", 0, round_ts[1] - 1, 1).unwrap();
+    assert_eq!(result.len(), 4);
+    assert!(result.values().all(|cols| cols[&b"col1".to_vec()].len() == 1));
+
+    let result = cf.scan_window(b"row5", b"row9", 0, u64::MAX, 10).unwrap();
+    assert!(result.is_empty());
+
+    drop(dir);
+}
+
+#[test]
+fn test_column_family_read_triggered_compaction() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    let options = ColumnFamilyOptions {
+        read_triggered_compaction: true,
+        read_compaction_threshold: 2,
+        ..ColumnFamilyOptions::default()
+    };
+    table.create_cf_with_options("hot_cf", options).unwrap();
+    table.create_cf("cold_cf").unwrap();
+    let hot = table.cf("hot_cf").unwrap();
+    let cold = table.cf("cold_cf").unwrap();
+
+    for cf in [&hot, &cold] {
+        for i in 1..=4 {
+            cf.put(b"row1".to_vec(), b"col1".to_vec(), format!("value{}", i).into_bytes()).unwrap();
+            cf.put(format!("other{}", i).into_bytes(), b"col1".to_vec(), b"x".to_vec()).unwrap();
+            cf.flush().unwrap();
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+    assert_eq!(sst_file_count(&table_path.join("hot_cf")), 4);
+    assert_eq!(sst_file_count(&table_path.join("cold_cf")), 4);
+
+    for _ in 0..50 {
+        assert_eq!(hot.get_versions(b"row1", b"col1", 10).unwrap().len(), 4);
+        assert_eq!(cold.get_versions(b"row1", b"col1", 10).unwrap().len(), 4);
+        if sst_file_count(&table_path.join("hot_cf")) < 4 {
+            break;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+
+    assert_eq!(sst_file_count(&table_path.join("hot_cf")), 1);
+    assert_eq!(sst_file_count(&table_path.join("cold_cf")), 4);
+
+    let versions = hot.get_versions(b"row1", b"col1", 10).unwrap();
+    assert_eq!(versions.len(), 4);
+    assert_eq!(String::from_utf8_lossy(&versions[0].1), "value4");
+    for i in 1..=4 {
+        assert_eq!(hot.get(format!("other{}", i).as_bytes(), b"col1").unwrap().unwrap(), b"x");
+    }
+
+    drop(dir);
+}
+
+#[test]
+fn test_column_family_get_version_at() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    for i in 1..=3 {
+        cf.put(b"row1".to_vec(), b"col1".to_vec(), format!("value{}", i).into_bytes()).unwrap();
+        thread::sleep(Duration::from_millis(10));
+        if i == 2 {
+            cf.flush().unwrap();
+        }
+    }
+
+    let newest = cf.get_version_at(b"row1", b"col1", 0).unwrap().unwrap();
+    let middle = cf.get_version_at(b"row1", b"col1", 1).unwrap().unwrap();
+    let oldest = cf.get_version_at(b"row1", b"col1", 2).unwrap().unwrap();
+
+    assert_eq!(String::from_utf8_lossy(&newest.1), "value3");
+    assert_eq!(String::from_utf8_lossy(&middle.1), "value2");
+    assert_eq!(String::from_utf8_lossy(&oldest.1), "value1");
+    assert!(newest.0 > middle.0 && middle.0 > oldest.0);
+
+    assert!(cf.get_version_at(b"row1", b"col1", 3).unwrap().is_none());
+    assert!(cf.get_version_at(b"row2", b"col1", 0).unwrap().is_none());
+
+    cf.delete(b"row1".to_vec(), b"col1".to_vec()).unwrap();
+    let after_delete = cf.get_version_at(b"row1", b"col1", 0).unwrap().unwrap();
+    assert_eq!(String::from_utf8_lossy(&after_delete.1), "value3");
+
+    drop(dir);
+}
+
+#[test]
+fn test_column_family_dump_round_trip() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("source").unwrap();
+    table.create_cf("target").unwrap();
+    let source = table.cf("source").unwrap();
+    let target = table.cf("target").unwrap();
+
+    source.put(b"row1".to_vec(), b"col1".to_vec(), b"old".to_vec()).unwrap();
+    thread::sleep(Duration::from_millis(10));
+    source.put(b"row2".to_vec(), b"col1".to_vec(), b"gone".to_vec()).unwrap();
+    source.flush().unwrap();
+    thread::sleep(Duration::from_millis(10));
+    source.put(b"row1".to_vec(), b"col1".to_vec(), b"new".to_vec()).unwrap();
+    source.delete(b"row2".to_vec(), b"col1".to_vec()).unwrap();
+
+    let mut dump = Vec::new();
+    let written = source.export_dump(&mut dump).unwrap();
+    assert_eq!(written, 4);
+
+    let imported = target.import_dump(dump.as_slice()).unwrap();
+    assert_eq!(imported, written);
+
+    assert_eq!(
+        target.get_versions(b"row1", b"col1", 10).unwrap(),
+        source.get_versions(b"row1", b"col1", 10).unwrap()
+    );
+    assert_eq!(target.get(b"row1", b"col1").unwrap().unwrap(), b"new");
+    assert!(target.get(b"row2", b"col1").unwrap().is_none());
+
+    drop(dir);
+}
+
+#[test]
+fn test_column_family_truncated_dump_rejected() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("source").unwrap();
+    table.create_cf("target").unwrap();
+    let source = table.cf("source").unwrap();
+    let target = table.cf("target").unwrap();
+
+    for i in 1..=3 {
+        source.put(format!("row{}", i).into_bytes(), b"col1".to_vec(), b"value".to_vec()).unwrap();
+    }
+
+    let mut dump = Vec::new();
+    source.export_dump(&mut dump).unwrap();
+
+    // Cut the dump off in the middle of its trailer, then in the middle of a record.
+    for cut in [dump.len() - 3, dump.len() / 2] {
+        let err = target.import_dump(&dump[..cut]).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    // A flipped byte inside a record fails its checksum.
+    let mut corrupt = dump.clone();
+    corrupt[20] ^= 0xFF;
+    assert_eq!(target.import_dump(corrupt.as_slice()).unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+
+    for i in 1..=3 {
+        assert!(target.get(format!("row{}", i).as_bytes(), b"col1").unwrap().is_none());
+    }
+
+    drop(dir);
+}
+
+#[test]
+fn test_column_family_check_and_execute_put() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    let versioned_put = |version: &str, data: &str| {
+        let mut put = Put::new(b"row1".to_vec());
+        put.add_column(b"version".to_vec(), version.as_bytes().to_vec());
+        put.add_column(b"data".to_vec(), data.as_bytes().to_vec());
+        put
+    };
+
+    // Absent cell: the first writer wins, a second create fails.
+    assert!(cf.check_and_execute_put(b"row1".to_vec(), b"version".to_vec(), None, versioned_put("1", "a")).unwrap());
+    assert!(!cf.check_and_execute_put(b"row1".to_vec(), b"version".to_vec(), None, versioned_put("1", "b")).unwrap());
+    assert_eq!(cf.get(b"row1", b"data").unwrap().unwrap(), b"a");
+
+    // A swap from the current value applies every column of the put.
+    assert!(cf.check_and_execute_put(b"row1".to_vec(), b"version".to_vec(), Some(b"1"), versioned_put("2", "c")).unwrap());
+    assert_eq!(cf.get(b"row1", b"version").unwrap().unwrap(), b"2");
+    assert_eq!(cf.get(b"row1", b"data").unwrap().unwrap(), b"c");
+
+    // A stale expectation changes nothing.
+    cf.flush().unwrap();
+    assert!(!cf.check_and_execute_put(b"row1".to_vec(), b"version".to_vec(), Some(b"1"), versioned_put("3", "d")).unwrap());
+    assert_eq!(cf.get(b"row1", b"version").unwrap().unwrap(), b"2");
+    assert_eq!(cf.get(b"row1", b"data").unwrap().unwrap(), b"c");
+
+    // Rapid writes push data's newest version ahead of the clock; the put must still land
+    // on top of it, not just on top of the checked column.
+    for _ in 0..200 {
+        cf.put_and_get_previous(b"row1".to_vec(), b"data".to_vec(), b"old".to_vec()).unwrap();
+    }
+    assert!(cf.check_and_execute_put(b"row1".to_vec(), b"version".to_vec(), Some(b"2"), versioned_put("3", "new")).unwrap());
+    assert_eq!(cf.get(b"row1", b"version").unwrap().unwrap(), b"3");
+    assert_eq!(cf.get(b"row1", b"data").unwrap().unwrap(), b"new");
+
+    let err = cf.check_and_execute_put(b"row2".to_vec(), b"version".to_vec(), None, versioned_put("1", "e")).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    assert_eq!(cf.get(b"row2", b"version").unwrap(), None);
+
+    drop(dir);
+}
+
+#[test]
+fn test_column_family_check_and_put_reads_sstables() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    assert!(cf.check_and_put(b"row1".to_vec(), b"col1".to_vec(), None, b"v1".to_vec()).unwrap());
+    assert!(!cf.check_and_put(b"row1".to_vec(), b"col1".to_vec(), None, b"again".to_vec()).unwrap());
+
+    thread::sleep(Duration::from_millis(10));
+    cf.put(b"row1".to_vec(), b"col1".to_vec(), b"v2".to_vec()).unwrap();
+    cf.flush().unwrap();
+
+    // The current value now lives only in SSTables, and a stale expectation must fail.
+    assert!(!cf.check_and_put(b"row1".to_vec(), b"col1".to_vec(), Some(b"v1"), b"stale".to_vec()).unwrap());
+    assert_eq!(cf.get(b"row1", b"col1").unwrap().unwrap(), b"v2");
+
+    assert!(cf.check_and_put(b"row1".to_vec(), b"col1".to_vec(), Some(b"v2"), b"v3".to_vec()).unwrap());
+    assert_eq!(cf.get(b"row1", b"col1").unwrap().unwrap(), b"v3");
+
+    thread::sleep(Duration::from_millis(10));
+    cf.delete(b"row1".to_vec(), b"col1".to_vec()).unwrap();
+    cf.flush().unwrap();
+    assert!(!cf.check_and_put(b"row1".to_vec(), b"col1".to_vec(), Some(b"v3"), b"v4".to_vec()).unwrap());
+    assert!(cf.check_and_put(b"row1".to_vec(), b"col1".to_vec(), None, b"v4".to_vec()).unwrap());
+    assert_eq!(cf.get(b"row1", b"col1").unwrap().unwrap(), b"v4");
+
+    drop(dir);
+}
+
+#[test]
+fn test_column_family_hot_cell_triggers_flush() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    let options = ColumnFamilyOptions {
+        max_versions_in_memstore_per_cell: Some(5),
+        ..ColumnFamilyOptions::default()
+    };
+    table.create_cf_with_options("hot_cf", options).unwrap();
+    let cf = table.cf("hot_cf").unwrap();
+    let cf_path = table_path.join("hot_cf");
+
+    for i in 1..=5 {
+        cf.put(b"row1".to_vec(), b"col1".to_vec(), format!("value{}", i).into_bytes()).unwrap();
+        cf.put(format!("other{}", i).into_bytes(), b"col1".to_vec(), b"x".to_vec()).unwrap();
+        thread::sleep(Duration::from_millis(2));
+    }
+    assert_eq!(sst_file_count(&cf_path), 0);
+
+    cf.put(b"row1".to_vec(), b"col1".to_vec(), b"value6".to_vec()).unwrap();
+    assert_eq!(sst_file_count(&cf_path), 1);
+
+    let versions = cf.get_versions(b"row1", b"col1", 10).unwrap();
+    assert_eq!(versions.len(), 6);
+    assert_eq!(String::from_utf8_lossy(&versions[0].1), "value6");
+
+    drop(dir);
+}
+
+#[test]
+fn test_column_family_auto_flush_disabled() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    let options = ColumnFamilyOptions::builder()
+        .flush_threshold(10)
+        .max_versions_in_memstore_per_cell(2)
+        .auto_flush(false)
+        .build();
+    table.create_cf_with_options("bulk_cf", options).unwrap();
+    let cf = table.cf("bulk_cf").unwrap();
+    let cf_path = table_path.join("bulk_cf");
+
+    for i in 0..100 {
+        cf.put(format!("row{:03}", i).into_bytes(), b"col1".to_vec(), b"x".to_vec()).unwrap();
+    }
+    for i in 0..5 {
+        cf.put(b"hot".to_vec(), b"col1".to_vec(), format!("v{}", i).into_bytes()).unwrap();
+        thread::sleep(Duration::from_millis(2));
+    }
+    assert_eq!(sst_file_count(&cf_path), 0);
+    let (_, source) = cf.get_with_source(b"row050", b"col1").unwrap().unwrap();
+    assert_eq!(source, ReadSource::Memstore);
+
+    cf.flush().unwrap();
+    assert_eq!(sst_file_count(&cf_path), 1);
+    assert_eq!(cf.get_versions(b"hot", b"col1", 10).unwrap().len(), 5);
+    assert_eq!(cf.scan_with_filter(b"row000", b"row999", &FilterSet::new()).unwrap().len(), 100);
+
+    drop(dir);
+}
+
+#[test]
+fn test_column_family_get_versions_raw() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    cf.put(b"row1".to_vec(), b"col1".to_vec(), b"first".to_vec()).unwrap();
+    thread::sleep(Duration::from_millis(10));
+    cf.delete_with_ttl(b"row1".to_vec(), b"col1".to_vec(), Some(5000)).unwrap();
+    cf.flush().unwrap();
+    thread::sleep(Duration::from_millis(10));
+    cf.put(b"row1".to_vec(), b"col1".to_vec(), b"second".to_vec()).unwrap();
+
+    let raw = cf.get_versions_raw(b"row1", b"col1", 10).unwrap();
+    assert_eq!(raw.len(), 3);
+    assert_eq!(raw[0].1, CellValue::Put(b"second".to_vec()));
+    assert_eq!(raw[1].1, CellValue::Delete(Some(5000)));
+    assert_eq!(raw[2].1, CellValue::Put(b"first".to_vec()));
+    assert!(raw[0].0 > raw[1].0 && raw[1].0 > raw[2].0);
+
+    let live = cf.get_versions(b"row1", b"col1", 10).unwrap();
+    assert_eq!(live.len(), 2);
+    assert_eq!(live[0].0, raw[0].0);
+    assert_eq!(live[1].0, raw[2].0);
+
+    assert_eq!(cf.get_versions_raw(b"row1", b"col1", 2).unwrap(), raw[..2].to_vec());
+
+    drop(dir);
+}
+
+#[test]
+fn test_column_family_options_builder() {
+    let (dir, table_path) = temp_table_dir();
+
+    let options = ColumnFamilyOptions::builder()
+        .flush_threshold(3)
+        .compaction_interval(Duration::from_secs(5))
+        .read_triggered_compaction(true)
+        .read_compaction_threshold(4)
+        .max_versions_in_memstore_per_cell(2)
+        .build();
+    assert_eq!(options.flush_threshold, 3);
+    assert_eq!(options.compaction_interval, Duration::from_secs(5));
+    assert!(options.read_triggered_compaction);
+    assert_eq!(options.read_compaction_threshold, 4);
+    assert_eq!(options.max_versions_in_memstore_per_cell, Some(2));
+
+    let defaults = ColumnFamilyOptions::builder().build();
+    assert_eq!(defaults.flush_threshold, ColumnFamilyOptions::default().flush_threshold);
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf_with_options("small_cf", options).unwrap();
+    let cf = table.cf("small_cf").unwrap();
+    let cf_path = table_path.join("small_cf");
+
+    for i in 1..=3 {
+        cf.put(format!("row{}", i).into_bytes(), b"col1".to_vec(), b"value".to_vec()).unwrap();
+    }
+    assert_eq!(sst_file_count(&cf_path), 0);
+
+    cf.put(b"row4".to_vec(), b"col1".to_vec(), b"value".to_vec()).unwrap();
+    assert_eq!(sst_file_count(&cf_path), 1);
+
+    for i in 1..=4 {
+        assert!(cf.get(format!("row{}", i).as_bytes(), b"col1").unwrap().is_some());
+    }
+
+    drop(dir);
+}
+
+#[test]
+fn test_flush_threshold_applies_to_every_write() {
+    let (dir, table_path) = temp_table_dir();
+    let options = ColumnFamilyOptions::builder().flush_threshold(5).build();
+
+    // Each write path flushes on the sixth MemStore entry.
+    type Write = fn(&ColumnFamily, Vec<u8>);
+    let writers: [(&str, Write); 3] = [
+        ("put_cf", |cf, row| cf.put(row, b"col1".to_vec(), b"value".to_vec()).unwrap()),
+        ("execute_put_cf", |cf, row| {
+            let mut put = Put::new(row);
+            put.add_column(b"col1".to_vec(), b"value".to_vec());
+            cf.execute_put(put).unwrap();
+        }),
+        ("delete_cf", |cf, row| cf.delete_with_ttl(row, b"col1".to_vec(), None).unwrap()),
+    ];
+    for (name, write) in writers {
+        let cf = ColumnFamily::open_with_options(&table_path, name, options.clone()).unwrap();
+        let cf_path = table_path.join(name);
+
+        for i in 1..=5 {
+            write(&cf, format!("row{}", i).into_bytes());
+        }
+        assert_eq!(sst_file_count(&cf_path), 0, "{}", name);
+
+        write(&cf, b"row6".to_vec());
+        assert_eq!(sst_file_count(&cf_path), 1, "{}", name);
+    }
+
+    drop(dir);
+}
+
+#[test]
+fn test_unflushed_writes_survive_reopen() {
+    let (dir, table_path) = temp_table_dir();
+    let wal_path = table_path.join("test_cf").join("wal.log");
+
+    {
+        let mut table = Table::open(&table_path).unwrap();
+        table.create_cf("test_cf").unwrap();
+        let cf = table.cf("test_cf").unwrap();
+        cf.put(b"row1".to_vec(), b"col1".to_vec(), b"v1".to_vec()).unwrap();
+        thread::sleep(Duration::from_millis(10));
+        cf.put(b"row1".to_vec(), b"col1".to_vec(), b"v2".to_vec()).unwrap();
+        cf.put(b"row1".to_vec(), b"gone".to_vec(), b"x".to_vec()).unwrap();
+        thread::sleep(Duration::from_millis(10));
+        cf.delete(b"row1".to_vec(), b"gone".to_vec()).unwrap();
+    }
+
+    // The WAL is replayed on open.
+    {
+        let table = Table::open(&table_path).unwrap();
+        let cf = table.cf("test_cf").unwrap();
+        assert_eq!(cf.get(b"row1", b"col1").unwrap().unwrap(), b"v2");
+        assert_eq!(cf.get(b"row1", b"gone").unwrap(), None);
+        assert_eq!(cf.get_versions(b"row1", b"col1", 10).unwrap().len(), 2);
+
+        // A flush empties the WAL, so the flushed entries are not replayed a second time.
+        cf.flush().unwrap();
+        assert_eq!(fs::metadata(&wal_path).unwrap().len(), 0);
+        cf.put(b"row2".to_vec(), b"col1".to_vec(), b"after".to_vec()).unwrap();
+    }
+
+    let table = Table::open(&table_path).unwrap();
+    let cf = table.cf("test_cf").unwrap();
+    assert_eq!(cf.get(b"row1", b"col1").unwrap().unwrap(), b"v2");
+    assert_eq!(cf.get(b"row2", b"col1").unwrap().unwrap(), b"after");
+    assert_eq!(cf.get_versions_raw(b"row1", b"col1", 10).unwrap().len(), 2);
+
+    drop(dir);
+}
+
+#[test]
+fn test_column_family_options_persist_across_reopen() {
+    let (dir, table_path) = temp_table_dir();
+
+    {
+        let mut table = Table::open(&table_path).unwrap();
+        let options = ColumnFamilyOptions::builder()
+            .ttl_ms(200)
+            .flush_threshold(500)
+            .build();
+        table.create_cf_with_options("ttl_cf", options).unwrap();
+        let cf = table.cf("ttl_cf").unwrap();
+        cf.put(b"row1".to_vec(), b"col1".to_vec(), b"short-lived".to_vec()).unwrap();
+        assert!(cf.get(b"row1", b"col1").unwrap().is_some());
+        cf.close();
+    }
+    assert!(table_path.join("ttl_cf").join("options.json").exists());
+
+    let table = Table::open(&table_path).unwrap();
+    let cf = table.cf("ttl_cf").unwrap();
+    assert_eq!(cf.options().ttl_ms, Some(200));
+    assert_eq!(cf.options().flush_threshold, 500);
+
+    cf.put(b"row2".to_vec(), b"col1".to_vec(), b"fresh".to_vec()).unwrap();
+    thread::sleep(Duration::from_millis(300));
+    assert!(cf.get(b"row1", b"col1").unwrap().is_none());
+    assert!(cf.get(b"row2", b"col1").unwrap().is_none());
+    assert!(cf.get_versions(b"row1", b"col1", 10).unwrap().is_empty());
+
+    // Options passed explicitly at open time replace the saved ones.
+    let reopened = ColumnFamily::open_with_options(&table_path, "ttl_cf", ColumnFamilyOptions::default()).unwrap();
+    assert_eq!(reopened.options().ttl_ms, None);
+    assert_eq!(reopened.get(b"row1", b"col1").unwrap().unwrap(), b"short-lived");
+    let table = Table::open(&table_path).unwrap();
+    assert_eq!(table.cf("ttl_cf").unwrap().options().ttl_ms, None);
+
+    let invalid = ColumnFamilyOptions::builder().flush_threshold(0).build();
+    let err = ColumnFamily::open_with_options(&table_path, "bad_cf", invalid).err().unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+
+    drop(dir);
+}
+
+struct ChannelListener {
+    flushes: Mutex<mpsc::Sender<FlushInfo>>,
+    compactions: Mutex<mpsc::Sender<CompactionInfo>>,
+}
+
+impl EventListener for ChannelListener {
+    fn on_flush(&self, info: FlushInfo) {
+        self.flushes.lock().unwrap().send(info).unwrap();
+    }
+
+    fn on_compaction(&self, info: CompactionInfo) {
+        self.compactions.lock().unwrap().send(info).unwrap();
+    }
+}
+
+#[test]
+fn test_column_family_event_listener() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    let (flush_tx, flush_rx) = mpsc::channel();
+    let (compaction_tx, compaction_rx) = mpsc::channel();
+    cf.add_listener(Arc::new(ChannelListener {
+        flushes: Mutex::new(flush_tx),
+        compactions: Mutex::new(compaction_tx),
+    }));
+
+    for i in 1..=2 {
+        cf.put(b"row1".to_vec(), format!("col{}", i).into_bytes(), b"value".to_vec()).unwrap();
+        cf.flush().unwrap();
+
+        let info = flush_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(info.cf_name, "test_cf");
+        assert_eq!(info.entries, 1);
+        assert!(info.bytes > 0);
+        assert!(info.sstable.exists());
+    }
+
+    // An empty flush writes nothing and reports nothing.
+    cf.flush().unwrap();
+    assert!(flush_rx.recv_timeout(Duration::from_millis(200)).is_err());
+
+    cf.major_compact().unwrap();
+    let info = compaction_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(info.compaction_type, CompactionType::Major);
+    assert_eq!(info.inputs.len(), 2);
+    assert!(info.inputs.iter().all(|path| !path.exists()));
+    assert!(info.outputs.iter().all(|output| output.exists()));
+    assert!(info.input_bytes > 0 && info.output_bytes > 0);
+
+    drop(dir);
+}
+
+#[test]
+fn test_column_family_scan_modified_since() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    for i in 1..=3 {
+        cf.put(format!("row{}", i).into_bytes(), b"col1".to_vec(), b"old".to_vec()).unwrap();
+    }
+    cf.flush().unwrap();
+    thread::sleep(Duration::from_millis(10));
+
+    let since = chrono::Utc::now().timestamp_millis() as u64;
+    thread::sleep(Duration::from_millis(10));
+
+    cf.put(b"row3".to_vec(), b"col2".to_vec(), b"new".to_vec()).unwrap();
+    cf.delete(b"row2".to_vec(), b"col1".to_vec()).unwrap();
+    cf.flush().unwrap();
+    cf.put(b"row4".to_vec(), b"col1".to_vec(), b"new".to_vec()).unwrap();
+
+    let changes = cf.scan_modified_since(b"row1", b"row9", since).unwrap();
+    assert_eq!(changes.keys().cloned().collect::<Vec<_>>(), vec![b"row2".to_vec(), b"row3".to_vec(), b"row4".to_vec()]);
+
+    let row2 = &changes[b"row2".as_slice()][b"col1".as_slice()];
+    assert_eq!(row2.len(), 1);
+    assert_eq!(row2[0].1, CellValue::Delete(None));
+    assert!(row2[0].0 > since);
+
+    let row3 = &changes[b"row3".as_slice()];
+    assert!(!row3.contains_key(b"col1".as_slice()));
+    assert_eq!(row3[b"col2".as_slice()][0].1, CellValue::Put(b"new".to_vec()));
+
+    let narrowed = cf.scan_modified_since(b"row3", b"row3", since).unwrap();
+    assert_eq!(narrowed.len(), 1);
+
+    assert!(cf.scan_modified_since(b"row1", b"row9", u64::MAX - 1).unwrap().is_empty());
+
+    drop(dir);
+}
+
+#[test]
+fn test_column_family_open_read_only() {
+    let (dir, table_path) = temp_table_dir();
+
+    {
+        let mut table = Table::open(&table_path).unwrap();
+        table.create_cf("test_cf").unwrap();
+        let cf = table.cf("test_cf").unwrap();
+        cf.put(b"row1".to_vec(), b"col1".to_vec(), b"value1".to_vec()).unwrap();
+        cf.flush().unwrap();
+        cf.close();
+    }
+
+    // Leave only the SSTable behind and make the directory read-only, so a WAL can't be created.
+    let cf_path = table_path.join("test_cf");
+    fs::remove_file(cf_path.join("wal.log")).unwrap();
+    fs::remove_file(cf_path.join("options.json")).unwrap();
+    let mut perms = fs::metadata(&cf_path).unwrap().permissions();
+    perms.set_readonly(true);
+    fs::set_permissions(&cf_path, perms.clone()).unwrap();
+
+    let table = Table::open_read_only(&table_path).unwrap();
+    let cf = table.cf("test_cf").unwrap();
+    assert!(cf.is_read_only());
+    assert_eq!(cf.get(b"row1", b"col1").unwrap(), Some(b"value1".to_vec()));
+
+    let err = cf.put(b"row2".to_vec(), b"col1".to_vec(), b"value2".to_vec()).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+    assert_eq!(cf.flush().unwrap_err().kind(), std::io::ErrorKind::PermissionDenied);
+    assert_eq!(cf.major_compact().unwrap_err().kind(), std::io::ErrorKind::PermissionDenied);
+
+    let mut table = table;
+    assert_eq!(table.create_cf("other_cf").unwrap_err().kind(), std::io::ErrorKind::PermissionDenied);
+    assert!(!cf_path.join("wal.log").exists());
+    assert!(!cf_path.join("options.json").exists());
+    assert!(!table_path.join("other_cf").exists());
+
+    assert_eq!(
+        ColumnFamily::open_read_only(&table_path, "missing_cf").err().map(|e| e.kind()),
+        Some(std::io::ErrorKind::NotFound)
+    );
+
+    #[allow(clippy::permissions_set_readonly_false)]
+    perms.set_readonly(false);
+    fs::set_permissions(&cf_path, perms).unwrap();
+    drop(dir);
+}
+
+#[test]
+fn test_column_family_drop_column() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    for i in 1..=5 {
+        let row = format!("row{}", i).into_bytes();
+        cf.put(row.clone(), b"retired".to_vec(), b"old".to_vec()).unwrap();
+        cf.put(row, b"kept".to_vec(), format!("value{}", i).into_bytes()).unwrap();
+        if i == 3 {
+            cf.flush().unwrap();
+        }
+    }
+
+    assert_eq!(cf.drop_column(b"retired".to_vec()).unwrap(), 5);
+
+    for i in 1..=5 {
+        let row = format!("row{}", i).into_bytes();
+        assert_eq!(cf.get(&row, b"retired").unwrap(), None);
+        assert_eq!(cf.get(&row, b"kept").unwrap(), Some(format!("value{}", i).into_bytes()));
+    }
+
+    cf.flush().unwrap();
+    cf.major_compact().unwrap();
+    assert_eq!(cf.get(b"row2", b"retired").unwrap(), None);
+    assert_eq!(cf.drop_column(b"retired".to_vec()).unwrap(), 0);
+
+    drop(dir);
+}
+
+#[test]
+fn test_column_family_distinct_columns() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    cf.put(b"user1".to_vec(), b"name".to_vec(), b"Ann".to_vec()).unwrap();
+    cf.put(b"user1".to_vec(), b"email".to_vec(), b"ann@example.com".to_vec()).unwrap();
+    cf.flush().unwrap();
+    cf.put(b"user2".to_vec(), b"name".to_vec(), b"Bob".to_vec()).unwrap();
+    cf.put(b"user2".to_vec(), b"phone".to_vec(), b"555".to_vec()).unwrap();
+    cf.put(b"user3".to_vec(), b"fax".to_vec(), b"556".to_vec()).unwrap();
+    cf.put(b"user9".to_vec(), b"outside".to_vec(), b"x".to_vec()).unwrap();
+    thread::sleep(Duration::from_millis(10));
+    cf.delete(b"user3".to_vec(), b"fax".to_vec()).unwrap();
+
+    let columns = cf.distinct_columns(b"user1", b"user3").unwrap();
+    let expected: BTreeSet<Vec<u8>> = [b"email".to_vec(), b"name".to_vec(), b"phone".to_vec()].into();
+    assert_eq!(columns, expected);
+
+    assert!(cf.distinct_columns(b"user4", b"user8").unwrap().is_empty());
+
+    drop(dir);
+}
+
+#[test]
+fn test_column_family_delete_prefix() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    for row in ["tenant1:a", "tenant1:b", "tenant10:a", "tenant2:a"] {
+        cf.put(row.as_bytes().to_vec(), b"col1".to_vec(), b"v".to_vec()).unwrap();
+        cf.put(row.as_bytes().to_vec(), b"col2".to_vec(), b"v".to_vec()).unwrap();
+    }
+    cf.flush().unwrap();
+    cf.put(b"tenant1:c".to_vec(), b"col1".to_vec(), b"v".to_vec()).unwrap();
+    cf.put(vec![0xFF, 0xFF, 0x01], b"col1".to_vec(), b"v".to_vec()).unwrap();
+
+    assert_eq!(cf.delete_prefix(b"tenant1:").unwrap(), 5);
+
+    for row in ["tenant1:a", "tenant1:b", "tenant1:c"] {
+        assert_eq!(cf.get(row.as_bytes(), b"col1").unwrap(), None);
+        assert_eq!(cf.get(row.as_bytes(), b"col2").unwrap(), None);
+    }
+    for row in ["tenant10:a", "tenant2:a"] {
+        assert_eq!(cf.get(row.as_bytes(), b"col1").unwrap(), Some(b"v".to_vec()));
+    }
+
+    // An all-0xFF prefix has no upper bound and reads to the end of the keyspace.
+    assert_eq!(cf.delete_prefix(&[0xFF, 0xFF]).unwrap(), 1);
+    assert_eq!(cf.get(&[0xFF, 0xFF, 0x01], b"col1").unwrap(), None);
+    assert_eq!(cf.delete_prefix(b"tenant1:").unwrap(), 0);
+
+    drop(dir);
+}
+
+#[test]
+fn test_table_get_any() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    for name in ["cf_a", "cf_b", "cf_c"] {
+        table.create_cf(name).unwrap();
+    }
+    table.cf("cf_a").unwrap().put(b"row1".to_vec(), b"other".to_vec(), b"a".to_vec()).unwrap();
+    table.cf("cf_b").unwrap().put(b"row1".to_vec(), b"email".to_vec(), b"b".to_vec()).unwrap();
+    table.cf("cf_c").unwrap().put(b"row2".to_vec(), b"email".to_vec(), b"c".to_vec()).unwrap();
+
+    assert_eq!(table.get_any(b"row1", b"email").unwrap(), Some(("cf_b".to_string(), b"b".to_vec())));
+    assert_eq!(table.get_any(b"row2", b"email").unwrap(), Some(("cf_c".to_string(), b"c".to_vec())));
+    assert_eq!(table.get_any(b"row3", b"email").unwrap(), None);
+
+    // With the column in several column families, the first by name wins.
+    table.cf("cf_c").unwrap().put(b"row1".to_vec(), b"email".to_vec(), b"c".to_vec()).unwrap();
+    assert_eq!(table.get_any(b"row1", b"email").unwrap(), Some(("cf_b".to_string(), b"b".to_vec())));
+
+    drop(dir);
+}
+
+#[test]
+fn test_column_family_seal() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    cf.put(b"row1".to_vec(), b"col1".to_vec(), b"value1".to_vec()).unwrap();
+    cf.flush().unwrap();
+    cf.put(b"row2".to_vec(), b"col1".to_vec(), b"value2".to_vec()).unwrap();
+
+    table.cf("test_cf").unwrap().seal();
+    assert!(cf.is_sealed());
+
+    let err = cf.put(b"row3".to_vec(), b"col1".to_vec(), b"value3".to_vec()).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+    assert!(err.to_string().contains("sealed"));
+    assert!(cf.delete(b"row1".to_vec(), b"col1".to_vec()).is_err());
+    let mut put = Put::new(b"row4".to_vec());
+    put.add_column(b"col1".to_vec(), b"value4".to_vec());
+    assert!(cf.execute_put(put).is_err());
+
+    let mut dump = Vec::new();
+    cf.export_dump(&mut dump).unwrap();
+    let err = cf.import_dump(dump.as_slice()).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+
+    assert_eq!(cf.get(b"row1", b"col1").unwrap(), Some(b"value1".to_vec()));
+    assert_eq!(cf.get(b"row2", b"col1").unwrap(), Some(b"value2".to_vec()));
+    assert_eq!(cf.get(b"row3", b"col1").unwrap(), None);
+
+    // Data written before the seal can still be flushed and compacted.
+    cf.flush().unwrap();
+    cf.major_compact().unwrap();
+    assert_eq!(cf.get(b"row2", b"col1").unwrap(), Some(b"value2".to_vec()));
+
+    drop(dir);
+}
+
+#[test]
+fn test_table_compact_all() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    let names: Vec<String> = (1..=6).map(|i| format!("cf{}", i)).collect();
+    for name in &names {
+        table.create_cf(name).unwrap();
+        let cf = table.cf(name).unwrap();
+        for i in 1..=3 {
+            cf.put(format!("row{}", i).into_bytes(), b"col1".to_vec(), name.clone().into_bytes()).unwrap();
+            cf.flush().unwrap();
+        }
+        assert_eq!(cf.sstable_usage().unwrap().0, 3);
+    }
+
+    table.compact_all().unwrap();
+
+    for name in &names {
+        let cf = table.cf(name).unwrap();
+        assert_eq!(cf.sstable_usage().unwrap().0, 1);
+        for i in 1..=3 {
+            assert_eq!(cf.get(format!("row{}", i).as_bytes(), b"col1").unwrap(), Some(name.clone().into_bytes()));
+        }
+    }
+
+    drop(dir);
+}
+
+#[test]
+fn test_column_family_get_with_source() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    cf.put(b"row1".to_vec(), b"col1".to_vec(), b"value1".to_vec()).unwrap();
+    assert_eq!(
+        cf.get_with_source(b"row1", b"col1").unwrap(),
+        Some((b"value1".to_vec(), ReadSource::Memstore))
+    );
+
+    cf.flush().unwrap();
+    let sst_path = fs::read_dir(table_path.join("test_cf")).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .find(|path| path.extension().is_some_and(|ext| ext == "sst"))
+        .unwrap();
+    assert_eq!(
+        cf.get_with_source(b"row1", b"col1").unwrap(),
+        Some((b"value1".to_vec(), ReadSource::SSTable(sst_path)))
+    );
+
+    assert_eq!(cf.get_with_source(b"row2", b"col1").unwrap(), None);
+
+    drop(dir);
+}
+
+#[test]
+fn test_compaction_splits_output_by_target_size() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    for batch in 0..3 {
+        for i in (batch..300).step_by(3) {
+            let row = format!("row{:04}", i).into_bytes();
+            for col in ["a", "b", "c"] {
+                cf.put(row.clone(), col.as_bytes().to_vec(), vec![b'x'; 100]).unwrap();
+            }
+        }
+        cf.flush().unwrap();
+    }
+
+    let options = CompactionOptions {
+        compaction_type: CompactionType::Major,
+        target_output_bytes: Some(8 * 1024),
+        ..CompactionOptions::default()
+    };
+    cf.compact_with_options(options).unwrap();
+
+    let mut sst_paths: Vec<PathBuf> = fs::read_dir(table_path.join("test_cf")).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "sst"))
+        .collect();
+    sst_paths.sort();
+    assert!(sst_paths.len() > 3, "expected several outputs, got {}", sst_paths.len());
+    assert_eq!(cf.sstable_usage().unwrap().0, sst_paths.len());
+
+    let footers: Vec<_> = sst_paths.iter()
+        .map(|path| SSTableReader::read_footer(path).unwrap().unwrap())
+        .collect();
+    assert_eq!(footers.first().unwrap().min_row, b"row0000".to_vec());
+    assert_eq!(footers.last().unwrap().max_row, b"row0299".to_vec());
+    for pair in footers.windows(2) {
+        assert!(pair[0].max_row < pair[1].min_row, "output files overlap");
+    }
+
+    for i in [0, 1, 150, 298, 299] {
+        let row = format!("row{:04}", i).into_bytes();
+        assert_eq!(cf.get(&row, b"b").unwrap(), Some(vec![b'x'; 100]));
+    }
+
+    // A reopened column family picks up the split files in the same order.
+    drop(cf);
+    drop(table);
+    let table = Table::open(&table_path).unwrap();
+    let cf = table.cf("test_cf").unwrap();
+    assert_eq!(cf.get(b"row0150", b"c").unwrap(), Some(vec![b'x'; 100]));
+    cf.put(b"row0150".to_vec(), b"c".to_vec(), b"new".to_vec()).unwrap();
+    cf.flush().unwrap();
+    assert_eq!(cf.get(b"row0150", b"c").unwrap(), Some(b"new".to_vec()));
+
+    drop(dir);
+}
+
+#[test]
+fn test_column_family_await_compaction() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    let options = ColumnFamilyOptions::builder()
+        .read_triggered_compaction(true)
+        .read_compaction_threshold(2)
+        .build();
+    table.create_cf_with_options("test_cf", options).unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    for i in 1..=4 {
+        cf.put(b"row1".to_vec(), b"col1".to_vec(), format!("value{}", i).into_bytes()).unwrap();
+        cf.put(format!("other{}", i).into_bytes(), b"col1".to_vec(), b"x".to_vec()).unwrap();
+        cf.flush().unwrap();
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(cf.sstable_usage().unwrap().0, 4);
+
+    // Reading row1 consults all four SSTables and schedules a compaction in the background.
+    assert_eq!(cf.get_versions(b"row1", b"col1", 10).unwrap().len(), 4);
+    cf.await_compaction().unwrap();
+    assert_eq!(cf.sstable_usage().unwrap().0, 1);
+    assert_eq!(sst_file_count(&table_path.join("test_cf")), 1);
+
+    // Nothing running: returns straight away.
+    cf.await_compaction().unwrap();
+
+    drop(dir);
+}
+
+#[test]
+fn test_column_family_counters() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    assert_eq!(cf.get_counter(b"page1", b"views").unwrap(), None);
+    assert_eq!(cf.increment(b"page1".to_vec(), b"views".to_vec(), 5).unwrap(), 5);
+    assert_eq!(cf.increment(b"page1".to_vec(), b"views".to_vec(), 3).unwrap(), 8);
+    cf.flush().unwrap();
+    assert_eq!(cf.increment(b"page1".to_vec(), b"views".to_vec(), -2).unwrap(), 6);
+    assert_eq!(cf.get_counter(b"page1", b"views").unwrap(), Some(6));
+    assert_eq!(cf.get_versions_raw(b"page1", b"views", 1).unwrap()[0].1, CellValue::Counter(6));
+    assert_eq!(cf.get(b"page1", b"views").unwrap(), Some(6i64.to_be_bytes().to_vec()));
+
+    // Counters written as decimal text before native counters existed carry on.
+    cf.put(b"page2".to_vec(), b"views".to_vec(), b"41".to_vec()).unwrap();
+    assert_eq!(cf.increment(b"page2".to_vec(), b"views".to_vec(), 1).unwrap(), 42);
+
+    cf.put(b"page3".to_vec(), b"views".to_vec(), b"many".to_vec()).unwrap();
+    let err = cf.increment(b"page3".to_vec(), b"views".to_vec(), 1).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+    thread::sleep(Duration::from_millis(10));
+    cf.delete(b"page1".to_vec(), b"views".to_vec()).unwrap();
+    assert_eq!(cf.get_counter(b"page1", b"views").unwrap(), None);
+    assert_eq!(cf.increment(b"page1".to_vec(), b"views".to_vec(), 1).unwrap(), 1);
+
+    cf.increment(b"page4".to_vec(), b"views".to_vec(), i64::MAX).unwrap();
+    let err = cf.increment(b"page4".to_vec(), b"views".to_vec(), 1).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+
+    // Counters survive compaction and aggregate as 8-byte integers, no text involved.
+    cf.flush().unwrap();
+    cf.major_compact().unwrap();
+    assert_eq!(cf.get_counter(b"page2", b"views").unwrap(), Some(42));
+
+    let mut agg_set = AggregationSet::new();
+    agg_set.add_encoded_aggregation(b"views".to_vec(), AggregationType::Sum, ValueEncoding::BeI64);
+    let mut filter_set = FilterSet::new();
+    filter_set.max_versions = Some(1);
+    let result = cf.aggregate_range(b"page1", b"page2", Some(&filter_set), &agg_set).unwrap();
+    assert_eq!(result[b"page1".as_slice()][b"views".as_slice()].to_string(), "1");
+    assert_eq!(result[b"page2".as_slice()][b"views".as_slice()].to_string(), "42");
+
+    // A text counter converted by increment still sums with the default text encoding:
+    // its old version is read as text and its counter version as an integer.
+    let mut agg_set = AggregationSet::new();
+    agg_set.add_aggregation(b"views".to_vec(), AggregationType::Sum);
+    let result = cf.aggregate_range(b"page2", b"page2", Some(&filter_set), &agg_set).unwrap();
+    assert_eq!(result[b"page2".as_slice()][b"views".as_slice()].to_string(), "42");
+    let result = cf.aggregate(b"page2", Some(&filter_set), &agg_set).unwrap();
+    assert_eq!(result[b"views".as_slice()].to_string(), "42");
+    let result = cf.aggregate(b"page2", None, &agg_set).unwrap();
+    assert_eq!(result[b"views".as_slice()].to_string(), "83");
+
+    drop(dir);
+}
+
+#[test]
+fn test_column_family_compact_max_age_after_rapid_increments() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    // Increments within one millisecond get timestamps ahead of the clock; an age limit
+    // must treat them as brand new rather than underflow.
+    for _ in 0..2000 {
+        cf.increment(b"page1".to_vec(), b"views".to_vec(), 1).unwrap();
+    }
+    cf.flush().unwrap();
+    cf.compact_with_options(CompactionOptions {
+        compaction_type: CompactionType::Major,
+        max_age_ms: Some(60_000),
+        ..Default::default()
+    }).unwrap();
+    assert_eq!(cf.get_counter(b"page1", b"views").unwrap(), Some(2000));
+
+    drop(dir);
+}
+
+#[test]
+fn test_column_family_increment_from_threads() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    let handles: Vec<_> = (0..8)
+        .map(|_| {
+            let cf = cf.clone();
+            thread::spawn(move || {
+                for _ in 0..25 {
+                    cf.increment(b"row1".to_vec(), b"hits".to_vec(), 2).unwrap();
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    assert_eq!(cf.get_counter(b"row1", b"hits").unwrap(), Some(400));
+    assert_eq!(cf.increment(b"row1".to_vec(), b"hits".to_vec(), -400).unwrap(), 0);
+
+    drop(dir);
+}
+
+#[test]
+fn test_column_family_typed_values() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    cf.put_i64(b"row1".to_vec(), b"int".to_vec(), -42).unwrap();
+    cf.put_f64(b"row1".to_vec(), b"float".to_vec(), 2.5).unwrap();
+    cf.put_bool(b"row1".to_vec(), b"flag".to_vec(), true).unwrap();
+    cf.put_bool(b"row2".to_vec(), b"flag".to_vec(), false).unwrap();
+    cf.flush().unwrap();
+
+    assert_eq!(cf.get_i64(b"row1", b"int").unwrap(), Some(-42));
+    assert_eq!(cf.get_f64(b"row1", b"float").unwrap(), Some(2.5));
+    assert_eq!(cf.get_bool(b"row1", b"flag").unwrap(), Some(true));
+    assert_eq!(cf.get_bool(b"row2", b"flag").unwrap(), Some(false));
+    assert_eq!(cf.get_i64(b"row1", b"missing").unwrap(), None);
+    assert_eq!(cf.get(b"row1", b"int").unwrap().unwrap(), (-42i64).to_be_bytes());
+
+    // Counters share the i64 layout.
+    cf.increment(b"row3".to_vec(), b"int".to_vec(), 7).unwrap();
+    assert_eq!(cf.get_i64(b"row3", b"int").unwrap(), Some(7));
+
+    let err = cf.get_i64(b"row1", b"flag").unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    cf.put(b"row4".to_vec(), b"flag".to_vec(), vec![2]).unwrap();
+    assert_eq!(cf.get_bool(b"row4", b"flag").unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+
+    // Aggregations read the same layouts.
+    let mut agg_set = AggregationSet::new();
+    agg_set.add_encoded_aggregation(b"int".to_vec(), AggregationType::Sum, ValueEncoding::BeI64);
+    agg_set.add_encoded_aggregation(b"float".to_vec(), AggregationType::Average, ValueEncoding::LeF64);
+    let result = cf.aggregate_range(b"row1", b"row9", None, &agg_set).unwrap();
+    assert_eq!(result[b"row1".as_slice()][b"int".as_slice()].to_string(), "-42");
+    assert_eq!(result[b"row1".as_slice()][b"float".as_slice()].to_string(), "2.5");
+    assert_eq!(result[b"row3".as_slice()][b"int".as_slice()].to_string(), "7");
+
+    drop(dir);
+}
+
+#[test]
+fn test_column_family_iter_entries_range() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    for value in ["v1", "v2"] {
+        cf.put(b"row1".to_vec(), b"col1".to_vec(), value.as_bytes().to_vec()).unwrap();
+        thread::sleep(Duration::from_millis(10));
+    }
+    cf.put(b"row1".to_vec(), b"col2".to_vec(), b"c2".to_vec()).unwrap();
+    cf.put(b"row1".to_vec(), b"col3".to_vec(), b"c3".to_vec()).unwrap();
+    cf.put(b"row2".to_vec(), b"col1".to_vec(), b"r2".to_vec()).unwrap();
+    cf.flush().unwrap();
+    thread::sleep(Duration::from_millis(10));
+    cf.put(b"row1".to_vec(), b"col1".to_vec(), b"v3".to_vec()).unwrap();
+    thread::sleep(Duration::from_millis(10));
+    cf.delete(b"row1".to_vec(), b"col2".to_vec()).unwrap();
+
+    let everything = |row: &[u8]| EntryKey { row: row.to_vec(), column: Vec::new(), timestamp: 0 };
+    let all: Vec<(EntryKey, CellValue)> = cf
+        .iter_entries_range(everything(b"row1"), everything(b"row3"))
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(all.len(), 7);
+    let v2_ts = all[1].0.timestamp;
+    let col2_put_ts = all[3].0.timestamp;
+
+    // From col1's second version up to and including col2's put, but not its newer tombstone.
+    let start = EntryKey { row: b"row1".to_vec(), column: b"col1".to_vec(), timestamp: v2_ts };
+    let end = EntryKey { row: b"row1".to_vec(), column: b"col2".to_vec(), timestamp: col2_put_ts };
+    let entries: Vec<(Vec<u8>, CellValue)> = cf
+        .iter_entries_range(start, end)
+        .map(|entry| entry.map(|(key, value)| (key.column, value)))
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(entries, vec![
+        (b"col1".to_vec(), CellValue::Put(b"v2".to_vec())),
+        (b"col1".to_vec(), CellValue::Put(b"v3".to_vec())),
+        (b"col2".to_vec(), CellValue::Put(b"c2".to_vec())),
+    ]);
+
+    drop(dir);
+}
+
+#[test]
+fn test_column_family_ingest_from() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("target").unwrap();
+    table.create_cf("source").unwrap();
+    let target = table.cf("target").unwrap();
+    let source = table.cf("source").unwrap();
+
+    target.put(b"a_only".to_vec(), b"col1".to_vec(), b"a".to_vec()).unwrap();
+    target.put(b"shared".to_vec(), b"col1".to_vec(), b"target_old".to_vec()).unwrap();
+    thread::sleep(Duration::from_millis(10));
+    source.put(b"b_only".to_vec(), b"col1".to_vec(), b"b".to_vec()).unwrap();
+    source.put(b"shared".to_vec(), b"col1".to_vec(), b"source_1".to_vec()).unwrap();
+    source.put(b"deleted".to_vec(), b"col1".to_vec(), b"gone".to_vec()).unwrap();
+    source.flush().unwrap();
+    thread::sleep(Duration::from_millis(10));
+    source.put(b"shared".to_vec(), b"col1".to_vec(), b"source_2".to_vec()).unwrap();
+    source.delete(b"deleted".to_vec(), b"col1".to_vec()).unwrap();
+    source.put(b"shared".to_vec(), b"col2".to_vec(), b"source_old".to_vec()).unwrap();
+    thread::sleep(Duration::from_millis(10));
+    target.put(b"shared".to_vec(), b"col2".to_vec(), b"target_newest".to_vec()).unwrap();
+
+    let source_versions = source.get_versions(b"shared", b"col1", 10).unwrap();
+    target.ingest_from(&source).unwrap();
+
+    assert_eq!(target.get(b"a_only", b"col1").unwrap().unwrap(), b"a");
+    assert_eq!(target.get(b"b_only", b"col1").unwrap().unwrap(), b"b");
+    assert_eq!(target.get(b"deleted", b"col1").unwrap(), None);
+    assert!(target.get_versions(b"deleted", b"col1", 10).unwrap().is_empty());
+
+    // Timestamps are kept, so the newest version wins whichever side it came from.
+    let merged = target.get_versions(b"shared", b"col1", 10).unwrap();
+    assert_eq!(merged.len(), 3);
+    assert_eq!(merged[..2], source_versions[..]);
+    assert_eq!(merged[2].1, b"target_old");
+    assert_eq!(target.get(b"shared", b"col2").unwrap().unwrap(), b"target_newest");
+
+    // The source is left as it was.
+    assert_eq!(source.get(b"a_only", b"col1").unwrap(), None);
+
+    drop(dir);
+}
+
+#[test]
+fn test_column_family_checkpoint() {
+    let (dir, table_path) = temp_table_dir();
+    let wal_path = table_path.join("test_cf").join("wal.log");
+
+    {
+        let mut table = Table::open(&table_path).unwrap();
+        table.create_cf("test_cf").unwrap();
+        let cf = table.cf("test_cf").unwrap();
+
+        for i in 0..5 {
+            cf.put(format!("row{}", i).into_bytes(), b"col1".to_vec(), b"value".to_vec()).unwrap();
+        }
+        cf.delete(b"row0".to_vec(), b"col1".to_vec()).unwrap();
+        assert!(fs::metadata(&wal_path).unwrap().len() > 0);
+
+        cf.checkpoint().unwrap();
+        assert_eq!(fs::metadata(&wal_path).unwrap().len(), 0);
+        assert_eq!(cf.sstables().unwrap().len(), 1);
+
+        // Checkpointing again with nothing new is harmless.
+        cf.checkpoint().unwrap();
+        assert_eq!(cf.sstables().unwrap().len(), 1);
+        cf.close();
+    }
+
+    // Nothing is replayed: every value comes from the SSTable.
+    let table = Table::open(&table_path).unwrap();
+    let cf = table.cf("test_cf").unwrap();
+    for i in 1..5 {
+        let (value, source) = cf.get_with_source(format!("row{}", i).as_bytes(), b"col1").unwrap().unwrap();
+        assert_eq!(value, b"value");
+        assert!(matches!(source, ReadSource::SSTable(_)));
+    }
+    assert_eq!(cf.get(b"row0", b"col1").unwrap(), None);
+
+    drop(dir);
+}
+
+#[test]
+fn test_column_family_get_cached() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    let window = Duration::from_millis(50);
+    cf.put(b"row1".to_vec(), b"col1".to_vec(), b"value1".to_vec()).unwrap();
+    assert_eq!(cf.get_cached(b"row1", b"col1", window).unwrap(), Some(b"value1".to_vec()));
+    assert_eq!(cf.get_cached(b"row2", b"col1", window).unwrap(), None);
+
+    // Within the window the cached results are served without reading the store.
+    thread::sleep(Duration::from_millis(10));
+    cf.put(b"row1".to_vec(), b"col1".to_vec(), b"value2".to_vec()).unwrap();
+    cf.put(b"row2".to_vec(), b"col1".to_vec(), b"new".to_vec()).unwrap();
+    assert_eq!(cf.get_cached(b"row1", b"col1", Duration::from_secs(60)).unwrap(), Some(b"value1".to_vec()));
+    assert_eq!(cf.get_cached(b"row2", b"col1", Duration::from_secs(60)).unwrap(), None);
+    assert_eq!(cf.get(b"row1", b"col1").unwrap(), Some(b"value2".to_vec()));
+
+    // Past the window they are refreshed.
+    thread::sleep(window);
+    assert_eq!(cf.get_cached(b"row1", b"col1", window).unwrap(), Some(b"value2".to_vec()));
+    assert_eq!(cf.get_cached(b"row2", b"col1", window).unwrap(), Some(b"new".to_vec()));
+    assert_eq!(cf.get_cached(b"row1", b"col1", Duration::ZERO).unwrap(), Some(b"value2".to_vec()));
+
+    drop(dir);
+}
+
+#[test]
+fn test_column_family_replace_row() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    cf.put(b"row1".to_vec(), b"a".to_vec(), b"a1".to_vec()).unwrap();
+    cf.put(b"row1".to_vec(), b"b".to_vec(), b"b1".to_vec()).unwrap();
+    cf.flush().unwrap();
+    cf.put(b"row1".to_vec(), b"c".to_vec(), b"c1".to_vec()).unwrap();
+    cf.put(b"row2".to_vec(), b"a".to_vec(), b"untouched".to_vec()).unwrap();
+
+    let columns = HashMap::from([(b"a".to_vec(), b"a2".to_vec()), (b"d".to_vec(), b"d2".to_vec())]);
+    cf.replace_row(b"row1".to_vec(), columns).unwrap();
+
+    let row = cf.scan_row_with_filter(b"row1", FilterSet::new().with_first_version_only()).unwrap();
+    assert_eq!(row.keys().cloned().collect::<Vec<_>>(), vec![b"a".to_vec(), b"d".to_vec()]);
+    assert_eq!(cf.get(b"row1", b"a").unwrap(), Some(b"a2".to_vec()));
+    assert_eq!(cf.get(b"row1", b"b").unwrap(), None);
+    assert_eq!(cf.get(b"row1", b"c").unwrap(), None);
+    assert_eq!(cf.get(b"row1", b"d").unwrap(), Some(b"d2".to_vec()));
+    assert_eq!(cf.get(b"row2", b"a").unwrap(), Some(b"untouched".to_vec()));
+
+    // Every change shares one timestamp.
+    let a_ts = cf.get_versions(b"row1", b"a", 1).unwrap()[0].0;
+    let d_ts = cf.get_versions(b"row1", b"d", 1).unwrap()[0].0;
+    assert_eq!(a_ts, d_ts);
+
+    drop(dir);
+}
+
+#[test]
+fn test_column_family_increment_columns() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    cf.increment(b"post1".to_vec(), b"views".to_vec(), 10).unwrap();
+    cf.flush().unwrap();
+
+    let totals = cf.increment_columns(b"post1".to_vec(), HashMap::from([
+        (b"likes".to_vec(), 1),
+        (b"views".to_vec(), 5),
+    ])).unwrap();
+    assert_eq!(totals, HashMap::from([(b"likes".to_vec(), 1), (b"views".to_vec(), 15)]));
+    assert_eq!(cf.get_counter(b"post1", b"likes").unwrap(), Some(1));
+    assert_eq!(cf.get_counter(b"post1", b"views").unwrap(), Some(15));
+
+    // Both counters were written at the same timestamp.
+    let likes_ts = cf.get_versions(b"post1", b"likes", 1).unwrap()[0].0;
+    let views_ts = cf.get_versions(b"post1", b"views", 1).unwrap()[0].0;
+    assert_eq!(likes_ts, views_ts);
+
+    // One bad counter leaves all of them untouched.
+    cf.put(b"post1".to_vec(), b"title".to_vec(), b"hello".to_vec()).unwrap();
+    let err = cf.increment_columns(b"post1".to_vec(), HashMap::from([
+        (b"likes".to_vec(), 1),
+        (b"title".to_vec(), 1),
+    ])).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert_eq!(cf.get_counter(b"post1", b"likes").unwrap(), Some(1));
+
+    drop(dir);
+}
+
+#[test]
+fn test_column_family_compact_cell() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    cf.put(b"row1".to_vec(), b"other".to_vec(), b"o1".to_vec()).unwrap();
+    cf.put(b"row2".to_vec(), b"hot".to_vec(), b"r2".to_vec()).unwrap();
+    for i in 1..=10 {
+        cf.put(b"row1".to_vec(), b"hot".to_vec(), format!("v{}", i).into_bytes()).unwrap();
+        if i % 4 == 0 {
+            cf.flush().unwrap();
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    cf.put(b"row1".to_vec(), b"other".to_vec(), b"o2".to_vec()).unwrap();
+    assert_eq!(cf.get_versions(b"row1", b"hot", 100).unwrap().len(), 10);
+    let sst_count = cf.sstable_usage().unwrap().0;
+
+    cf.compact_cell(b"row1", b"hot", 2).unwrap();
+
+    let versions: Vec<Vec<u8>> = cf.get_versions(b"row1", b"hot", 100).unwrap()
+        .into_iter()
+        .map(|(_, value)| value)
+        .collect();
+    assert_eq!(versions, vec![b"v10".to_vec(), b"v9".to_vec()]);
+
+    // Other cells and rows are untouched, and no full compaction ran.
+    assert_eq!(cf.get_versions(b"row1", b"other", 100).unwrap().len(), 2);
+    assert_eq!(cf.get(b"row2", b"hot").unwrap(), Some(b"r2".to_vec()));
+    assert!(cf.sstable_usage().unwrap().0 >= sst_count);
+
+    // Nothing left to trim.
+    cf.compact_cell(b"row1", b"hot", 2).unwrap();
+    assert_eq!(cf.get_versions(b"row1", b"hot", 100).unwrap().len(), 2);
+
+    drop(dir);
+}
+
+#[test]
+fn test_table_on_in_memory_filesystem() {
+    let fs = Arc::new(MemFileSystem::new());
+    let table_path = PathBuf::from("/mem/table");
+
+    {
+        let mut table = Table::open_with_fs(&table_path, fs.clone()).unwrap();
+        table.create_cf("test_cf").unwrap();
+        let cf = table.cf("test_cf").unwrap();
+
+        cf.put(b"row1".to_vec(), b"col1".to_vec(), b"value1".to_vec()).unwrap();
+        cf.flush().unwrap();
+        thread::sleep(Duration::from_millis(10));
+        cf.put(b"row1".to_vec(), b"col1".to_vec(), b"value2".to_vec()).unwrap();
+        cf.flush().unwrap();
+        cf.put(b"row2".to_vec(), b"col1".to_vec(), b"unflushed".to_vec()).unwrap();
+
+        assert_eq!(cf.get(b"row1", b"col1").unwrap(), Some(b"value2".to_vec()));
+        assert_eq!(cf.sstable_usage().unwrap().0, 2);
+        cf.major_compact().unwrap();
+        assert_eq!(cf.sstable_usage().unwrap().0, 1);
+        cf.close();
+    }
+
+    // Nothing was written to the local disk.
+    assert!(!table_path.exists());
+    let cf_path = table_path.join("test_cf");
+    assert!(fs.exists(&cf_path.join("wal.log")));
+    assert_eq!(fs.read_dir(&cf_path).unwrap().iter()
+        .filter(|path| path.extension().is_some_and(|ext| ext == "sst"))
+        .count(), 1);
+
+    // A read-only open reads the same fs, WAL included, and leaves it untouched.
+    let files_before = fs.read_dir(&cf_path).unwrap();
+    {
+        let table = Table::open_read_only_with_fs(&table_path, fs.clone()).unwrap();
+        let cf = table.cf("test_cf").unwrap();
+        assert!(cf.is_read_only());
+        assert_eq!(cf.get(b"row1", b"col1").unwrap(), Some(b"value2".to_vec()));
+        assert_eq!(cf.get(b"row2", b"col1").unwrap(), Some(b"unflushed".to_vec()));
+        let err = cf.put(b"row3".to_vec(), b"col1".to_vec(), b"value3".to_vec()).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+        assert_eq!(
+            ColumnFamily::open_read_only_with_fs(&table_path, "missing_cf", fs.clone()).err().map(|e| e.kind()),
+            Some(std::io::ErrorKind::NotFound)
+        );
+    }
+    assert_eq!(fs.read_dir(&cf_path).unwrap(), files_before);
+
+    // Reopening on the same fs replays the WAL and finds the SSTable.
+    let table = Table::open_with_fs(&table_path, fs).unwrap();
+    let cf = table.cf("test_cf").unwrap();
+    assert_eq!(cf.get(b"row1", b"col1").unwrap(), Some(b"value2".to_vec()));
+    assert_eq!(cf.get(b"row2", b"col1").unwrap(), Some(b"unflushed".to_vec()));
+    assert_eq!(cf.get_versions(b"row1", b"col1", 10).unwrap().len(), 2);
+}
+
+#[test]
+fn test_table_open_in_memory() {
+    let mut table = Table::open_in_memory().unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    for i in 1..=3 {
+        cf.put(b"row1".to_vec(), b"col1".to_vec(), format!("value{}", i).into_bytes()).unwrap();
+        cf.put(format!("row{}", i + 1).into_bytes(), b"col1".to_vec(), b"other".to_vec()).unwrap();
+        cf.flush().unwrap();
+        thread::sleep(Duration::from_millis(10));
+    }
+    cf.put(b"row1".to_vec(), b"col2".to_vec(), b"unflushed".to_vec()).unwrap();
+
+    assert_eq!(cf.get(b"row1", b"col1").unwrap(), Some(b"value3".to_vec()));
+    let row = cf.scan_row_versions(b"row1", 10).unwrap();
+    assert_eq!(row[&b"col1".to_vec()].len(), 3);
+    assert_eq!(row[&b"col2".to_vec()][0].1, b"unflushed");
+
+    cf.compact_with_options(CompactionOptions {
+        compaction_type: CompactionType::Major,
+        max_versions: Some(1),
+        ..CompactionOptions::default()
+    }).unwrap();
+    assert_eq!(cf.sstable_usage().unwrap().0, 1);
+    assert_eq!(cf.get_versions(b"row1", b"col1", 10).unwrap().len(), 1);
+    assert_eq!(cf.get(b"row4", b"col1").unwrap(), Some(b"other".to_vec()));
+
+    // Nothing was written to the local disk.
+    assert!(!PathBuf::from("/memory").exists());
+
+    // A second in-memory table starts out empty.
+    assert!(Table::open_in_memory().unwrap().cf("test_cf").is_none());
+}
+
+#[test]
+fn test_column_family_cleanup_compaction() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    cf.put(b"row7".to_vec(), b"b".to_vec(), b"older".to_vec()).unwrap();
+    cf.flush().unwrap();
+    thread::sleep(Duration::from_millis(10));
+    cf.put(b"row1".to_vec(), b"a".to_vec(), b"v".to_vec()).unwrap();
+    cf.delete(b"row7".to_vec(), b"b".to_vec()).unwrap();
+    cf.delete_with_ttl(b"row2".to_vec(), b"c".to_vec(), Some(1)).unwrap();
+    cf.flush().unwrap();
+    cf.put(b"row3".to_vec(), b"live".to_vec(), b"w".to_vec()).unwrap();
+    cf.delete_with_ttl(b"row4".to_vec(), b"c".to_vec(), Some(1)).unwrap();
+    cf.flush().unwrap();
+    cf.put(b"row5".to_vec(), b"x".to_vec(), b"y".to_vec()).unwrap();
+    cf.flush().unwrap();
+    thread::sleep(Duration::from_millis(20));
+
+    let before = cf.sstables().unwrap();
+    let counts = |tables: &[SSTableMeta]| -> Vec<u32> { tables.iter().map(|meta| meta.entry_count).collect() };
+    assert_eq!(counts(&before), vec![1, 3, 2, 1]);
+
+    let options = CompactionOptions { compaction_type: CompactionType::Cleanup, ..Default::default() };
+    let plan = cf.compaction_plan(&options).unwrap();
+    assert_eq!(plan.dropped_tombstones, 2);
+    cf.compact_with_options(options).unwrap();
+
+    // Only the expired TTL tombstones are gone; every file keeps its name and place.
+    let after = cf.sstables().unwrap();
+    assert_eq!(counts(&after), vec![1, 2, 1, 1]);
+    let paths = |tables: &[SSTableMeta]| -> Vec<_> { tables.iter().map(|meta| meta.path.clone()).collect() };
+    assert_eq!(paths(&after), paths(&before));
+
+    // The tombstone without a TTL still hides the put in the older file.
+    assert_eq!(cf.get(b"row7", b"b").unwrap(), None);
+    assert_eq!(cf.get(b"row1", b"a").unwrap().unwrap(), b"v");
+    assert_eq!(cf.get(b"row3", b"live").unwrap().unwrap(), b"w");
+    assert_eq!(cf.get(b"row5", b"x").unwrap().unwrap(), b"y");
+
+    // Versions past max_age_ms go too, and a file left empty is removed.
+    let options = CompactionOptions {
+        compaction_type: CompactionType::Cleanup,
+        max_age_ms: Some(0),
+        ..Default::default()
+    };
+    thread::sleep(Duration::from_millis(5));
+    cf.compact_with_options(options).unwrap();
+    assert_eq!(counts(&cf.sstables().unwrap()), vec![1]);
+    assert_eq!(cf.get(b"row7", b"b").unwrap(), None);
+    assert_eq!(cf.get(b"row5", b"x").unwrap(), None);
+
+    drop(dir);
+}
+
+#[test]
+fn test_column_family_sstables() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+    assert!(cf.sstables().unwrap().is_empty());
+
+    cf.put(b"row1".to_vec(), b"col1".to_vec(), b"value1".to_vec()).unwrap();
+    cf.put(b"row3".to_vec(), b"col1".to_vec(), b"value3".to_vec()).unwrap();
+    cf.flush().unwrap();
+    cf.put(b"row2".to_vec(), b"col1".to_vec(), b"value2".to_vec()).unwrap();
+    cf.flush().unwrap();
+
+    let sstables = cf.sstables().unwrap();
+    assert_eq!(sstables.len(), 2);
+    assert!(sstables[0].seq < sstables[1].seq);
+
+    assert_eq!(sstables[0].entry_count, 2);
+    assert_eq!(sstables[0].key_range, Some((b"row1".to_vec(), b"row3".to_vec())));
+    assert_eq!(sstables[1].entry_count, 1);
+    assert_eq!(sstables[1].key_range, Some((b"row2".to_vec(), b"row2".to_vec())));
+    for meta in &sstables {
+        assert_eq!(meta.size_bytes, fs::metadata(&meta.path).unwrap().len());
+    }
+    assert_eq!(
+        cf.sstable_usage().unwrap().1,
+        sstables.iter().map(|meta| meta.size_bytes).sum::<u64>()
+    );
+
+    drop(dir);
+}
+
+#[test]
+fn test_read_consistency_levels() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    cf.put(b"row1".to_vec(), b"col1".to_vec(), b"v1".to_vec()).unwrap();
+    thread::sleep(Duration::from_millis(10));
+    let after_v1 = chrono::Utc::now().timestamp_millis() as u64;
+    thread::sleep(Duration::from_millis(10));
+    cf.put(b"row1".to_vec(), b"col1".to_vec(), b"v2".to_vec()).unwrap();
+    cf.put(b"row1".to_vec(), b"flushed_only".to_vec(), b"on_disk".to_vec()).unwrap();
+    cf.flush().unwrap();
+    thread::sleep(Duration::from_millis(10));
+    cf.put(b"row1".to_vec(), b"col1".to_vec(), b"v3".to_vec()).unwrap();
+
+    let get = |consistency| cf.get_with_consistency(b"row1", b"col1", consistency).unwrap();
+    let values = |consistency| cf.get_versions_with_consistency(b"row1", b"col1", 10, consistency)
+        .unwrap()
+        .into_iter()
+        .map(|(_, v)| v)
+        .collect::<Vec<_>>();
+
+    assert_eq!(get(ReadConsistency::Default).unwrap(), b"v3");
+    assert_eq!(values(ReadConsistency::Default), vec![b"v3".to_vec(), b"v2".to_vec(), b"v1".to_vec()]);
+
+    assert_eq!(get(ReadConsistency::MemstoreOnly).unwrap(), b"v3");
+    assert_eq!(values(ReadConsistency::MemstoreOnly), vec![b"v3".to_vec()]);
+    assert_eq!(cf.get_with_consistency(b"row1", b"flushed_only", ReadConsistency::MemstoreOnly).unwrap(), None);
+
+    assert_eq!(get(ReadConsistency::SkipMemstore).unwrap(), b"v2");
+    assert_eq!(values(ReadConsistency::SkipMemstore), vec![b"v2".to_vec(), b"v1".to_vec()]);
+    assert_eq!(cf.get_with_consistency(b"row1", b"flushed_only", ReadConsistency::SkipMemstore).unwrap().unwrap(), b"on_disk");
+
+    assert_eq!(get(ReadConsistency::SnapshotAt(after_v1)).unwrap(), b"v1");
+    assert_eq!(values(ReadConsistency::SnapshotAt(after_v1)), vec![b"v1".to_vec()]);
+    assert_eq!(cf.get_with_consistency(b"row1", b"flushed_only", ReadConsistency::SnapshotAt(after_v1)).unwrap(), None);
+
+    // A delete hides the older versions at every level that sees it.
+    thread::sleep(Duration::from_millis(10));
+    cf.delete(b"row1".to_vec(), b"col1".to_vec()).unwrap();
+    assert_eq!(get(ReadConsistency::MemstoreOnly), None);
+    assert_eq!(get(ReadConsistency::SkipMemstore).unwrap(), b"v2");
+
+    drop(dir);
+}
+
+#[test]
+fn test_column_family_exists() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    cf.put(b"row1".to_vec(), b"on_disk".to_vec(), b"v".to_vec()).unwrap();
+    cf.put(b"row1".to_vec(), b"deleted".to_vec(), b"v".to_vec()).unwrap();
+    cf.flush().unwrap();
+    thread::sleep(Duration::from_millis(10));
+    cf.put(b"row1".to_vec(), b"in_memory".to_vec(), b"v".to_vec()).unwrap();
+    cf.delete(b"row1".to_vec(), b"deleted".to_vec()).unwrap();
+    cf.increment(b"row1".to_vec(), b"counter".to_vec(), 1).unwrap();
+
+    assert!(cf.exists(b"row1", b"on_disk").unwrap());
+    assert!(cf.exists(b"row1", b"in_memory").unwrap());
+    assert!(cf.exists(b"row1", b"counter").unwrap());
+    assert!(!cf.exists(b"row1", b"deleted").unwrap());
+    assert!(!cf.exists(b"row1", b"absent").unwrap());
+    assert!(!cf.exists(b"row2", b"on_disk").unwrap());
+
+    // A tombstone flushed to a newer SSTable hides the older put too.
+    cf.flush().unwrap();
+    assert!(!cf.exists(b"row1", b"deleted").unwrap());
+    assert!(cf.exists(b"row1", b"on_disk").unwrap());
+
+    drop(dir);
+}
+
+#[test]
+fn test_column_family_access_heatmap() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("off_cf").unwrap();
+    let off = table.cf("off_cf").unwrap();
+    off.put(b"user:1".to_vec(), b"col".to_vec(), b"v".to_vec()).unwrap();
+    off.get(b"user:1", b"col").unwrap();
+    assert!(off.access_heatmap(4).is_empty());
+
+    let options = ColumnFamilyOptions::builder()
+        .access_stats_prefix_len(5)
+        .build();
+    table.create_cf_with_options("test_cf", options).unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    for i in 0..50 {
+        let row = format!("user:{}", i % 5).into_bytes();
+        cf.put(row.clone(), b"col".to_vec(), b"v".to_vec()).unwrap();
+        cf.get(&row, b"col").unwrap();
+        cf.exists(&row, b"col").unwrap();
+    }
+    cf.put(b"item:1".to_vec(), b"col".to_vec(), b"v".to_vec()).unwrap();
+    cf.get(b"item:1", b"col").unwrap();
+    cf.scan_row_versions(b"order:1", 1).unwrap();
+
+    let heatmap = cf.access_heatmap(5);
+    assert_eq!(heatmap.len(), 3);
+    assert_eq!(heatmap[b"user:".as_slice()], (100, 50));
+    assert_eq!(heatmap[b"item:".as_slice()], (1, 1));
+    assert_eq!(heatmap[b"order".as_slice()], (1, 0));
+    let hottest = heatmap.iter().max_by_key(|(_, (reads, writes))| reads + writes).unwrap();
+    assert_eq!(hottest.0, b"user:");
+
+    // Coarser buckets merge the counts; finer ones stop at the tracked prefix.
+    let coarse = cf.access_heatmap(1);
+    assert_eq!(coarse[b"u".as_slice()], (100, 50));
+    assert_eq!(coarse[b"i".as_slice()], (1, 1));
+    assert_eq!(cf.access_heatmap(10), heatmap);
+
+    drop(dir);
+}
+
+#[test]
+fn test_column_family_get_bounded() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    // An old cell at the bottom of a stack of SSTables that don't mention it.
+    cf.put(b"row1".to_vec(), b"old".to_vec(), b"v1".to_vec()).unwrap();
+    cf.flush().unwrap();
+    for i in 0..3 {
+        cf.put(b"row1".to_vec(), b"recent".to_vec(), format!("r{}", i).into_bytes()).unwrap();
+        thread::sleep(Duration::from_millis(10));
+        cf.flush().unwrap();
+    }
+
+    assert_eq!(cf.get_bounded(b"row1", b"old", 2).unwrap(), (None, true));
+    assert_eq!(cf.get_bounded(b"row1", b"old", 4).unwrap(), (Some(b"v1".to_vec()), false));
+    assert_eq!(cf.get_bounded(b"row1", b"recent", 1).unwrap(), (Some(b"r2".to_vec()), false));
+    assert_eq!(cf.get_bounded(b"row1", b"absent", 10).unwrap(), (None, false));
+
+    // The MemStore is always consulted, even with no SSTables allowed.
+    cf.put(b"row1".to_vec(), b"old".to_vec(), b"v2".to_vec()).unwrap();
+    assert_eq!(cf.get_bounded(b"row1", b"old", 0).unwrap(), (Some(b"v2".to_vec()), false));
+
+    drop(dir);
+}
+
+#[test]
+fn test_column_family_get_with_versions_meta() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    assert_eq!(cf.get_with_versions_meta(b"row1", b"col1").unwrap(), None);
+
+    for (i, value) in ["v1", "v2", "v3"].iter().enumerate() {
+        cf.put(b"row1".to_vec(), b"col1".to_vec(), value.as_bytes().to_vec()).unwrap();
+        thread::sleep(Duration::from_millis(10));
+        if i == 0 {
+            cf.flush().unwrap();
+        }
+    }
+
+    let (value, timestamps) = cf.get_with_versions_meta(b"row1", b"col1").unwrap().unwrap();
+    assert_eq!(value, b"v3".to_vec());
+    let expected: Vec<u64> = cf.get_versions(b"row1", b"col1", 10).unwrap().into_iter().map(|(ts, _)| ts).collect();
+    assert_eq!(timestamps.len(), 3);
+    assert_eq!(timestamps, expected);
+
+    // Once the latest version is a delete there is no current value to report.
+    cf.delete(b"row1".to_vec(), b"col1".to_vec()).unwrap();
+    assert_eq!(cf.get_with_versions_meta(b"row1", b"col1").unwrap(), None);
+
+    drop(dir);
+}
+
+#[test]
+fn test_column_family_get_oldest() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    assert_eq!(cf.get_oldest(b"row1", b"col1").unwrap(), None);
+
+    for (i, value) in ["first", "second", "third"].iter().enumerate() {
+        cf.put(b"row1".to_vec(), b"col1".to_vec(), value.as_bytes().to_vec()).unwrap();
+        if i == 1 {
+            cf.flush().unwrap();
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    let versions = cf.get_versions(b"row1", b"col1", 10).unwrap();
+    let (ts, value) = cf.get_oldest(b"row1", b"col1").unwrap().unwrap();
+    assert_eq!(value, b"first");
+    assert_eq!(Some(&(ts, value)), versions.last());
+
+    // Only the memstore holds the cell: the oldest in-memory version is found.
+    cf.put(b"row2".to_vec(), b"col1".to_vec(), b"a".to_vec()).unwrap();
+    thread::sleep(Duration::from_millis(10));
+    cf.put(b"row2".to_vec(), b"col1".to_vec(), b"b".to_vec()).unwrap();
+    assert_eq!(cf.get_oldest(b"row2", b"col1").unwrap().unwrap().1, b"a");
+
+    drop(dir);
+}
+
+#[test]
+fn test_flush_and_compact() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    for i in 1..=4 {
+        cf.put(b"row1".to_vec(), b"col1".to_vec(), format!("v{}", i).into_bytes()).unwrap();
+        if i < 4 {
+            cf.flush().unwrap();
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    let options = CompactionOptions {
+        compaction_type: CompactionType::Major,
+        max_versions: Some(2),
+        ..CompactionOptions::default()
+    };
+    cf.flush_and_compact(options).unwrap();
+
+    let versions = cf.get_versions(b"row1", b"col1", 10).unwrap();
+    assert_eq!(versions.iter().map(|(_, v)| v.as_slice()).collect::<Vec<_>>(), vec![&b"v4"[..], b"v3"]);
+    assert_eq!(cf.sstables().unwrap().len(), 1);
+
+    drop(dir);
+}
+
+#[test]
+fn test_compaction_max_versions_per_column() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    for i in 1..=5 {
+        for row in ["row1", "row2"] {
+            for col in ["col_a", "col_b", "col_c"] {
+                cf.put(row.as_bytes().to_vec(), col.as_bytes().to_vec(), format!("v{}", i).into_bytes()).unwrap();
+            }
+        }
+        cf.flush().unwrap();
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    let options = CompactionOptions {
+        compaction_type: CompactionType::Major,
+        max_versions: Some(2),
+        max_versions_per_column: Some(BTreeMap::from([
+            (b"col_a".to_vec(), 3),
+            (b"col_b".to_vec(), 1),
+        ])),
+        ..CompactionOptions::default()
+    };
+    cf.compact_with_options(options).unwrap();
+
+    for row in [&b"row1"[..], b"row2"] {
+        let col_a = cf.get_versions(row, b"col_a", 10).unwrap();
+        assert_eq!(col_a.iter().map(|(_, v)| v.as_slice()).collect::<Vec<_>>(), vec![&b"v5"[..], b"v4", b"v3"]);
+        let col_b = cf.get_versions(row, b"col_b", 10).unwrap();
+        assert_eq!(col_b.len(), 1);
+        assert_eq!(col_b[0].1, b"v5");
+        // Columns that are not listed fall back to max_versions.
+        assert_eq!(cf.get_versions(row, b"col_c", 10).unwrap().len(), 2);
+    }
+
+    drop(dir);
+}
+
+type WriteHook = Arc<dyn Fn(usize) + Send + Sync>;
+type SyncHook = Box<dyn Fn(&std::path::Path) -> std::io::Result<()> + Send + Sync>;
+
+/// A MemFileSystem that reports the size of every write made to an SSTable, and every file
+/// or directory synced, to optional hooks. A sync hook's error is returned from the sync.
+#[derive(Default)]
+struct HookedFileSystem {
+    inner: MemFileSystem,
+    on_sst_write: Option<WriteHook>,
+    on_sync: Option<SyncHook>,
+}
+
+impl HookedFileSystem {
+    fn on_sst_write(mut self, hook: impl Fn(usize) + Send + Sync + 'static) -> Self {
+        self.on_sst_write = Some(Arc::new(hook));
+        self
+    }
+
+    fn on_sync(mut self, hook: impl Fn(&std::path::Path) -> std::io::Result<()> + Send + Sync + 'static) -> Self {
+        self.on_sync = Some(Box::new(hook));
+        self
+    }
+
+    fn sync(&self, path: &std::path::Path) -> std::io::Result<()> {
+        self.on_sync.as_ref().map_or(Ok(()), |hook| hook(path))
+    }
+}
+
+struct HookedWriter {
+    inner: Box<dyn WriteFile>,
+    on_write: WriteHook,
+}
+
+impl std::io::Write for HookedWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        (self.on_write)(written);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl FileSystem for HookedFileSystem {
+    fn open(&self, path: &std::path::Path) -> std::io::Result<Box<dyn ReadFile>> {
+        self.inner.open(path)
+    }
+
+    fn create(&self, path: &std::path::Path) -> std::io::Result<Box<dyn WriteFile>> {
+        let file = self.inner.create(path)?;
+        match &self.on_sst_write {
+            Some(hook) if path.extension().is_some_and(|ext| ext == "sst") => {
+                Ok(Box::new(HookedWriter { inner: file, on_write: hook.clone() }))
+            }
+            _ => Ok(file),
+        }
+    }
+
+    fn append(&self, path: &std::path::Path) -> std::io::Result<Box<dyn WriteFile>> {
+        self.inner.append(path)
+    }
+
+    fn read_dir(&self, path: &std::path::Path) -> std::io::Result<Vec<PathBuf>> {
+        self.inner.read_dir(path)
+    }
+
+    fn create_dir_all(&self, path: &std::path::Path) -> std::io::Result<()> {
+        self.inner.create_dir_all(path)
+    }
+
+    fn remove_file(&self, path: &std::path::Path) -> std::io::Result<()> {
+        self.inner.remove_file(path)
+    }
+
+    fn rename(&self, from: &std::path::Path, to: &std::path::Path) -> std::io::Result<()> {
+        self.inner.rename(from, to)
+    }
+
+    fn exists(&self, path: &std::path::Path) -> bool {
+        self.inner.exists(path)
+    }
+
+    fn is_dir(&self, path: &std::path::Path) -> bool {
+        self.inner.is_dir(path)
+    }
+
+    fn file_size(&self, path: &std::path::Path) -> std::io::Result<u64> {
+        self.inner.file_size(path)
+    }
+
+    fn sync_file(&self, path: &std::path::Path) -> std::io::Result<()> {
+        self.sync(path)
+    }
+
+    fn sync_dir(&self, path: &std::path::Path) -> std::io::Result<()> {
+        self.sync(path)
+    }
+}
+
+/// A HookedFileSystem that records every path synced through it, and the shared list.
+fn sync_recorder() -> (Arc<HookedFileSystem>, Arc<Mutex<Vec<PathBuf>>>) {
+    let synced = Arc::new(Mutex::new(Vec::new()));
+    let recorded = synced.clone();
+    let fs = HookedFileSystem::default().on_sync(move |path| {
+        recorded.lock().unwrap().push(path.to_path_buf());
+        Ok(())
+    });
+    (Arc::new(fs), synced)
+}
+
+#[test]
+fn test_sync_sstables() {
+    assert!(ColumnFamilyOptions::default().sync_sstables);
+    let is_sst = |path: &PathBuf| path.extension().is_some_and(|ext| ext == "sst");
+
+    let (fs, synced) = sync_recorder();
+    {
+        let mut table = Table::open_with_fs("/mem/table", fs.clone()).unwrap();
+        table.create_cf("test_cf").unwrap();
+        let cf = table.cf("test_cf").unwrap();
+        for i in 0..2 {
+            cf.put(b"row1".to_vec(), b"col1".to_vec(), format!("value{}", i).into_bytes()).unwrap();
+            thread::sleep(Duration::from_millis(10));
+            cf.flush().unwrap();
+        }
+        cf.major_compact().unwrap();
+
+        let synced = synced.lock().unwrap().clone();
+        // Two flushes and one compaction output.
+        assert_eq!(synced.iter().filter(|path| is_sst(path)).count(), 3);
+        assert!(synced.iter().filter(|path| !is_sst(path)).all(|dir| dir.ends_with("test_cf")));
+        // Dropped without close, as if the process had crashed.
+    }
+
+    let table = Table::open_with_fs("/mem/table", fs.clone()).unwrap();
+    let cf = table.cf("test_cf").unwrap();
+    assert_eq!(cf.get(b"row1", b"col1").unwrap(), Some(b"value1".to_vec()));
+    cf.close();
+
+    let (fs, synced) = sync_recorder();
+    let mut table = Table::open_with_fs("/mem/table", fs.clone()).unwrap();
+    let options = ColumnFamilyOptions::builder().sync_sstables(false).build();
+    table.create_cf_with_options("test_cf", options).unwrap();
+    let cf = table.cf("test_cf").unwrap();
+    cf.put(b"row1".to_vec(), b"col1".to_vec(), b"value".to_vec()).unwrap();
+    cf.flush().unwrap();
+    assert!(synced.lock().unwrap().is_empty());
+    cf.close();
+
+    // A flush whose SSTable can't be synced fails without losing anything.
+    let failing = HookedFileSystem::default().on_sync(|path| {
+        if path.extension().is_some_and(|ext| ext == "sst") {
+            return Err(std::io::Error::other("sync failed"));
+        }
+        Ok(())
+    });
+    let fs = Arc::new(failing);
+    {
+        let mut table = Table::open_with_fs("/mem/table", fs.clone()).unwrap();
+        table.create_cf("test_cf").unwrap();
+        let cf = table.cf("test_cf").unwrap();
+        cf.put(b"row1".to_vec(), b"col1".to_vec(), b"value".to_vec()).unwrap();
+        assert!(cf.flush().is_err());
+        assert_eq!(cf.get(b"row1", b"col1").unwrap(), Some(b"value".to_vec()));
+        assert!(cf.sstables().unwrap().is_empty());
+    }
+
+    // The WAL still holds the write.
+    let table = Table::open_with_fs("/mem/table", fs).unwrap();
+    let cf = table.cf("test_cf").unwrap();
+    assert_eq!(cf.get(b"row1", b"col1").unwrap(), Some(b"value".to_vec()));
+}
+
+#[test]
+fn test_sstable_write_buffer_size() {
+    assert!(ColumnFamilyOptions::builder().sstable_write_buffer_bytes(0).build().validate().is_err());
+
+    for buffer_bytes in [256, 1 << 20] {
+        let writes = Arc::new(Mutex::new(Vec::new()));
+        let recorded = writes.clone();
+        let fs = HookedFileSystem::default().on_sst_write(move |len| recorded.lock().unwrap().push(len));
+        let mut table = Table::open_with_fs("/mem/table", Arc::new(fs)).unwrap();
+        let options = ColumnFamilyOptions::builder()
+            .sstable_write_buffer_bytes(buffer_bytes)
+            .build();
+        table.create_cf_with_options("test_cf", options).unwrap();
+        let cf = table.cf("test_cf").unwrap();
+
+        for i in 0..1000 {
+            cf.put(format!("row{:04}", i).into_bytes(), b"col1".to_vec(), format!("value{}", i).into_bytes()).unwrap();
+        }
+        cf.flush().unwrap();
+
+        let (_, sst_bytes) = cf.sstable_usage().unwrap();
+        let writes = writes.lock().unwrap().clone();
+        assert_eq!(writes.iter().sum::<usize>() as u64, sst_bytes);
+        if buffer_bytes == 256 {
+            // Small entries are gathered into chunks no bigger than the buffer.
+            assert!(writes.len() as u64 >= sst_bytes / 256);
+            assert!(writes.iter().all(|&len| len <= 256));
+        } else {
+            // The whole file fits in the buffer and goes out in one write.
+            assert_eq!(writes.len(), 1);
+        }
+
+        for i in (0..1000).step_by(97) {
+            assert_eq!(
+                cf.get(format!("row{:04}", i).as_bytes(), b"col1").unwrap(),
+                Some(format!("value{}", i).into_bytes())
+            );
+        }
+        cf.close();
+    }
+}
+
+#[test]
+fn test_column_family_rewrite_sstables() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    cf.put(b"row1".to_vec(), b"col1".to_vec(), b"old1".to_vec()).unwrap();
+    cf.put(b"row2".to_vec(), b"col1".to_vec(), b"old2".to_vec()).unwrap();
+    cf.delete(b"row2".to_vec(), b"col2".to_vec()).unwrap();
+    cf.flush().unwrap();
+    thread::sleep(Duration::from_millis(10));
+    cf.put(b"row1".to_vec(), b"col1".to_vec(), b"new1".to_vec()).unwrap();
+    cf.flush().unwrap();
+
+    let mut sst_paths: Vec<PathBuf> = fs::read_dir(table_path.join("test_cf")).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "sst"))
+        .collect();
+    sst_paths.sort();
+    let (old_path, current_path) = (&sst_paths[0], &sst_paths[1]);
+
+    // Turn the older file into the first on-disk format: no version header, no footer.
+    let bytes = fs::read(old_path).unwrap();
+    let footer_len = u32::from_be_bytes(bytes[bytes.len() - 8..bytes.len() - 4].try_into().unwrap()) as usize;
+    fs::write(old_path, &bytes[5..bytes.len() - 8 - footer_len]).unwrap();
+    assert!(!SSTableReader::is_current_format(old_path).unwrap());
+    assert!(SSTableReader::is_current_format(current_path).unwrap());
+
+    let before = SSTableReader::open(old_path).unwrap().scan_all().unwrap();
+    let current_bytes = fs::read(current_path).unwrap();
+
+    cf.rewrite_sstables().unwrap();
+
+    assert!(SSTableReader::is_current_format(old_path).unwrap());
+    assert!(SSTableReader::read_footer(old_path).unwrap().is_some());
+    assert_eq!(SSTableReader::open(old_path).unwrap().scan_all().unwrap(), before);
+    assert_eq!(fs::read(current_path).unwrap(), current_bytes);
+    assert_eq!(sst_file_count(&table_path.join("test_cf")), 2);
+
+    assert_eq!(cf.get(b"row1", b"col1").unwrap(), Some(b"new1".to_vec()));
+    assert_eq!(cf.get(b"row2", b"col1").unwrap(), Some(b"old2".to_vec()));
+    assert_eq!(cf.get_versions(b"row1", b"col1", 10).unwrap().len(), 2);
+    assert_eq!(cf.get_versions_raw(b"row2", b"col2", 10).unwrap().len(), 1);
+
+    drop(dir);
+}
+
+#[test]
+fn test_column_family_put_and_delete_get_previous() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    assert_eq!(cf.put_and_get_previous(b"row1".to_vec(), b"col1".to_vec(), b"first".to_vec()).unwrap(), None);
+    assert_eq!(
+        cf.put_and_get_previous(b"row1".to_vec(), b"col1".to_vec(), b"second".to_vec()).unwrap(),
+        Some(b"first".to_vec())
+    );
+    cf.flush().unwrap();
+    // The previous value is found in an SSTable too.
+    assert_eq!(
+        cf.put_and_get_previous(b"row1".to_vec(), b"col1".to_vec(), b"third".to_vec()).unwrap(),
+        Some(b"second".to_vec())
+    );
+    assert_eq!(cf.get(b"row1", b"col1").unwrap(), Some(b"third".to_vec()));
+
+    assert_eq!(cf.delete_and_get_previous(b"row1".to_vec(), b"col1".to_vec()).unwrap(), Some(b"third".to_vec()));
+    assert_eq!(cf.get(b"row1", b"col1").unwrap(), None);
+    assert_eq!(cf.delete_and_get_previous(b"row1".to_vec(), b"col1".to_vec()).unwrap(), None);
+    assert_eq!(cf.put_and_get_previous(b"row1".to_vec(), b"col1".to_vec(), b"fourth".to_vec()).unwrap(), None);
+    assert_eq!(cf.get(b"row1", b"col1").unwrap(), Some(b"fourth".to_vec()));
+
+    drop(dir);
+}
+
+#[test]
+fn test_compaction_plan_matches_compaction() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    for i in 1..=4 {
+        cf.put(b"row1".to_vec(), b"col1".to_vec(), format!("v{}", i).into_bytes()).unwrap();
+        cf.put(b"row2".to_vec(), b"col1".to_vec(), format!("v{}", i).into_bytes()).unwrap();
+        if i == 2 {
+            thread::sleep(Duration::from_millis(10));
+            cf.delete(b"row1".to_vec(), b"col1".to_vec()).unwrap();
+        }
+        cf.flush().unwrap();
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    let options = CompactionOptions {
+        compaction_type: CompactionType::Major,
+        max_versions: Some(2),
+        ..CompactionOptions::default()
+    };
+    let (files_before, bytes_before) = cf.sstable_usage().unwrap();
+    let plan = cf.compaction_plan(&options).unwrap();
+
+    // Nothing changed on disk.
+    assert_eq!(cf.sstable_usage().unwrap(), (files_before, bytes_before));
+    assert_eq!(plan.inputs.len(), 4);
+    assert_eq!(plan.input_bytes, bytes_before);
+    assert_eq!(plan.input_entries, 9);
+    assert_eq!(plan.dropped_versions, 4);
+    assert_eq!(plan.dropped_tombstones, 1);
+    assert_eq!(plan.output_entries, 4);
+
+    let raw_before: usize = [&b"row1"[..], b"row2"].iter()
+        .map(|row| cf.get_versions_raw(row, b"col1", 100).unwrap().len())
+        .sum();
+    cf.compact_with_options(options).unwrap();
+    let raw_after: usize = [&b"row1"[..], b"row2"].iter()
+        .map(|row| cf.get_versions_raw(row, b"col1", 100).unwrap().len())
+        .sum();
+
+    assert_eq!(raw_before - raw_after, plan.dropped_versions + plan.dropped_tombstones);
+    assert_eq!(cf.sstable_usage().unwrap(), (1, plan.estimated_output_bytes));
+
+    // A minor compaction of a single SSTable does nothing.
+    let minor = cf.compaction_plan(&CompactionOptions::default()).unwrap();
+    assert_eq!(minor, CompactionPlan {
+        inputs: Vec::new(),
+        input_bytes: 0,
+        input_entries: 0,
+        output_entries: 0,
+        estimated_output_bytes: 0,
+        dropped_versions: 0,
+        dropped_tombstones: 0,
+    });
+
+    drop(dir);
+}
@@ -8,7 +8,7 @@ use tempfile::tempdir;
 use tokio::time;
 use futures::StreamExt;
 use RedBase::api::{Put, Get, CompactionOptions, CompactionType};
-use RedBase::async_api::{Table, ColumnFamily};
+use RedBase::async_api::{Table, ColumnFamily, SizeCompactionTrigger};
 use RedBase::filter::{Filter, FilterSet};
 use RedBase::aggregation::{AggregationType, AggregationSet, AggregationResult};
 
@@ -218,6 +218,8 @@ async fn test_compact_with_max_versions() {
         max_versions: Some(2),
         max_age_ms: None,
         cleanup_tombstones: true,
+        target_output_bytes: None,
+        max_versions_per_column: None,
     };
     cf.compact_with_options(options).await.unwrap();
 
@@ -258,6 +260,8 @@ async fn test_compact_with_max_age() {
         max_versions: Some(1),
         max_age_ms: None,
         cleanup_tombstones: true,
+        target_output_bytes: None,
+        max_versions_per_column: None,
     };
     cf.compact_with_options(options).await.unwrap();
 
@@ -273,6 +277,60 @@ async fn test_compact_with_max_age() {
     }
 }
 
+#[tokio::test]
+async fn test_compact_with_max_versions_convenience() {
+    let (dir, table_path) = temp_table_dir();
+
+    let table = Table::open(&table_path).await.unwrap();
+    table.create_cf("test_cf").await.unwrap();
+    let cf = table.cf("test_cf").await.unwrap();
+
+    // Two SSTables, so the default minor compaction has something to merge.
+    for i in 1..=5 {
+        cf.put(b"row1".to_vec(), b"col1".to_vec(), format!("value{}", i).into_bytes()).await.unwrap();
+        time::sleep(time::Duration::from_millis(10)).await;
+        if i == 3 || i == 5 {
+            cf.flush().await.unwrap();
+        }
+    }
+    assert_eq!(cf.get_versions(b"row1", b"col1", 10).await.unwrap().len(), 5);
+
+    cf.compact_with_max_versions(2).await.unwrap();
+
+    let versions = cf.get_versions(b"row1", b"col1", 10).await.unwrap();
+    assert_eq!(versions.len(), 2);
+    assert_eq!(String::from_utf8_lossy(&versions[0].1), "value5");
+    assert_eq!(String::from_utf8_lossy(&versions[1].1), "value4");
+
+    drop(dir);
+}
+
+#[tokio::test]
+async fn test_compact_with_max_age_convenience() {
+    let (dir, table_path) = temp_table_dir();
+
+    let table = Table::open(&table_path).await.unwrap();
+    table.create_cf("test_cf").await.unwrap();
+    let cf = table.cf("test_cf").await.unwrap();
+
+    for i in 1..=3 {
+        cf.put(b"row1".to_vec(), b"col1".to_vec(), format!("value{}", i).into_bytes()).await.unwrap();
+        time::sleep(time::Duration::from_millis(10)).await;
+    }
+    cf.flush().await.unwrap();
+    time::sleep(time::Duration::from_millis(300)).await;
+    cf.put(b"row1".to_vec(), b"col1".to_vec(), b"recent_value".to_vec()).await.unwrap();
+    cf.flush().await.unwrap();
+
+    cf.compact_with_max_age(200).await.unwrap();
+
+    let versions = cf.get_versions(b"row1", b"col1", 10).await.unwrap();
+    assert_eq!(versions.len(), 1);
+    assert_eq!(String::from_utf8_lossy(&versions[0].1), "recent_value");
+
+    drop(dir);
+}
+
 #[tokio::test]
 async fn test_get_with_filter() {
     let (dir, table_path) = temp_table_dir();
@@ -536,6 +594,8 @@ async fn test_compact_with_options() {
         max_versions: Some(2),
         max_age_ms: None,
         cleanup_tombstones: true,
+        target_output_bytes: None,
+        max_versions_per_column: None,
     };
     cf.compact_with_options(options).await.unwrap();
 
@@ -734,3 +794,198 @@ async fn test_get_versions_with_time_range() {
     });
     assert!(found_value2, "Should contain value2");
 }
+
+#[tokio::test]
+async fn test_size_triggered_compaction() {
+    let (dir, table_path) = temp_table_dir();
+
+    let table = Table::open(&table_path).await.unwrap();
+
+    table.create_cf("test_cf").await.unwrap();
+
+    let cf = table.cf("test_cf").await.unwrap();
+
+    let trigger = SizeCompactionTrigger {
+        max_sstable_count: Some(2),
+        max_total_bytes: None,
+        check_interval: Duration::from_millis(50),
+    };
+    assert!(!trigger.should_compact(2, 0));
+    assert!(trigger.should_compact(3, 0));
+
+    let handle = cf.spawn_size_compaction(trigger);
+
+    for i in 1..=3 {
+        cf.put(
+            format!("row{}", i).into_bytes(),
+            b"col1".to_vec(),
+            format!("value{}", i).into_bytes()
+        ).await.unwrap();
+        cf.flush().await.unwrap();
+    }
+
+    let mut count = cf.sstable_usage().await.unwrap().0;
+    for _ in 0..100 {
+        if count == 1 {
+            break;
+        }
+        time::sleep(time::Duration::from_millis(20)).await;
+        count = cf.sstable_usage().await.unwrap().0;
+    }
+    assert_eq!(count, 1);
+
+    handle.abort();
+    assert!(handle.await.unwrap_err().is_cancelled());
+
+    for i in 1..=3 {
+        let value = cf.get(format!("row{}", i).as_bytes(), b"col1").await.unwrap();
+        assert_eq!(value.unwrap(), format!("value{}", i).into_bytes());
+    }
+
+    drop(dir);
+}
+
+#[tokio::test]
+async fn test_lock_row_mutual_exclusion() {
+    let (dir, table_path) = temp_table_dir();
+
+    let table = Table::open(&table_path).await.unwrap();
+
+    table.create_cf("test_cf").await.unwrap();
+
+    let cf = table.cf("test_cf").await.unwrap();
+
+    cf.put(b"row1".to_vec(), b"counter".to_vec(), b"0".to_vec()).await.unwrap();
+
+    let mut tasks = Vec::new();
+    for _ in 0..2 {
+        let cf = cf.clone();
+        tasks.push(tokio::spawn(async move {
+            for _ in 0..5 {
+                let guard = cf.lock_row(b"row1").await;
+                assert_eq!(guard.row(), b"row1");
+
+                let current = cf.get(b"row1", b"counter").await.unwrap().unwrap();
+                let current: u64 = String::from_utf8(current).unwrap().parse().unwrap();
+                // Give the other task a chance to interleave if the lock didn't hold.
+                time::sleep(time::Duration::from_millis(5)).await;
+                cf.put(b"row1".to_vec(), b"counter".to_vec(), (current + 1).to_string().into_bytes()).await.unwrap();
+                time::sleep(time::Duration::from_millis(2)).await;
+            }
+        }));
+    }
+    for task in tasks {
+        task.await.unwrap();
+    }
+
+    let value = cf.get(b"row1", b"counter").await.unwrap().unwrap();
+    assert_eq!(value, b"10");
+
+    // A different row is not blocked by a held lock.
+    let _held = cf.lock_row(b"row1").await;
+    let other = time::timeout(Duration::from_millis(100), cf.lock_row(b"row2")).await;
+    assert!(other.is_ok());
+
+    drop(dir);
+}
+
+#[tokio::test]
+async fn test_check_and_put_and_increment_race() {
+    let (dir, table_path) = temp_table_dir();
+
+    let table = Table::open(&table_path).await.unwrap();
+
+    table.create_cf("test_cf").await.unwrap();
+
+    let cf = table.cf("test_cf").await.unwrap();
+
+    // Two tasks race to claim the same cell; exactly one wins.
+    let mut tasks = Vec::new();
+    for owner in ["task1", "task2"] {
+        let cf = cf.clone();
+        tasks.push(tokio::spawn(async move {
+            cf.check_and_put(b"row1".to_vec(), b"owner".to_vec(), None, owner.as_bytes().to_vec()).await.unwrap()
+        }));
+    }
+    let mut winners = Vec::new();
+    for (owner, task) in ["task1", "task2"].into_iter().zip(tasks) {
+        if task.await.unwrap() {
+            winners.push(owner);
+        }
+    }
+    assert_eq!(winners.len(), 1);
+    let value = cf.get(b"row1", b"owner").await.unwrap().unwrap();
+    assert_eq!(value, winners[0].as_bytes());
+
+    assert!(!cf.check_and_put(b"row1".to_vec(), b"owner".to_vec(), Some(b"nobody"), b"x".to_vec()).await.unwrap());
+    assert!(cf.check_and_put(b"row1".to_vec(), b"owner".to_vec(), Some(winners[0].as_bytes()), b"x".to_vec()).await.unwrap());
+
+    // Concurrent increments never lose an update.
+    let mut tasks = Vec::new();
+    for _ in 0..4 {
+        let cf = cf.clone();
+        tasks.push(tokio::spawn(async move {
+            for _ in 0..5 {
+                cf.increment(b"row1".to_vec(), b"hits".to_vec(), 1).await.unwrap();
+            }
+        }));
+    }
+    for task in tasks {
+        task.await.unwrap();
+    }
+    assert_eq!(cf.get_counter(b"row1", b"hits").await.unwrap(), Some(20));
+
+    drop(dir);
+}
+
+#[tokio::test]
+async fn test_full_scan_stream() {
+    let (dir, table_path) = temp_table_dir();
+
+    let table = Table::open(&table_path).await.unwrap();
+    table.create_cf("test_cf").await.unwrap();
+    let cf = table.cf("test_cf").await.unwrap();
+
+    let mut expected = BTreeMap::new();
+    for i in 0..600 {
+        let row = format!("row{:04}", i).into_bytes();
+        let mut put = Put::new(row.clone());
+        put.add_column(b"a".to_vec(), format!("a{}", i).into_bytes());
+        put.add_column(b"b".to_vec(), format!("b{}", i).into_bytes());
+        cf.execute_put(put).await.unwrap();
+        expected.insert((row.clone(), b"a".to_vec()), format!("a{}", i).into_bytes());
+        expected.insert((row, b"b".to_vec()), format!("b{}", i).into_bytes());
+        if i == 299 {
+            cf.flush().await.unwrap();
+        }
+    }
+    time::sleep(time::Duration::from_millis(10)).await;
+
+    // Overwrite some cells and delete every cell of a span of rows longer than one batch.
+    for i in 0..50 {
+        let row = format!("row{:04}", i).into_bytes();
+        cf.put(row.clone(), b"a".to_vec(), b"updated".to_vec()).await.unwrap();
+        expected.insert((row, b"a".to_vec()), b"updated".to_vec());
+    }
+    cf.flush().await.unwrap();
+    for i in 200..500 {
+        let row = format!("row{:04}", i).into_bytes();
+        for column in [b"a".to_vec(), b"b".to_vec()] {
+            cf.delete(row.clone(), column.clone()).await.unwrap();
+            expected.remove(&(row.clone(), column));
+        }
+    }
+
+    let cells: Vec<_> = cf.full_scan_stream()
+        .map(|cell| cell.unwrap())
+        .collect()
+        .await;
+
+    assert!(cells.iter().all(|(_, _, ts, _)| *ts > 0));
+    let scanned: Vec<_> = cells.into_iter()
+        .map(|(row, column, _, value)| ((row, column), value))
+        .collect();
+    assert_eq!(scanned, expected.into_iter().collect::<Vec<_>>());
+
+    drop(dir);
+}
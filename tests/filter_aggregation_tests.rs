@@ -5,9 +5,9 @@ use std::{
     time::Duration,
 };
 use tempfile::tempdir;
-use RedBase::api::{Table, ColumnFamily};
-use RedBase::filter::{Filter, FilterSet, ColumnFilter};
-use RedBase::aggregation::{AggregationType, AggregationSet, AggregationResult};
+use RedBase::api::{Table, ColumnFamily, ScanOrder, ScanRecord};
+use RedBase::filter::{Filter, FilterError, FilterSet, ColumnFilter};
+use RedBase::aggregation::{AggregationType, AggregationSet, AggregationResult, ValueEncoding};
 
 fn temp_table_dir() -> (tempfile::TempDir, PathBuf) {
     let dir = tempdir().unwrap();
@@ -66,6 +66,99 @@ fn test_filter_contains() {
     drop(dir);
 }
 
+#[test]
+fn test_filter_starts_with_ends_with() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    cf.put(b"row1".to_vec(), b"owner".to_vec(), b"user:123".to_vec()).unwrap();
+    cf.put(b"row2".to_vec(), b"owner".to_vec(), b"admin:1".to_vec()).unwrap();
+
+    let user = Filter::StartsWith(b"user:".to_vec());
+    assert_eq!(cf.get_with_filter(b"row1", b"owner", &user).unwrap().unwrap(), b"user:123");
+    assert!(cf.get_with_filter(b"row2", b"owner", &user).unwrap().is_none());
+
+    let ends_in_one = Filter::EndsWith(b":1".to_vec());
+    assert!(cf.get_with_filter(b"row1", b"owner", &ends_in_one).unwrap().is_none());
+    assert_eq!(cf.get_with_filter(b"row2", b"owner", &ends_in_one).unwrap().unwrap(), b"admin:1");
+
+    let mut filter_set = FilterSet::new();
+    filter_set.add_column_filter(b"owner".to_vec(), user);
+    let result = cf.scan_with_filter(b"row1", b"row2", &filter_set).unwrap();
+    assert_eq!(result.keys().cloned().collect::<Vec<_>>(), vec![b"row1".to_vec()]);
+
+    drop(dir);
+}
+
+#[test]
+fn test_filter_set_validate() {
+    let mut filter_set = FilterSet::new();
+    filter_set
+        .add_column_filter(b"name".to_vec(), Filter::Regex("^[a-z]+$".to_string()))
+        .add_numeric_column_filter(b"age".to_vec(), Filter::GreaterThan(b"18".to_vec()));
+    assert_eq!(filter_set.validate(), Ok(()));
+
+    filter_set.add_column_filter(
+        b"email".to_vec(),
+        Filter::Not(Box::new(Filter::Regex("([a-z]+".to_string()))),
+    );
+    let err = filter_set.validate().unwrap_err();
+    match &err {
+        FilterError::Column { column, error } => {
+            assert_eq!(column, b"email");
+            assert!(matches!(**error, FilterError::InvalidRegex { ref pattern, .. } if pattern == "([a-z]+"));
+        }
+        other => panic!("Expected a column error, got {:?}", other),
+    }
+    let message = err.to_string();
+    assert!(message.contains("email") && message.contains("([a-z]+"), "unhelpful message: {}", message);
+
+    let mut filter_set = FilterSet::new();
+    filter_set.add_numeric_column_filter(b"age".to_vec(), Filter::Between(b"18".to_vec(), b"old".to_vec()));
+    assert!(matches!(
+        filter_set.validate(),
+        Err(FilterError::Column { error, .. }) if *error == FilterError::InvalidNumericOperand(b"old".to_vec())
+    ));
+}
+
+#[test]
+fn test_filter_combinators() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    cf.put(b"row1".to_vec(), b"col1".to_vec(), b"apple".to_vec()).unwrap();
+    cf.put(b"row2".to_vec(), b"col1".to_vec(), b"banana".to_vec()).unwrap();
+    cf.put(b"row3".to_vec(), b"col1".to_vec(), b"cherry".to_vec()).unwrap();
+
+    let a_not_b = Filter::And(vec![
+        Filter::Contains(b"a".to_vec()),
+        Filter::Not(Box::new(Filter::Contains(b"b".to_vec()))),
+    ]);
+    assert_eq!(cf.get_with_filter(b"row1", b"col1", &a_not_b).unwrap().unwrap(), b"apple");
+    assert!(cf.get_with_filter(b"row2", b"col1", &a_not_b).unwrap().is_none());
+    assert!(cf.get_with_filter(b"row3", b"col1", &a_not_b).unwrap().is_none());
+
+    let either = Filter::Or(vec![
+        Filter::StartsWith(b"ch".to_vec()),
+        a_not_b,
+    ]);
+    assert!(cf.get_with_filter(b"row1", b"col1", &either).unwrap().is_some());
+    assert!(cf.get_with_filter(b"row2", b"col1", &either).unwrap().is_none());
+    assert!(cf.get_with_filter(b"row3", b"col1", &either).unwrap().is_some());
+
+    // The empty And matches everything, the empty Or nothing.
+    assert!(Filter::And(vec![]).matches(b"anything"));
+    assert!(!Filter::Or(vec![]).matches(b"anything"));
+
+    drop(dir);
+}
+
 #[test]
 fn test_filter_set() {
     let (dir, table_path) = temp_table_dir();
@@ -103,6 +196,273 @@ fn test_filter_set() {
     drop(dir);
 }
 
+#[test]
+fn test_filter_set_max_rows() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    // row1 and row3 don't match the filter and must not use up the limit.
+    for (row, status) in [("row1", "closed"), ("row2", "open"), ("row3", "closed"),
+                          ("row4", "open"), ("row5", "open"), ("row6", "open"), ("row7", "open")] {
+        cf.put(row.as_bytes().to_vec(), b"status".to_vec(), status.as_bytes().to_vec()).unwrap();
+    }
+
+    let mut filter_set = FilterSet::new();
+    filter_set.add_column_filter(b"status".to_vec(), Filter::Equal(b"open".to_vec()));
+    assert_eq!(cf.scan_with_filter(b"row1", b"row9", &filter_set).unwrap().len(), 5);
+
+    filter_set.with_max_rows(2);
+    let result = cf.scan_with_filter(b"row1", b"row9", &filter_set).unwrap();
+    assert_eq!(result.into_keys().collect::<Vec<_>>(), vec![b"row2".to_vec(), b"row4".to_vec()]);
+
+    let descending = cf.scan_ordered(b"row1", b"row9", &filter_set, ScanOrder::Descending).unwrap();
+    assert_eq!(descending.into_iter().map(|(row, _)| row).collect::<Vec<_>>(), vec![b"row7".to_vec(), b"row6".to_vec()]);
+
+    let mut buf = Vec::new();
+    assert_eq!(cf.scan_to_writer(b"row1", b"row9", &filter_set, &mut buf).unwrap(), 2);
+
+    drop(dir);
+}
+
+#[test]
+fn test_filter_set_first_version_only() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    for value in ["v1", "v2", "v3"] {
+        cf.put(b"row1".to_vec(), b"col1".to_vec(), value.as_bytes().to_vec()).unwrap();
+        cf.put(b"row1".to_vec(), b"col2".to_vec(), value.as_bytes().to_vec()).unwrap();
+        thread::sleep(Duration::from_millis(10));
+    }
+    cf.delete(b"row1".to_vec(), b"col2".to_vec()).unwrap();
+
+    let mut filter_set = FilterSet::new();
+    filter_set.with_max_versions(10).with_first_version_only();
+    let result = cf.scan_row_with_filter(b"row1", &filter_set).unwrap();
+
+    // col2's newest version is a delete, so its older puts must not show through.
+    assert_eq!(result.len(), 1);
+    let versions = &result[&b"col1".to_vec()];
+    assert_eq!(versions.len(), 1);
+    assert_eq!(versions[0].1, b"v3".to_vec());
+
+    drop(dir);
+}
+
+#[test]
+fn test_scan_row_with_filter_single_version() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    for i in 0..30 {
+        cf.put(b"row1".to_vec(), b"hot".to_vec(), format!("v{}", i).into_bytes()).unwrap();
+        cf.put(b"row1".to_vec(), b"deleted".to_vec(), format!("d{}", i).into_bytes()).unwrap();
+        if i % 10 == 9 {
+            cf.flush().unwrap();
+        }
+        thread::sleep(Duration::from_millis(2));
+    }
+    cf.delete(b"row1".to_vec(), b"deleted".to_vec()).unwrap();
+
+    let mut filter_set = FilterSet::new();
+    filter_set.with_max_versions(1);
+    let latest = cf.scan_row_with_filter(b"row1", &filter_set).unwrap();
+
+    // Same answer as taking the newest entry of every column's full history.
+    let full = cf.scan_row_versions(b"row1", usize::MAX).unwrap();
+    let expected: BTreeMap<_, _> = full.into_iter()
+        .map(|(column, versions)| (column, versions[..1].to_vec()))
+        .collect();
+    assert_eq!(latest, expected);
+    assert_eq!(latest[b"hot".as_slice()][0].1, b"v29".to_vec());
+    // The tombstone is skipped, as before, leaving the newest put under it.
+    assert_eq!(latest[b"deleted".as_slice()][0].1, b"d29".to_vec());
+
+    drop(dir);
+}
+
+#[test]
+fn test_filter_set_keys_only() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    for row in ["row1", "row2"] {
+        cf.put(row.as_bytes().to_vec(), b"col1".to_vec(), b"first".to_vec()).unwrap();
+        cf.put(row.as_bytes().to_vec(), b"col2".to_vec(), b"other".to_vec()).unwrap();
+    }
+    cf.flush().unwrap();
+    thread::sleep(Duration::from_millis(10));
+    cf.put(b"row1".to_vec(), b"col1".to_vec(), b"second".to_vec()).unwrap();
+
+    let mut filter_set = FilterSet::new();
+    let full = cf.scan_with_filter(b"row1", b"row2", &filter_set).unwrap();
+    filter_set.with_keys_only();
+    let keys = cf.scan_with_filter(b"row1", b"row2", &filter_set).unwrap();
+
+    assert_eq!(keys.len(), 2);
+    assert_eq!(full.keys().collect::<Vec<_>>(), keys.keys().collect::<Vec<_>>());
+    for (row, columns) in &full {
+        assert_eq!(columns.keys().collect::<Vec<_>>(), keys[row].keys().collect::<Vec<_>>());
+        for (column, versions) in columns {
+            let stripped = &keys[row][column];
+            assert_eq!(versions.iter().map(|(ts, _)| *ts).collect::<Vec<_>>(), stripped.iter().map(|(ts, _)| *ts).collect::<Vec<_>>());
+            assert!(stripped.iter().all(|(_, value)| value.is_empty()));
+        }
+    }
+    assert_eq!(keys[&b"row1".to_vec()][&b"col1".to_vec()].len(), 2);
+
+    // Value filters still match against the real values.
+    filter_set.add_column_filter(b"col1".to_vec(), Filter::Equal(b"second".to_vec()));
+    let result = cf.scan_row_with_filter(b"row1", &filter_set).unwrap();
+    assert_eq!(result[&b"col1".to_vec()].len(), 1);
+    assert!(result[&b"col1".to_vec()][0].1.is_empty());
+
+    drop(dir);
+}
+
+#[test]
+fn test_aggregate_ignores_keys_only() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    for value in ["10", "20", "30"] {
+        cf.put(b"row1".to_vec(), b"score".to_vec(), value.as_bytes().to_vec()).unwrap();
+        thread::sleep(Duration::from_millis(2));
+    }
+
+    let mut filter_set = FilterSet::new();
+    filter_set.with_keys_only();
+    let mut agg_set = AggregationSet::new();
+    agg_set.add_aggregation(b"score".to_vec(), AggregationType::Sum);
+
+    let result = cf.aggregate(b"row1", Some(&filter_set), &agg_set).unwrap();
+    assert_eq!(result[b"score".as_slice()].to_string(), "60");
+    let result = cf.aggregate_range(b"row1", b"row1", Some(&filter_set), &agg_set).unwrap();
+    assert_eq!(result[b"row1".as_slice()][b"score".as_slice()].to_string(), "60");
+
+    drop(dir);
+}
+
+#[test]
+fn test_aggregations_honour_first_version_only() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    for value in ["10", "20", "30"] {
+        cf.put(b"t1:a".to_vec(), b"score".to_vec(), value.as_bytes().to_vec()).unwrap();
+        thread::sleep(Duration::from_millis(2));
+    }
+
+    let mut filter_set = FilterSet::new();
+    filter_set.with_first_version_only();
+    let mut agg_set = AggregationSet::new();
+    agg_set.add_aggregation(b"score".to_vec(), AggregationType::Sum);
+
+    // Every aggregation path sees only the newest version.
+    let result = cf.aggregate(b"t1:a", Some(&filter_set), &agg_set).unwrap();
+    assert_eq!(result[b"score".as_slice()].to_string(), "30");
+    let result = cf.aggregate_range(b"t1", b"t9", Some(&filter_set), &agg_set).unwrap();
+    assert_eq!(result[b"t1:a".as_slice()][b"score".as_slice()].to_string(), "30");
+    let result = cf.aggregate_grouped(b"t1", b"t9", 2, Some(&filter_set), &agg_set).unwrap();
+    assert_eq!(result[b"t1".as_slice()][b"score".as_slice()].to_string(), "30");
+
+    drop(dir);
+}
+
+#[test]
+fn test_filter_set_row_prefix() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    for row in ["a", "a:1", "a:2", "a:3", "ab", "b:1"] {
+        cf.put(row.as_bytes().to_vec(), b"col1".to_vec(), b"1".to_vec()).unwrap();
+    }
+    cf.flush().unwrap();
+    cf.put(b"a:4".to_vec(), b"col1".to_vec(), b"1".to_vec()).unwrap();
+
+    let mut filter_set = FilterSet::new();
+    filter_set.with_row_prefix(b"a:".to_vec());
+    let rows = |start: &[u8], end: &[u8]| -> Vec<Vec<u8>> {
+        cf.scan_with_filter(start, end, &filter_set).unwrap().into_keys().collect()
+    };
+
+    assert_eq!(rows(b"", b"z"), vec![b"a:1".to_vec(), b"a:2".to_vec(), b"a:3".to_vec(), b"a:4".to_vec()]);
+    // The prefix narrows an explicit range rather than replacing it.
+    assert_eq!(rows(b"a:2", b"a:3"), vec![b"a:2".to_vec(), b"a:3".to_vec()]);
+    assert!(rows(b"b", b"z").is_empty());
+
+    let ordered = cf.scan_ordered(b"", b"z", &filter_set, ScanOrder::Descending).unwrap();
+    assert_eq!(ordered.first().unwrap().0, b"a:4");
+    assert_eq!(ordered.len(), 4);
+
+    let mut agg_set = AggregationSet::new();
+    agg_set.add_aggregation(b"col1".to_vec(), AggregationType::Count);
+    let result = cf.aggregate_range(b"", b"z", Some(&filter_set), &agg_set).unwrap();
+    assert_eq!(result.len(), 4);
+
+    drop(dir);
+}
+
+#[test]
+fn test_scan_with_filter_finds_rows_only_in_sstables() {
+    let (dir, table_path) = temp_table_dir();
+
+    {
+        let mut table = Table::open(&table_path).unwrap();
+        table.create_cf("test_cf").unwrap();
+        let cf = table.cf("test_cf").unwrap();
+        for i in 0..10 {
+            cf.put(format!("row{}", i).into_bytes(), b"col1".to_vec(), format!("value{}", i).into_bytes()).unwrap();
+            if i % 3 == 2 {
+                cf.flush().unwrap();
+            }
+        }
+        cf.flush().unwrap();
+        cf.close();
+    }
+
+    // After reopening, the MemStore is empty and every row is on disk.
+    let table = Table::open(&table_path).unwrap();
+    let cf = table.cf("test_cf").unwrap();
+    let result = cf.scan_with_filter(b"row0", b"row9", &FilterSet::match_all()).unwrap();
+    assert_eq!(result.len(), 10);
+    for i in 0..10 {
+        let row = format!("row{}", i).into_bytes();
+        assert_eq!(result[&row][&b"col1".to_vec()][0].1, format!("value{}", i).into_bytes());
+    }
+    let result = cf.scan_with_filter(b"row3", b"row5", &FilterSet::match_all()).unwrap();
+    assert_eq!(result.keys().cloned().collect::<Vec<_>>(), vec![b"row3".to_vec(), b"row4".to_vec(), b"row5".to_vec()]);
+
+    // A damaged SSTable fails the scan with an error that names it.
+    let damaged = cf.sstables().unwrap()[1].path.clone();
+    std::fs::write(&damaged, b"not an sstable").unwrap();
+    let err = cf.scan_with_filter(b"row0", b"row9", &FilterSet::match_all()).unwrap_err();
+    assert!(err.to_string().contains(&damaged.display().to_string()), "{}", err);
+
+    drop(dir);
+}
+
 #[test]
 fn test_aggregation_count() {
     let (dir, table_path) = temp_table_dir();
@@ -136,6 +496,41 @@ fn test_aggregation_count() {
     drop(dir);
 }
 
+#[test]
+fn test_aggregation_apply_all() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    for i in 1..=3 {
+        for (col, versions) in [("col_a", 1), ("col_b", 2), ("col_c", 3)] {
+            if i <= versions {
+                cf.put(b"row1".to_vec(), col.as_bytes().to_vec(), format!("{}", i * 10).into_bytes()).unwrap();
+            }
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    let mut agg_set = AggregationSet::new();
+    agg_set.apply_all(AggregationType::Count);
+    let result = cf.aggregate(b"row1", None, &agg_set).unwrap();
+    assert_eq!(result.len(), 3);
+    assert!(matches!(result.get(b"col_a".as_slice()), Some(AggregationResult::Count(1))));
+    assert!(matches!(result.get(b"col_b".as_slice()), Some(AggregationResult::Count(2))));
+    assert!(matches!(result.get(b"col_c".as_slice()), Some(AggregationResult::Count(3))));
+
+    // A column with its own aggregation keeps it.
+    agg_set.add_aggregation(b"col_c".to_vec(), AggregationType::Sum);
+    let result = cf.aggregate(b"row1", None, &agg_set).unwrap();
+    assert_eq!(result.len(), 3);
+    assert!(matches!(result.get(b"col_c".as_slice()), Some(AggregationResult::Sum(60))));
+    assert!(matches!(result.get(b"col_b".as_slice()), Some(AggregationResult::Count(2))));
+
+    drop(dir);
+}
+
 #[test]
 fn test_aggregation_sum() {
     let (dir, table_path) = temp_table_dir();
@@ -177,6 +572,87 @@ fn test_aggregation_sum() {
     drop(dir);
 }
 
+#[test]
+fn test_aggregation_sum_encoded_values() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    for value in [40i64, -5, 7] {
+        cf.put(b"row1".to_vec(), b"counter".to_vec(), value.to_be_bytes().to_vec()).unwrap();
+        thread::sleep(Duration::from_millis(10));
+    }
+    for value in [1.5f64, 2.25] {
+        cf.put(b"row1".to_vec(), b"ratio".to_vec(), value.to_le_bytes().to_vec()).unwrap();
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    let mut agg_set = AggregationSet::new();
+    agg_set.add_encoded_aggregation(b"counter".to_vec(), AggregationType::Sum, ValueEncoding::BeI64);
+    agg_set.add_encoded_aggregation(b"ratio".to_vec(), AggregationType::Average, ValueEncoding::LeF64);
+
+    let result = cf.aggregate(b"row1", None, &agg_set).unwrap();
+
+    match result.get(b"counter".as_slice()) {
+        Some(AggregationResult::Sum(sum)) => assert_eq!(*sum, 42),
+        other => panic!("Expected Sum aggregation result for counter, got {:?}", other),
+    }
+    match result.get(b"ratio".as_slice()) {
+        Some(AggregationResult::Average(avg)) => assert!((avg - 1.875).abs() < 1e-9),
+        other => panic!("Expected Average aggregation result for ratio, got {:?}", other),
+    }
+
+    // The same bytes read as UTF-8 text are not numbers.
+    let mut utf8_set = AggregationSet::new();
+    utf8_set.add_aggregation(b"counter".to_vec(), AggregationType::Sum);
+    let result = cf.aggregate(b"row1", None, &utf8_set).unwrap();
+    assert!(matches!(result.get(b"counter".as_slice()), Some(AggregationResult::Error(_))));
+
+    drop(dir);
+}
+
+#[test]
+fn test_aggregation_sum_mixed_int_and_float() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    cf.put(b"row1".to_vec(), b"mixed".to_vec(), b"1".to_vec()).unwrap();
+    thread::sleep(Duration::from_millis(10));
+    cf.put(b"row1".to_vec(), b"mixed".to_vec(), b"2.5".to_vec()).unwrap();
+    cf.put(b"row1".to_vec(), b"ints".to_vec(), b"3".to_vec()).unwrap();
+
+    let mut agg_set = AggregationSet::new();
+    agg_set.add_aggregation(b"mixed".to_vec(), AggregationType::Sum);
+    agg_set.add_aggregation(b"ints".to_vec(), AggregationType::Sum);
+
+    let result = cf.aggregate(b"row1", None, &agg_set).unwrap();
+
+    // The integer part is not lost once a float shows up.
+    assert!(matches!(result[b"mixed".as_slice()], AggregationResult::SumFloat(_)));
+    assert_eq!(result[b"mixed".as_slice()].to_f64(), Some(3.5));
+    // All-integer sums keep their own variant but read the same way.
+    assert!(matches!(result[b"ints".as_slice()], AggregationResult::Sum(3)));
+    assert_eq!(result[b"ints".as_slice()].to_f64(), Some(3.0));
+    assert_eq!(AggregationResult::Min(b"1".to_vec()).to_f64(), None);
+
+    // Integers before and after the first float all count.
+    for value in ["10", "2.5", "3"] {
+        cf.put(b"row2".to_vec(), b"mixed".to_vec(), value.as_bytes().to_vec()).unwrap();
+        thread::sleep(Duration::from_millis(10));
+    }
+    let mut agg_set = AggregationSet::new();
+    agg_set.add_aggregation(b"mixed".to_vec(), AggregationType::Sum);
+    let result = cf.aggregate(b"row2", None, &agg_set).unwrap();
+    assert!(matches!(result[b"mixed".as_slice()], AggregationResult::SumFloat(sum) if sum == 15.5));
+
+    drop(dir);
+}
+
 #[test]
 fn test_aggregation_average() {
     let (dir, table_path) = temp_table_dir();
@@ -285,6 +761,262 @@ fn test_aggregation_min_max() {
     drop(dir);
 }
 
+#[test]
+fn test_aggregation_range() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    for value in ["10", "3", "27", "15"] {
+        cf.put(b"sensor1".to_vec(), b"temp".to_vec(), value.as_bytes().to_vec()).unwrap();
+        thread::sleep(Duration::from_millis(10));
+    }
+    cf.put(b"sensor1".to_vec(), b"label".to_vec(), b"north".to_vec()).unwrap();
+
+    let mut agg_set = AggregationSet::new();
+    agg_set.add_aggregation(b"temp".to_vec(), AggregationType::Range);
+
+    let result = cf.aggregate(b"sensor1", None, &agg_set).unwrap();
+    match result.get(b"temp".as_slice()) {
+        Some(AggregationResult::Range(range)) => assert_eq!(*range, 24.0),
+        other => panic!("Expected Range aggregation result for temp, got {:?}", other),
+    }
+
+    let mut label_set = AggregationSet::new();
+    label_set.add_aggregation(b"label".to_vec(), AggregationType::Range);
+    let result = cf.aggregate(b"sensor1", None, &label_set).unwrap();
+    assert!(matches!(result.get(b"label".as_slice()), Some(AggregationResult::Error(_))));
+
+    drop(dir);
+}
+
+#[test]
+fn test_aggregation_min_max_numeric() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    for value in ["10", "3", "27", "9.5"] {
+        cf.put(b"sensor1".to_vec(), b"temp".to_vec(), value.as_bytes().to_vec()).unwrap();
+        thread::sleep(Duration::from_millis(10));
+    }
+    for count in [-4i64, 300, 12] {
+        cf.put(b"sensor1".to_vec(), b"hits".to_vec(), count.to_be_bytes().to_vec()).unwrap();
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    let mut agg_set = AggregationSet::new();
+    agg_set.add_aggregation(b"temp".to_vec(), AggregationType::MaxNumeric);
+    agg_set.add_encoded_aggregation(b"hits".to_vec(), AggregationType::MinNumeric, ValueEncoding::BeI64);
+
+    let result = cf.aggregate(b"sensor1", None, &agg_set).unwrap();
+    // A byte-wise Max would pick "9.5".
+    match result.get(b"temp".as_slice()) {
+        Some(AggregationResult::MaxNumeric { value, raw }) => {
+            assert_eq!(*value, 27.0);
+            assert_eq!(raw, b"27");
+        }
+        other => panic!("Expected MaxNumeric aggregation result for temp, got {:?}", other),
+    }
+    match result.get(b"hits".as_slice()) {
+        Some(AggregationResult::MinNumeric { value, raw }) => {
+            assert_eq!(*value, -4.0);
+            assert_eq!(raw, &(-4i64).to_be_bytes().to_vec());
+        }
+        other => panic!("Expected MinNumeric aggregation result for hits, got {:?}", other),
+    }
+    assert_eq!(result[b"temp".as_slice()].to_f64(), Some(27.0));
+
+    drop(dir);
+}
+
+#[test]
+fn test_aggregation_percentile() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    for value in ["40", "10", "30", "20"] {
+        cf.put(b"sensor1".to_vec(), b"latency".to_vec(), value.as_bytes().to_vec()).unwrap();
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    let aggregate = |aggregation_type: AggregationType| {
+        let mut agg_set = AggregationSet::new();
+        agg_set.add_aggregation(b"latency".to_vec(), aggregation_type);
+        cf.aggregate(b"sensor1", None, &agg_set).unwrap().remove(b"latency".as_slice()).unwrap()
+    };
+
+    let median = aggregate(AggregationType::Median).to_f64().unwrap();
+    assert_eq!(median, 25.0);
+    match aggregate(AggregationType::Percentile(0.5)) {
+        AggregationResult::Percentile { p, value } => {
+            assert_eq!(p, 0.5);
+            assert_eq!(value, median);
+        }
+        other => panic!("Expected Percentile aggregation result, got {:?}", other),
+    }
+    assert_eq!(aggregate(AggregationType::Percentile(1.0)).to_f64(), Some(40.0));
+    assert_eq!(aggregate(AggregationType::Percentile(0.0)).to_f64(), Some(10.0));
+    // Rank 0.9 * 3 = 2.7 lies 70% of the way from 30 to 40.
+    assert!((aggregate(AggregationType::Percentile(0.9)).to_f64().unwrap() - 37.0).abs() < 1e-9);
+
+    assert!(matches!(aggregate(AggregationType::Percentile(1.5)), AggregationResult::Error(_)));
+    assert!(matches!(aggregate(AggregationType::Percentile(-0.1)), AggregationResult::Error(_)));
+
+    drop(dir);
+}
+
+#[test]
+fn test_aggregation_first_last() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    cf.put(b"sensor1".to_vec(), b"status".to_vec(), b"old".to_vec()).unwrap();
+    thread::sleep(Duration::from_millis(10));
+    cf.put(b"sensor1".to_vec(), b"status".to_vec(), b"new".to_vec()).unwrap();
+
+    let mut agg_set = AggregationSet::new();
+    agg_set.add_aggregation(b"status".to_vec(), AggregationType::First);
+    let result = cf.aggregate(b"sensor1", None, &agg_set).unwrap();
+    match result.get(b"status".as_slice()) {
+        Some(AggregationResult::First(value)) => assert_eq!(value, b"old"),
+        other => panic!("Expected First aggregation result for status, got {:?}", other),
+    }
+
+    let mut agg_set = AggregationSet::new();
+    agg_set.add_aggregation(b"status".to_vec(), AggregationType::Last);
+    let result = cf.aggregate(b"sensor1", None, &agg_set).unwrap();
+    match result.get(b"status".as_slice()) {
+        Some(AggregationResult::Last(value)) => assert_eq!(value, b"new"),
+        other => panic!("Expected Last aggregation result for status, got {:?}", other),
+    }
+
+    // apply goes by timestamp, not by the order the versions are given in.
+    let values = BTreeMap::from([(b"status".to_vec(), vec![(20, b"b".to_vec()), (30, b"c".to_vec()), (10, b"a".to_vec())])]);
+    let mut agg_set = AggregationSet::new();
+    agg_set.add_aggregation(b"status".to_vec(), AggregationType::Last);
+    assert!(matches!(agg_set.apply(&values).get(b"status".as_slice()), Some(AggregationResult::Last(v)) if v == b"c"));
+
+    drop(dir);
+}
+
+#[test]
+fn test_aggregation_median_std_dev() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    for value in ["30", "10", "20"] {
+        cf.put(b"sensor1".to_vec(), b"temp".to_vec(), value.as_bytes().to_vec()).unwrap();
+        cf.put(b"sensor1".to_vec(), b"spread".to_vec(), value.as_bytes().to_vec()).unwrap();
+        thread::sleep(Duration::from_millis(10));
+    }
+    cf.put(b"sensor1".to_vec(), b"label".to_vec(), b"north".to_vec()).unwrap();
+
+    let mut agg_set = AggregationSet::new();
+    agg_set.add_aggregation(b"temp".to_vec(), AggregationType::Median);
+    agg_set.add_aggregation(b"spread".to_vec(), AggregationType::StdDev);
+
+    let result = cf.aggregate(b"sensor1", None, &agg_set).unwrap();
+    match result.get(b"temp".as_slice()) {
+        Some(AggregationResult::Median(median)) => assert_eq!(*median, 20.0),
+        other => panic!("Expected Median aggregation result for temp, got {:?}", other),
+    }
+    // Population standard deviation of 10, 20, 30: sqrt(200 / 3).
+    match result.get(b"spread".as_slice()) {
+        Some(AggregationResult::StdDev(std_dev)) => assert!((std_dev - (200.0f64 / 3.0).sqrt()).abs() < 1e-9),
+        other => panic!("Expected StdDev aggregation result for spread, got {:?}", other),
+    }
+
+    let mut agg_set = AggregationSet::new();
+    agg_set.add_aggregation(b"label".to_vec(), AggregationType::Median);
+    let result = cf.aggregate(b"sensor1", None, &agg_set).unwrap();
+    assert!(matches!(result.get(b"label".as_slice()), Some(AggregationResult::Error(_))));
+
+    drop(dir);
+}
+
+#[test]
+fn test_aggregate_range_with_limit() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    for i in 0..50 {
+        cf.put(format!("row{:02}", i).into_bytes(), b"count".to_vec(), b"1".to_vec()).unwrap();
+    }
+
+    let mut agg_set = AggregationSet::new();
+    agg_set.add_aggregation(b"count".to_vec(), AggregationType::Count);
+
+    let limited = cf.aggregate_range_with_limit(b"row00", b"row99", None, &agg_set, Some(5)).unwrap();
+    assert!(limited.truncated);
+    assert_eq!(
+        limited.groups.keys().cloned().collect::<Vec<_>>(),
+        (0..5).map(|i| format!("row{:02}", i).into_bytes()).collect::<Vec<_>>()
+    );
+
+    let exact = cf.aggregate_range_with_limit(b"row00", b"row99", None, &agg_set, Some(50)).unwrap();
+    assert!(!exact.truncated);
+    assert_eq!(exact.groups.len(), 50);
+
+    let unlimited = cf.aggregate_range_with_limit(b"row00", b"row99", None, &agg_set, None).unwrap();
+    assert!(!unlimited.truncated);
+    assert_eq!(unlimited.groups.len(), cf.aggregate_range(b"row00", b"row99", None, &agg_set).unwrap().len());
+
+    drop(dir);
+}
+
+#[test]
+fn test_aggregate_grouped() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    cf.put(b"t1:a".to_vec(), b"bytes".to_vec(), b"10".to_vec()).unwrap();
+    cf.put(b"t1:b".to_vec(), b"bytes".to_vec(), b"5".to_vec()).unwrap();
+    cf.flush().unwrap();
+    cf.put(b"t2:a".to_vec(), b"bytes".to_vec(), b"7".to_vec()).unwrap();
+    cf.put(b"t2:a".to_vec(), b"region".to_vec(), b"eu".to_vec()).unwrap();
+
+    let mut agg_set = AggregationSet::new();
+    agg_set.add_aggregation(b"bytes".to_vec(), AggregationType::Sum);
+    agg_set.add_aggregation(b"region".to_vec(), AggregationType::Count);
+
+    let result = cf.aggregate_grouped(b"t1", b"t9", 2, None, &agg_set).unwrap();
+    assert_eq!(result.keys().cloned().collect::<Vec<_>>(), vec![b"t1".to_vec(), b"t2".to_vec()]);
+    assert!(matches!(result[b"t1".as_slice()][b"bytes".as_slice()], AggregationResult::Sum(15)));
+    assert!(matches!(result[b"t2".as_slice()][b"bytes".as_slice()], AggregationResult::Sum(7)));
+    assert!(matches!(result[b"t2".as_slice()][b"region".as_slice()], AggregationResult::Count(1)));
+
+    // The filter set still applies to each row before it joins its group.
+    let mut filter_set = FilterSet::new();
+    filter_set.add_numeric_column_filter(b"bytes".to_vec(), Filter::GreaterThan(b"6".to_vec()));
+    let mut agg_set = AggregationSet::new();
+    agg_set.add_aggregation(b"bytes".to_vec(), AggregationType::Sum);
+    let result = cf.aggregate_grouped(b"t1", b"t9", 2, Some(&filter_set), &agg_set).unwrap();
+    assert!(matches!(result[b"t1".as_slice()][b"bytes".as_slice()], AggregationResult::Sum(10)));
+    assert!(matches!(result[b"t2".as_slice()][b"bytes".as_slice()], AggregationResult::Sum(7)));
+
+    drop(dir);
+}
+
 #[test]
 fn test_filter_regex() {
     let (dir, table_path) = temp_table_dir();
@@ -371,3 +1103,353 @@ fn test_filter_and_aggregation() {
 
     drop(dir);
 }
+
+#[test]
+fn test_filter_comparison_boundaries() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    cf.put(b"row1".to_vec(), b"col1".to_vec(), b"apple".to_vec()).unwrap();
+    cf.put(b"row1".to_vec(), b"col2".to_vec(), b"banana".to_vec()).unwrap();
+    cf.put(b"row1".to_vec(), b"col3".to_vec(), b"cherry".to_vec()).unwrap();
+
+    let matching = |filter: &Filter| -> Vec<Vec<u8>> {
+        [b"col1", b"col2", b"col3"].iter()
+            .filter_map(|col| cf.get_with_filter(b"row1", *col, filter).unwrap())
+            .collect()
+    };
+
+    let filter = Filter::GreaterThanOrEqual(b"banana".to_vec());
+    assert_eq!(matching(&filter), vec![b"banana".to_vec(), b"cherry".to_vec()]);
+
+    let filter = Filter::GreaterThan(b"banana".to_vec());
+    assert_eq!(matching(&filter), vec![b"cherry".to_vec()]);
+
+    let filter = Filter::LessThan(b"banana".to_vec());
+    assert_eq!(matching(&filter), vec![b"apple".to_vec()]);
+
+    let filter = Filter::LessThanOrEqual(b"banana".to_vec());
+    assert_eq!(matching(&filter), vec![b"apple".to_vec(), b"banana".to_vec()]);
+
+    let filter = Filter::Between(b"apple".to_vec(), b"banana".to_vec());
+    assert_eq!(matching(&filter), vec![b"apple".to_vec(), b"banana".to_vec()]);
+
+    let filter = Filter::Between(b"b".to_vec(), b"c".to_vec());
+    assert_eq!(matching(&filter), vec![b"banana".to_vec()]);
+
+    let filter = Filter::Between(b"cherry".to_vec(), b"cherry".to_vec());
+    assert_eq!(matching(&filter), vec![b"cherry".to_vec()]);
+
+    drop(dir);
+}
+
+#[test]
+fn test_filter_range() {
+    let inclusive = Filter::Range { lower: b"b".to_vec(), upper: b"d".to_vec(), inclusive: true };
+    let exclusive = Filter::Range { lower: b"b".to_vec(), upper: b"d".to_vec(), inclusive: false };
+    let values: [&[u8]; 6] = [b"a", b"b", b"bz", b"c", b"d", b"da"];
+    let matching = |filter: &Filter| -> Vec<&[u8]> {
+        values.iter().copied().filter(|v| filter.matches(v)).collect()
+    };
+    assert_eq!(matching(&inclusive), vec![b"b".as_slice(), b"bz", b"c", b"d"]);
+    assert_eq!(matching(&exclusive), vec![b"bz".as_slice(), b"c"]);
+
+    // Equal bounds: exactly that value when inclusive, nothing otherwise.
+    let point = |inclusive| Filter::Range { lower: b"c".to_vec(), upper: b"c".to_vec(), inclusive };
+    assert_eq!(matching(&point(true)), vec![b"c".as_slice()]);
+    assert!(matching(&point(false)).is_empty());
+
+    let (dir, table_path) = temp_table_dir();
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+    for (col, value) in [("col1", "apple"), ("col2", "banana"), ("col3", "cherry"), ("col4", "date")] {
+        cf.put(b"row1".to_vec(), col.as_bytes().to_vec(), value.as_bytes().to_vec()).unwrap();
+    }
+
+    let mut filter_set = FilterSet::new();
+    for col in ["col1", "col2", "col3", "col4"] {
+        filter_set.add_column_filter(
+            col.as_bytes().to_vec(),
+            Filter::Range { lower: b"banana".to_vec(), upper: b"date".to_vec(), inclusive: false },
+        );
+    }
+    let result = cf.scan_row_with_filter(b"row1", &filter_set).unwrap();
+    assert_eq!(result.keys().cloned().collect::<Vec<_>>(), vec![b"col3".to_vec()]);
+
+    drop(dir);
+}
+
+#[test]
+fn test_numeric_column_filter() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    cf.put(b"order1".to_vec(), b"amount".to_vec(), b"500".to_vec()).unwrap();
+    cf.put(b"order2".to_vec(), b"amount".to_vec(), b"2000".to_vec()).unwrap();
+    cf.put(b"order3".to_vec(), b"amount".to_vec(), b"15000".to_vec()).unwrap();
+
+    let mut lexicographic = FilterSet::new();
+    lexicographic.add_column_filter(b"amount".to_vec(), Filter::GreaterThan(b"1000".to_vec()));
+    let result = cf.scan_with_filter(b"order1", b"order3", &lexicographic).unwrap();
+    assert_eq!(result.len(), 3, "\"500\" sorts after \"1000\" byte-wise");
+
+    let mut numeric = FilterSet::new();
+    numeric.add_numeric_column_filter(b"amount".to_vec(), Filter::GreaterThan(b"1000".to_vec()));
+    let result = cf.scan_with_filter(b"order1", b"order3", &numeric).unwrap();
+    assert_eq!(result.len(), 2);
+    assert!(result.contains_key(&b"order2".to_vec()));
+    assert!(result.contains_key(&b"order3".to_vec()));
+
+    let column_filter = ColumnFilter {
+        column: b"amount".to_vec(),
+        filter: Filter::Between(b"1000".to_vec(), b"9999".to_vec()),
+        numeric: true,
+    };
+    assert!(!column_filter.matches(b"500"));
+    assert!(column_filter.matches(b"2000"));
+    assert!(!column_filter.matches(b"15000"));
+    assert!(!column_filter.matches(b"not-a-number"));
+
+    drop(dir);
+}
+
+#[test]
+fn test_filter_numeric_comparisons() {
+    let values: [&[u8]; 6] = [b"9", b"50", b"100", b"-3", b" 050 ", b"abc"];
+    let matching = |filter: &Filter| -> Vec<&[u8]> {
+        values.iter().copied().filter(|v| filter.matches(v)).collect()
+    };
+    assert_eq!(matching(&Filter::NumericGreaterThan(50)), vec![b"100".as_slice()]);
+    assert_eq!(matching(&Filter::NumericLessThan(50)), vec![b"9".as_slice(), b"-3"]);
+    assert_eq!(matching(&Filter::NumericEqual(50)), vec![b"50".as_slice(), b" 050 "]);
+    // Byte-wise, "9" sorts after "50".
+    assert!(Filter::GreaterThan(b"50".to_vec()).matches(b"9"));
+
+    let (dir, table_path) = temp_table_dir();
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+    cf.put(b"row1".to_vec(), b"score".to_vec(), b"9".to_vec()).unwrap();
+    thread::sleep(Duration::from_millis(10));
+    cf.put(b"row1".to_vec(), b"score".to_vec(), b"100".to_vec()).unwrap();
+
+    let mut filter_set = FilterSet::new();
+    filter_set.add_column_filter(b"score".to_vec(), Filter::NumericGreaterThan(50));
+    let result = cf.scan_row_with_filter(b"row1", &filter_set).unwrap();
+    let scores = &result[&b"score".to_vec()];
+    assert_eq!(scores.len(), 1);
+    assert_eq!(scores[0].1, b"100");
+
+    drop(dir);
+}
+
+#[test]
+fn test_filter_numeric_between() {
+    let half_open = Filter::NumericBetween { low: 10, high: 20, include_high: false };
+    let inclusive = Filter::NumericBetween { low: 10, high: 20, include_high: true };
+
+    let values: [&[u8]; 7] = [b"9", b"10", b"11", b"19", b"20", b"21", b"100"];
+    let matching = |filter: &Filter| -> Vec<&[u8]> {
+        values.iter().copied().filter(|v| filter.matches(v)).collect()
+    };
+    assert_eq!(matching(&half_open), vec![b"10".as_slice(), b"11", b"19"]);
+    assert_eq!(matching(&inclusive), vec![b"10".as_slice(), b"11", b"19", b"20"]);
+
+    let negative = Filter::NumericBetween { low: -5, high: 0, include_high: false };
+    assert!(negative.matches(b"-5"));
+    assert!(negative.matches(b" -1 "));
+    assert!(!negative.matches(b"0"));
+    assert!(!negative.matches(b"-6"));
+
+    assert!(!inclusive.matches(b"15.5"));
+    assert!(!inclusive.matches(b"fifteen"));
+    assert!(!inclusive.matches(&[0xFF, 0xFE]));
+
+    let (dir, table_path) = temp_table_dir();
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+    cf.put(b"order1".to_vec(), b"amount".to_vec(), b"500".to_vec()).unwrap();
+    cf.put(b"order2".to_vec(), b"amount".to_vec(), b"2000".to_vec()).unwrap();
+    cf.put(b"order3".to_vec(), b"amount".to_vec(), b"15000".to_vec()).unwrap();
+
+    let mut filter_set = FilterSet::new();
+    filter_set.add_column_filter(
+        b"amount".to_vec(),
+        Filter::NumericBetween { low: 500, high: 15000, include_high: false },
+    );
+    let result = cf.scan_with_filter(b"order1", b"order3", &filter_set).unwrap();
+    assert_eq!(result.keys().cloned().collect::<Vec<_>>(), vec![b"order1".to_vec(), b"order2".to_vec()]);
+
+    drop(dir);
+}
+
+#[test]
+fn test_filter_set_partial_configurations() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    cf.put(b"row1".to_vec(), b"col1".to_vec(), b"old1".to_vec()).unwrap();
+    cf.put(b"row1".to_vec(), b"col2".to_vec(), b"old2".to_vec()).unwrap();
+    thread::sleep(Duration::from_millis(20));
+    let cutoff = chrono::Utc::now().timestamp_millis() as u64;
+    thread::sleep(Duration::from_millis(20));
+    cf.put(b"row1".to_vec(), b"col1".to_vec(), b"new1".to_vec()).unwrap();
+
+    // Empty filter set: every column and version.
+    let result = cf.scan_row_with_filter(b"row1", &FilterSet::match_all()).unwrap();
+    assert_eq!(result.len(), 2);
+    assert_eq!(result.get(&b"col1".to_vec()).unwrap().len(), 2);
+    assert_eq!(result.get(&b"col2".to_vec()).unwrap().len(), 1);
+
+    // Timestamp range only: applies to every column, not just filtered ones.
+    let mut recent = FilterSet::new();
+    recent.with_timestamp_range(Some(cutoff), None);
+    let result = cf.scan_row_with_filter(b"row1", &recent).unwrap();
+    assert_eq!(result.len(), 1);
+    let col1 = result.get(&b"col1".to_vec()).unwrap();
+    assert_eq!(col1.len(), 1);
+    assert_eq!(col1[0].1, b"new1");
+
+    let mut older = FilterSet::new();
+    older.with_timestamp_range(None, Some(cutoff));
+    let result = cf.scan_row_with_filter(b"row1", &older).unwrap();
+    assert_eq!(result.len(), 2);
+    assert_eq!(result.get(&b"col1".to_vec()).unwrap()[0].1, b"old1");
+
+    // Column filter only: restricts the columns, keeps every matching version.
+    let mut column_only = FilterSet::new();
+    column_only.add_column_filter(b"col1".to_vec(), Filter::StartsWith(b"new".to_vec()));
+    let result = cf.scan_row_with_filter(b"row1", &column_only).unwrap();
+    assert_eq!(result.len(), 1);
+    assert_eq!(result.get(&b"col1".to_vec()).unwrap().len(), 1);
+
+    drop(dir);
+}
+
+#[test]
+fn test_filter_set_max_columns() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    // Written out of order, so the result has to be sorted by qualifier.
+    for i in (0..10).rev() {
+        cf.put(b"row1".to_vec(), format!("col{}", i).into_bytes(), format!("value{}", i).into_bytes()).unwrap();
+    }
+
+    let mut filter_set = FilterSet::new();
+    filter_set.with_max_columns(3);
+    let (result, truncated) = cf.scan_row_with_filter_limited(b"row1", &filter_set).unwrap();
+    assert!(truncated);
+    assert_eq!(result.keys().cloned().collect::<Vec<_>>(), vec![b"col0".to_vec(), b"col1".to_vec(), b"col2".to_vec()]);
+    assert_eq!(result[&b"col0".to_vec()][0].1, b"value0");
+    assert_eq!(cf.scan_row_with_filter(b"row1", &filter_set).unwrap(), result);
+
+    filter_set.with_max_columns(10);
+    let (result, truncated) = cf.scan_row_with_filter_limited(b"row1", &filter_set).unwrap();
+    assert!(!truncated);
+    assert_eq!(result.len(), 10);
+
+    drop(dir);
+}
+
+#[test]
+fn test_scan_to_writer() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    for i in 1..=5 {
+        let row = format!("row{}", i).into_bytes();
+        cf.put(row.clone(), b"amount".to_vec(), format!("{}", i * 100).into_bytes()).unwrap();
+        cf.put(row, b"note".to_vec(), b"n".to_vec()).unwrap();
+        if i == 2 {
+            cf.flush().unwrap();
+        }
+    }
+    thread::sleep(Duration::from_millis(10));
+    cf.put(b"row4".to_vec(), b"amount".to_vec(), b"450".to_vec()).unwrap();
+
+    let mut filter_set = FilterSet::new();
+    filter_set.add_numeric_column_filter(b"amount".to_vec(), Filter::GreaterThan(b"150".to_vec()));
+
+    let mut buffer = Vec::new();
+    let count = cf.scan_to_writer(b"row1", b"row5", &filter_set, &mut buffer).unwrap();
+
+    let mut reader = buffer.as_slice();
+    let mut records = Vec::new();
+    while let Some(record) = ScanRecord::read_from(&mut reader).unwrap() {
+        records.push(record);
+    }
+    assert_eq!(records.len() as u64, count);
+
+    let expected: Vec<ScanRecord> = cf.scan_with_filter(b"row1", b"row5", &filter_set).unwrap()
+        .into_iter()
+        .flat_map(|(row, columns)| {
+            columns.into_iter().flat_map(move |(column, versions)| {
+                let row = row.clone();
+                versions.into_iter().map(move |(timestamp, value)| ScanRecord {
+                    row: row.clone(),
+                    column: column.clone(),
+                    timestamp,
+                    value,
+                })
+            })
+        })
+        .collect();
+    assert_eq!(records, expected);
+    assert_eq!(records.len(), 5);
+    assert!(records.iter().all(|r| r.column == b"amount"));
+
+    drop(dir);
+}
+
+#[test]
+fn test_scan_ordered() {
+    let (dir, table_path) = temp_table_dir();
+
+    let mut table = Table::open(&table_path).unwrap();
+    table.create_cf("test_cf").unwrap();
+    let cf = table.cf("test_cf").unwrap();
+
+    for i in [3, 1, 5, 2, 4] {
+        let row = format!("row{}", i).into_bytes();
+        cf.put(row, b"amount".to_vec(), format!("{}", i * 100).into_bytes()).unwrap();
+        if i == 5 {
+            cf.flush().unwrap();
+        }
+    }
+
+    let mut filter_set = FilterSet::new();
+    filter_set.add_numeric_column_filter(b"amount".to_vec(), Filter::GreaterThan(b"150".to_vec()));
+
+    let rows = |order| -> Vec<Vec<u8>> {
+        cf.scan_ordered(b"row1", b"row5", &filter_set, order).unwrap()
+            .into_iter()
+            .map(|(row, _)| row)
+            .collect()
+    };
+    assert_eq!(rows(ScanOrder::Descending), vec![b"row5".to_vec(), b"row4".to_vec(), b"row3".to_vec(), b"row2".to_vec()]);
+    assert_eq!(rows(ScanOrder::Ascending), vec![b"row2".to_vec(), b"row3".to_vec(), b"row4".to_vec(), b"row5".to_vec()]);
+
+    let ascending = cf.scan_ordered(b"row1", b"row5", &filter_set, ScanOrder::Ascending).unwrap();
+    let by_row: BTreeMap<_, _> = cf.scan_with_filter(b"row1", b"row5", &filter_set).unwrap();
+    assert_eq!(ascending, by_row.into_iter().collect::<Vec<_>>());
+
+    drop(dir);
+}